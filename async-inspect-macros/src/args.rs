@@ -0,0 +1,279 @@
+//! Parsing of `#[trace(...)]` arguments
+
+use proc_macro2::{TokenStream as TokenStream2, TokenTree};
+use quote::ToTokens;
+use syn::parse::{Parse, ParseStream, Parser};
+use syn::punctuated::Punctuated;
+use syn::{Expr, FnArg, Ident, LitInt, LitStr, Pat, Signature, Token};
+
+/// Accepted `level` values and the `TaskLevel` variant each maps to
+const LEVELS: [(&str, &str); 5] = [
+    ("trace", "Trace"),
+    ("debug", "Debug"),
+    ("info", "Info"),
+    ("warn", "Warn"),
+    ("error", "Error"),
+];
+
+/// Arguments accepted by `#[trace]`
+#[derive(Default)]
+pub(crate) struct TraceArgs {
+    /// Task name, defaulting to the function name
+    pub(crate) name: Option<LitStr>,
+    /// Fields recorded on the task
+    pub(crate) fields: Vec<Field>,
+    /// Function arguments that no field may record
+    pub(crate) skip: Vec<Ident>,
+    /// `TaskLevel` variant, spanned to the `level` value
+    pub(crate) level: Option<Ident>,
+    /// Whether `.await` points are left uninstrumented
+    pub(crate) skip_awaits: bool,
+    /// Record one call in every `sample` calls
+    pub(crate) sample: Option<LitInt>,
+}
+
+/// How a field value is formatted
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FieldFormat {
+    /// `key = value` or `key = ?value`
+    Debug,
+    /// `key = %value`
+    Display,
+}
+
+/// A `key = value` entry of `fields(...)`
+pub(crate) struct Field {
+    pub(crate) key: Ident,
+    pub(crate) format: FieldFormat,
+    pub(crate) value: Expr,
+}
+
+impl Parse for Field {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let key: Ident = input.parse()?;
+        input.parse::<Token![=]>()?;
+
+        let format = if input.parse::<Option<Token![%]>>()?.is_some() {
+            FieldFormat::Display
+        } else {
+            input.parse::<Option<Token![?]>>()?;
+            FieldFormat::Debug
+        };
+
+        Ok(Self {
+            key,
+            format,
+            value: input.parse()?,
+        })
+    }
+}
+
+impl TraceArgs {
+    /// Parse the attribute arguments
+    pub(crate) fn parse(attr: TokenStream2) -> syn::Result<Self> {
+        let mut args = Self::default();
+
+        let parser = syn::meta::parser(|meta| {
+            if meta.path.is_ident("name") {
+                if args.name.is_some() {
+                    return Err(meta.error("duplicate `name` argument"));
+                }
+                args.name = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("fields") {
+                let content;
+                syn::parenthesized!(content in meta.input);
+                let fields = Punctuated::<Field, Token![,]>::parse_terminated(&content)?;
+                for field in fields {
+                    if args.fields.iter().any(|f| f.key == field.key) {
+                        return Err(syn::Error::new(
+                            field.key.span(),
+                            format!("duplicate field `{}`", field.key),
+                        ));
+                    }
+                    args.fields.push(field);
+                }
+            } else if meta.path.is_ident("skip") {
+                let content;
+                syn::parenthesized!(content in meta.input);
+                let idents = Punctuated::<Ident, Token![,]>::parse_terminated(&content)?;
+                args.skip.extend(idents);
+            } else if meta.path.is_ident("skip_awaits") {
+                if args.skip_awaits {
+                    return Err(meta.error("duplicate `skip_awaits` argument"));
+                }
+                args.skip_awaits = true;
+            } else if meta.path.is_ident("sample") {
+                if args.sample.is_some() {
+                    return Err(meta.error("duplicate `sample` argument"));
+                }
+                let sample: LitInt = meta.value()?.parse()?;
+                if sample.base10_parse::<u64>()? == 0 {
+                    return Err(syn::Error::new(
+                        sample.span(),
+                        "`sample` must be at least 1",
+                    ));
+                }
+                args.sample = Some(sample);
+            } else if meta.path.is_ident("level") {
+                if args.level.is_some() {
+                    return Err(meta.error("duplicate `level` argument"));
+                }
+                let level: LitStr = meta.value()?.parse()?;
+                let value = level.value().to_ascii_lowercase();
+                let Some((_, variant)) = LEVELS.iter().find(|(name, _)| *name == value) else {
+                    return Err(syn::Error::new(
+                        level.span(),
+                        "unknown level, expected one of \"trace\", \"debug\", \"info\", \
+                         \"warn\", \"error\"",
+                    ));
+                };
+                args.level = Some(Ident::new(variant, level.span()));
+            } else {
+                return Err(meta.error(
+                    "unknown argument, expected one of `name`, `fields`, `skip`, \
+                     `skip_awaits`, `sample`, `level`",
+                ));
+            }
+            Ok(())
+        });
+
+        parser.parse2(attr)?;
+        Ok(args)
+    }
+
+    /// Check that every skipped name is an argument of the function and
+    /// that no field records one
+    pub(crate) fn validate(&self, sig: &Signature) -> syn::Result<()> {
+        let arg_names: Vec<Ident> = argument_names(sig).collect();
+
+        for skipped in &self.skip {
+            if !arg_names.contains(skipped) {
+                return Err(syn::Error::new(
+                    skipped.span(),
+                    format!("`{skipped}` is not an argument of this function"),
+                ));
+            }
+        }
+
+        for field in &self.fields {
+            if let Some(skipped) = mentioned(field.value.to_token_stream(), &self.skip) {
+                return Err(syn::Error::new(
+                    skipped.span(),
+                    format!("`{skipped}` is skipped and cannot be recorded"),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Arguments bound to a plain identifier
+fn argument_names(sig: &Signature) -> impl Iterator<Item = Ident> + '_ {
+    sig.inputs.iter().filter_map(|arg| match arg {
+        FnArg::Typed(pat_type) => match &*pat_type.pat {
+            Pat::Ident(pat) => Some(pat.ident.clone()),
+            _ => None,
+        },
+        FnArg::Receiver(_) => None,
+    })
+}
+
+/// Find a variable of `names` used in an expression
+///
+/// Identifiers after a `.` are fields or methods, not variables.
+fn mentioned(tokens: TokenStream2, names: &[Ident]) -> Option<Ident> {
+    let mut after_dot = false;
+    for token in tokens {
+        match &token {
+            TokenTree::Ident(ident) if !after_dot && names.contains(ident) => {
+                return Some(ident.clone());
+            }
+            TokenTree::Group(group) => {
+                if let Some(ident) = mentioned(group.stream(), names) {
+                    return Some(ident);
+                }
+            }
+            _ => {}
+        }
+        after_dot = matches!(&token, TokenTree::Punct(punct) if punct.as_char() == '.');
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quote::quote;
+
+    fn error(attr: TokenStream2) -> String {
+        match TraceArgs::parse(attr) {
+            Ok(_) => panic!("expected an error"),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_all_arguments() {
+        let args = TraceArgs::parse(quote! {
+            name = "load_user",
+            fields(user_id = id, region = %cfg.region, tags = ?tags),
+            skip(password),
+            skip_awaits,
+            sample = 10,
+            level = "DEBUG"
+        })
+        .unwrap();
+
+        assert_eq!(args.name.unwrap().value(), "load_user");
+        let keys: Vec<String> = args.fields.iter().map(|f| f.key.to_string()).collect();
+        assert_eq!(keys, ["user_id", "region", "tags"]);
+        assert!(args.fields[1].format == FieldFormat::Display);
+        assert_eq!(args.skip[0], "password");
+        assert!(args.skip_awaits);
+        assert_eq!(args.sample.unwrap().base10_parse::<u64>().unwrap(), 10);
+        assert_eq!(args.level.unwrap(), "Debug");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(error(quote!(target = "db")).starts_with("unknown argument"));
+        assert!(error(quote!(level = "verbose")).starts_with("unknown level"));
+        assert_eq!(
+            error(quote!(level = "info", level = "warn")),
+            "duplicate `level` argument"
+        );
+        assert_eq!(error(quote!(sample = 0)), "`sample` must be at least 1");
+        assert_eq!(
+            error(quote!(name = "a", name = "b")),
+            "duplicate `name` argument"
+        );
+        assert_eq!(error(quote!(fields(a = 1, a = 2))), "duplicate field `a`");
+    }
+
+    #[test]
+    fn test_skip_must_name_an_argument() {
+        let sig: Signature =
+            syn::parse_quote!(async fn login(&self, user: String, password: String));
+
+        let ok = TraceArgs::parse(quote!(
+            skip(password),
+            fields(user = user, n = cfg.password)
+        ))
+        .unwrap();
+        assert!(ok.validate(&sig).is_ok());
+
+        let leaked =
+            TraceArgs::parse(quote!(skip(password), fields(len = password.len()))).unwrap();
+        assert_eq!(
+            leaked.validate(&sig).unwrap_err().to_string(),
+            "`password` is skipped and cannot be recorded"
+        );
+
+        let bad = TraceArgs::parse(quote!(skip(token))).unwrap();
+        assert_eq!(
+            bad.validate(&sig).unwrap_err().to_string(),
+            "`token` is not an argument of this function"
+        );
+    }
+}
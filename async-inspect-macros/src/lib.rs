@@ -3,8 +3,13 @@
 //! This crate provides attribute macros for automatic instrumentation of async functions.
 
 use proc_macro::TokenStream;
-use quote::quote;
-use syn::{parse_macro_input, visit_mut::VisitMut, Expr, ItemFn};
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{quote, quote_spanned};
+use syn::{parse_macro_input, spanned::Spanned, visit_mut::VisitMut, Expr, ItemFn};
+
+mod args;
+
+use args::{FieldFormat, TraceArgs};
 
 /// Attribute macro to automatically instrument async functions
 ///
@@ -23,9 +28,28 @@ use syn::{parse_macro_input, visit_mut::VisitMut, Expr, ItemFn};
 /// - Register the function as a tracked task
 /// - Automatically label each `.await` point
 /// - Track execution time
-/// - Report completion or failure
+/// - Report completion, or failure when the function returns `Err` (using the
+///   error's `Display` output) or panics
+/// - Report cancellation when the future is dropped before it completes
+///
+/// # Arguments
+///
+/// ```rust,ignore
+/// #[async_inspect::trace(
+///     name = "login",                       // Task name instead of the function name
+///     fields(user = user, n = ?n),          // Task fields, `Debug` by default; `%` uses `Display`
+///     skip(password),                       // Arguments no field may record
+///     skip_awaits,                          // Leave `.await` points uninstrumented
+///     sample = 10,                          // Record one call in every 10
+///     level = "debug",                      // Task level: trace, debug, info, warn or error
+/// )]
+/// async fn authenticate(user: String, password: String, cfg: &Config, n: u32) { /* ... */ }
+/// ```
+///
+/// Only the values listed in `fields(...)` are recorded; arguments are never
+/// captured implicitly.
 #[proc_macro_attribute]
-pub fn trace(_attr: TokenStream, item: TokenStream) -> TokenStream {
+pub fn trace(attr: TokenStream, item: TokenStream) -> TokenStream {
     let mut input = parse_macro_input!(item as ItemFn);
 
     // Ensure it's an async function
@@ -38,36 +62,112 @@ pub fn trace(_attr: TokenStream, item: TokenStream) -> TokenStream {
         .into();
     }
 
+    let args = match TraceArgs::parse(attr.into()).and_then(|args| {
+        args.validate(&input.sig)?;
+        Ok(args)
+    }) {
+        Ok(args) => args,
+        Err(err) => return err.to_compile_error().into(),
+    };
+
     let fn_name = &input.sig.ident;
-    let fn_name_str = fn_name.to_string();
+    let task_name = args
+        .name
+        .as_ref()
+        .map_or_else(|| fn_name.to_string(), syn::LitStr::value);
+    let fn_location = source_location(fn_name.span());
     let vis = &input.vis;
     let sig = &input.sig;
 
-    // Instrument the function body
-    let mut instrumenter = AwaitInstrumenter {
-        counter: 0,
-        fn_name: fn_name_str.clone(),
-    };
-    instrumenter.visit_block_mut(&mut input.block);
+    // Fields are formatted before the body runs
+    let fields = args.fields.iter().map(|field| {
+        let key = field.key.to_string();
+        let value = &field.value;
+        match field.format {
+            FieldFormat::Debug => quote! { .with_field(#key, ::std::format!("{:?}", &#value)) },
+            FieldFormat::Display => quote! { .with_field(#key, ::std::format!("{}", &#value)) },
+        }
+    });
+    let level = args.level.as_ref().map(|level| {
+        quote! { .with_level(::async_inspect::task::TaskLevel::#level) }
+    });
 
-    let instrumented_block = &input.block;
+    // Unsampled calls run under a task ID that is never registered, so
+    // their awaits are ignored rather than attributed to the caller's task
+    let sampled = match &args.sample {
+        Some(sample) => quote! {{
+            static __INSPECT_CALLS: ::std::sync::atomic::AtomicU64 =
+                ::std::sync::atomic::AtomicU64::new(0);
+            __INSPECT_CALLS.fetch_add(1, ::std::sync::atomic::Ordering::Relaxed) % #sample == 0
+        }},
+        None => quote! { true },
+    };
 
-    let output = quote! {
-        #vis #sig {
-            // Register this function as a task
-            let __inspect_task_id = ::async_inspect::inspector::Inspector::global()
-                .register_task(#fn_name_str.to_string());
+    // Instrument the function body
+    if !args.skip_awaits {
+        let mut instrumenter = AwaitInstrumenter {
+            counter: 0,
+            fn_name: task_name.clone(),
+        };
+        instrumenter.visit_block_mut(&mut input.block);
+    }
 
-            ::async_inspect::instrument::set_current_task_id(__inspect_task_id);
+    let instrumented_block = &input.block;
 
-            // Execute the original function
-            let __inspect_result = async move #instrumented_block;
+    // The output type must be known before checking it for an error, so it
+    // is spelled out; `impl Trait` outputs cannot be and are never failures
+    let record_outcome = match output_type(sig) {
+        Some(output) => quote! {
+            let __result: #output = __inspect_result.await;
 
+            // Mark task as failed if it returned an error, completed otherwise
+            let __inspect_failure = {
+                #[allow(unused_imports)]
+                use ::async_inspect::instrument::outcome::{AnyOutcome as _, ResultOutcome as _};
+                (&::async_inspect::instrument::outcome::Outcome(&__result)).failure()
+            };
+            match __inspect_failure {
+                ::core::option::Option::Some(error) => {
+                    ::async_inspect::inspector::Inspector::global()
+                        .task_failed(__inspect_task_id, ::core::option::Option::Some(error));
+                }
+                ::core::option::Option::None => {
+                    ::async_inspect::inspector::Inspector::global()
+                        .task_completed(__inspect_task_id);
+                }
+            }
+        },
+        None => quote! {
             let __result = __inspect_result.await;
 
             // Mark task as completed
             ::async_inspect::inspector::Inspector::global().task_completed(__inspect_task_id);
-            ::async_inspect::instrument::clear_current_task_id();
+        },
+    };
+
+    let output = quote! {
+        #vis #sig {
+            // Register this function as a task, nested under the caller's task
+            let __inspect_task_id = if #sampled {
+                ::async_inspect::instrument::register_child_of_current(
+                    ::async_inspect::task::TaskInfo::new(#task_name.to_string())
+                        .with_location(#fn_location.to_string())
+                        #level
+                        #(#fields)*,
+                    ::async_inspect::task::ChildKind::Inline,
+                )
+            } else {
+                ::async_inspect::task::TaskId::new()
+            };
+
+            // Execute the original function, entering the task on every poll.
+            // Panics and drops before completion are recorded by the wrapper.
+            let __inspect_result = ::async_inspect::instrument::Traced::new(
+                __inspect_task_id,
+                async move #instrumented_block,
+            );
+
+            #record_outcome
 
             __result
         }
@@ -76,6 +176,35 @@ pub fn trace(_attr: TokenStream, item: TokenStream) -> TokenStream {
     output.into()
 }
 
+/// Get the output type of a function, unless it is or contains `impl Trait`
+fn output_type(sig: &syn::Signature) -> Option<TokenStream2> {
+    fn contains_impl(tokens: TokenStream2) -> bool {
+        tokens.into_iter().any(|token| match token {
+            proc_macro2::TokenTree::Ident(ident) => ident == "impl",
+            proc_macro2::TokenTree::Group(group) => contains_impl(group.stream()),
+            _ => false,
+        })
+    }
+
+    match &sig.output {
+        syn::ReturnType::Default => Some(quote! { () }),
+        syn::ReturnType::Type(_, ty) => {
+            let ty = quote! { #ty };
+            (!contains_impl(ty.clone())).then_some(ty)
+        }
+    }
+}
+
+/// Expand to a `"file:line:column"` string literal for the given span
+///
+/// The location macros take the span they are invoked with, so spanning them
+/// to the user's tokens resolves to the user's source rather than this crate.
+fn source_location(span: Span) -> TokenStream2 {
+    quote_spanned! {span=>
+        ::core::concat!(::core::file!(), ":", ::core::line!(), ":", ::core::column!())
+    }
+}
+
 /// Visitor that instruments `.await` expressions
 struct AwaitInstrumenter {
     counter: usize,
@@ -93,8 +222,8 @@ impl VisitMut for AwaitInstrumenter {
             self.counter += 1;
             let label = format!("{}::await#{}", self.fn_name, self.counter);
 
-            // Get the source location
-            let location = format!("{}:{}", file!(), line!());
+            // Get the source location of the `.await` in the user's code
+            let location = source_location(await_expr.await_token.span());
 
             // Wrap the await with inspection
             // Clone the base to avoid borrow issues
@@ -102,9 +231,9 @@ impl VisitMut for AwaitInstrumenter {
 
             *expr = syn::parse_quote! {
                 {
-                    ::async_inspect::instrument::inspect_await_start(#label, Some(#location.to_string()));
+                    let __inspect_await = ::async_inspect::instrument::inspect_await_start(#label, ::core::option::Option::Some(#location.to_string()));
                     let __result = #base.await;
-                    ::async_inspect::instrument::inspect_await_end(#label);
+                    ::async_inspect::instrument::inspect_await_end(__inspect_await);
                     __result
                 }
             };
@@ -0,0 +1,79 @@
+//! Poll instrumentation overhead on 1 vs 16 worker threads
+//!
+//! Each iteration runs one `spawn_tracked` task per worker of a multi-threaded
+//! Tokio runtime, every task yielding a few times so it is polled repeatedly.
+//! With no contention in the inspector the time per iteration stays flat as
+//! the number of workers grows.
+//!
+//! To compare against the inspector before sharding, which took one lock for
+//! all tasks and one for all events, save a baseline with the `unsharded`
+//! feature and compare the default build against it:
+//!
+//! ```text
+//! cargo bench --bench poll_overhead --features unsharded -- --save-baseline unsharded
+//! cargo bench --bench poll_overhead -- --baseline unsharded
+//! ```
+
+use async_inspect::runtime::tokio::spawn_tracked;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::time::{Duration, Instant};
+use tokio::runtime::{Builder, Runtime};
+
+const WORKER_COUNTS: [usize; 2] = [1, 16];
+
+/// Times each tracked task is polled
+const POLLS_PER_TASK: usize = 8;
+
+fn runtime(workers: usize) -> Runtime {
+    Builder::new_multi_thread()
+        .worker_threads(workers)
+        .enable_all()
+        .build()
+        .unwrap()
+}
+
+/// Run `iters` tracked tasks in turn on each of `workers` drivers and
+/// return the wall time
+fn run_tracked(rt: &Runtime, workers: usize, iters: u64) -> Duration {
+    rt.block_on(async {
+        let start = Instant::now();
+        let drivers: Vec<_> = (0..workers)
+            .map(|_| {
+                tokio::spawn(async move {
+                    for _ in 0..iters {
+                        spawn_tracked("bench_task", async {
+                            for _ in 0..POLLS_PER_TASK {
+                                tokio::task::yield_now().await;
+                            }
+                        })
+                        .await
+                        .unwrap();
+                    }
+                })
+            })
+            .collect();
+
+        for driver in drivers {
+            driver.await.unwrap();
+        }
+        start.elapsed()
+    })
+}
+
+fn poll_overhead(c: &mut Criterion) {
+    let mut group = c.benchmark_group("poll_overhead");
+
+    for workers in WORKER_COUNTS {
+        let rt = runtime(workers);
+        group.bench_with_input(
+            BenchmarkId::new("spawn_tracked", workers),
+            &workers,
+            |b, &workers| b.iter_custom(|iters| run_tracked(&rt, workers, iters)),
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, poll_overhead);
+criterion_main!(benches);
@@ -6,7 +6,7 @@
 //! Run with: cargo run --example relationship_graph
 
 use async_inspect::graph::*;
-use async_inspect::task::{TaskId, TaskInfo, TaskState};
+use async_inspect::task::{TaskId, TaskInfo, TaskLevel, TaskState, WakerStats};
 use std::time::Instant;
 
 fn main() {
@@ -282,6 +282,13 @@ fn create_task(id: u64, name: &str, state: TaskState) -> TaskInfo {
         poll_count: 0,
         total_run_time: std::time::Duration::from_millis(0),
         parent: None,
+        child_kind: None,
         location: None,
+        await_point: None,
+        await_location: None,
+        await_since: None,
+        level: TaskLevel::default(),
+        fields: Vec::new(),
+        wakers: WakerStats::default(),
     }
 }
@@ -0,0 +1,63 @@
+//! Background monitor threads
+//!
+//! The [`Watchdog`](crate::watchdog::Watchdog) and the
+//! [`DeadlockMonitor`](crate::deadlock::DeadlockMonitor) both run a periodic
+//! check on a dedicated thread. [`MonitorHandle`] owns such a thread and
+//! stops it when dropped.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// Handle to a monitor thread, stopping it when dropped
+pub struct MonitorHandle {
+    stop: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl MonitorHandle {
+    /// Run `check` on a named thread every `interval` until stopped
+    pub(crate) fn spawn(
+        name: &str,
+        interval: Duration,
+        mut check: impl FnMut() + Send + 'static,
+    ) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::clone(&stop);
+
+        let thread = thread::Builder::new()
+            .name(name.to_string())
+            .spawn(move || {
+                while !stopped.load(Ordering::Relaxed) {
+                    check();
+                    thread::park_timeout(interval);
+                }
+            })
+            .expect("failed to spawn monitor thread");
+
+        Self {
+            stop,
+            thread: Some(thread),
+        }
+    }
+
+    /// Stop the monitor and wait for its thread to exit
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for MonitorHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
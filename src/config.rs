@@ -3,12 +3,20 @@
 //! This module provides configuration options for using async-inspect
 //! in production environments with minimal overhead.
 
+use crate::task::TaskLevel;
+use crate::timeline::EvictionPolicy;
 use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
 /// Global configuration instance
 static CONFIG: once_cell::sync::Lazy<Config> = once_cell::sync::Lazy::new(Config::default);
 
+/// Encodings of `EvictionPolicy` stored in `ConfigInner::eviction_policy`
+const POLICY_DROP_OLDEST: usize = 0;
+const POLICY_KEEP_TASK_TAIL: usize = 1;
+const POLICY_KEEP_UNFINISHED: usize = 2;
+
 /// Production configuration for async-inspect
 #[derive(Clone)]
 pub struct Config {
@@ -25,6 +33,15 @@ struct ConfigInner {
     /// Maximum number of tasks to track (0 = unlimited)
     max_tasks: AtomicUsize,
 
+    /// Index in `TaskLevel::ALL` of the least important level tracked
+    min_level: AtomicUsize,
+
+    /// Which events to evict once `max_events` is reached (see `EvictionPolicy`)
+    eviction_policy: AtomicUsize,
+
+    /// Per-task tail length for `EvictionPolicy::KeepTaskTail`
+    eviction_task_tail: AtomicUsize,
+
     /// Counter for sampling decisions
     sample_counter: AtomicU64,
 
@@ -34,6 +51,15 @@ struct ConfigInner {
     /// Whether to track poll counts
     track_polls: AtomicUsize,
 
+    /// Whether to instrument wakers
+    track_wakers: AtomicUsize,
+
+    /// Poll duration from which a poll is flagged as blocking (nanoseconds, 0 = off)
+    long_poll_threshold_ns: AtomicU64,
+
+    /// Whether to log long polls through `tracing`
+    log_long_polls: AtomicUsize,
+
     /// Whether to generate HTML reports
     enable_html: AtomicUsize,
 
@@ -57,9 +83,15 @@ impl Config {
                 sampling_rate: AtomicUsize::new(1),   // Track all tasks by default
                 max_events: AtomicUsize::new(10_000), // Default: keep last 10k events
                 max_tasks: AtomicUsize::new(1_000),   // Default: track up to 1k tasks
+                min_level: AtomicUsize::new(0),       // Track every level by default
+                eviction_policy: AtomicUsize::new(POLICY_DROP_OLDEST),
+                eviction_task_tail: AtomicUsize::new(0),
                 sample_counter: AtomicU64::new(0),
                 track_awaits: AtomicUsize::new(1), // Enabled by default
                 track_polls: AtomicUsize::new(1),  // Enabled by default
+                track_wakers: AtomicUsize::new(1), // Enabled by default
+                long_poll_threshold_ns: AtomicU64::new(1_000_000), // 1ms default
+                log_long_polls: AtomicUsize::new(0), // Disabled by default
                 enable_html: AtomicUsize::new(1),  // Enabled by default
                 overhead_ns: AtomicU64::new(0),
                 instrumentation_calls: AtomicU64::new(0),
@@ -99,6 +131,40 @@ impl Config {
         self.inner.max_tasks.load(Ordering::Relaxed)
     }
 
+    /// Set the least important task level that is tracked
+    pub fn set_min_level(&self, level: TaskLevel) {
+        self.inner
+            .min_level
+            .store(level as usize, Ordering::Relaxed);
+    }
+
+    /// Get the least important task level that is tracked
+    pub fn min_level(&self) -> TaskLevel {
+        TaskLevel::ALL[self.inner.min_level.load(Ordering::Relaxed)]
+    }
+
+    /// Set which events are evicted once `max_events` is reached
+    pub fn set_eviction_policy(&self, policy: EvictionPolicy) {
+        let (kind, tail) = match policy {
+            EvictionPolicy::DropOldest => (POLICY_DROP_OLDEST, 0),
+            EvictionPolicy::KeepTaskTail(n) => (POLICY_KEEP_TASK_TAIL, n),
+            EvictionPolicy::KeepUnfinished => (POLICY_KEEP_UNFINISHED, 0),
+        };
+        self.inner.eviction_task_tail.store(tail, Ordering::Relaxed);
+        self.inner.eviction_policy.store(kind, Ordering::Relaxed);
+    }
+
+    /// Get the event eviction policy
+    pub fn eviction_policy(&self) -> EvictionPolicy {
+        match self.inner.eviction_policy.load(Ordering::Relaxed) {
+            POLICY_KEEP_TASK_TAIL => {
+                EvictionPolicy::KeepTaskTail(self.inner.eviction_task_tail.load(Ordering::Relaxed))
+            }
+            POLICY_KEEP_UNFINISHED => EvictionPolicy::KeepUnfinished,
+            _ => EvictionPolicy::DropOldest,
+        }
+    }
+
     /// Enable or disable await tracking
     pub fn set_track_awaits(&self, enabled: bool) {
         self.inner
@@ -123,6 +189,44 @@ impl Config {
         self.inner.track_polls.load(Ordering::Relaxed) != 0
     }
 
+    /// Enable or disable waker instrumentation
+    pub fn set_track_wakers(&self, enabled: bool) {
+        self.inner
+            .track_wakers
+            .store(enabled as usize, Ordering::Relaxed);
+    }
+
+    /// Check if waker instrumentation is enabled
+    pub fn track_wakers(&self) -> bool {
+        self.inner.track_wakers.load(Ordering::Relaxed) != 0
+    }
+
+    /// Set the poll duration from which a poll is flagged as blocking the
+    /// executor (zero disables detection)
+    pub fn set_long_poll_threshold(&self, threshold: Duration) {
+        let nanos = u64::try_from(threshold.as_nanos()).unwrap_or(u64::MAX);
+        self.inner
+            .long_poll_threshold_ns
+            .store(nanos, Ordering::Relaxed);
+    }
+
+    /// Get the long poll threshold (zero when detection is disabled)
+    pub fn long_poll_threshold(&self) -> Duration {
+        Duration::from_nanos(self.inner.long_poll_threshold_ns.load(Ordering::Relaxed))
+    }
+
+    /// Enable or disable logging long polls with `tracing::warn!`
+    pub fn set_log_long_polls(&self, enabled: bool) {
+        self.inner
+            .log_long_polls
+            .store(enabled as usize, Ordering::Relaxed);
+    }
+
+    /// Check if long polls are logged with `tracing::warn!`
+    pub fn log_long_polls(&self) -> bool {
+        self.inner.log_long_polls.load(Ordering::Relaxed) != 0
+    }
+
     /// Enable or disable HTML report generation
     pub fn set_enable_html(&self, enabled: bool) {
         self.inner
@@ -179,6 +283,7 @@ impl Config {
         self.set_max_events(1_000); // Keep only 1k events
         self.set_max_tasks(500); // Track up to 500 tasks
         self.set_track_awaits(false); // Disable detailed await tracking
+        self.set_track_wakers(false); // Disable waker instrumentation
         self.set_enable_html(false); // Disable HTML generation
     }
 
@@ -188,6 +293,7 @@ impl Config {
         self.set_max_events(10_000); // Keep 10k events
         self.set_max_tasks(1_000); // Track up to 1k tasks
         self.set_track_awaits(true); // Enable await tracking
+        self.set_track_wakers(true); // Enable waker instrumentation
         self.set_enable_html(true); // Enable HTML generation
     }
 
@@ -197,6 +303,7 @@ impl Config {
         self.set_max_events(0); // Unlimited events
         self.set_max_tasks(0); // Unlimited tasks
         self.set_track_awaits(true); // Enable await tracking
+        self.set_track_wakers(true); // Enable waker instrumentation
         self.set_enable_html(true); // Enable HTML generation
     }
 
@@ -309,6 +416,28 @@ mod tests {
 
         assert_eq!(config.sampling_rate(), 100);
         assert!(!config.track_awaits());
+        assert!(!config.track_wakers());
         assert!(!config.enable_html());
     }
+
+    #[test]
+    fn test_eviction_policy() {
+        let config = Config::new();
+        assert_eq!(config.eviction_policy(), EvictionPolicy::DropOldest);
+
+        config.set_eviction_policy(EvictionPolicy::KeepTaskTail(8));
+        assert_eq!(config.eviction_policy(), EvictionPolicy::KeepTaskTail(8));
+
+        config.set_eviction_policy(EvictionPolicy::KeepUnfinished);
+        assert_eq!(config.eviction_policy(), EvictionPolicy::KeepUnfinished);
+    }
+
+    #[test]
+    fn test_min_level() {
+        let config = Config::new();
+        assert_eq!(config.min_level(), TaskLevel::Trace);
+
+        config.set_min_level(TaskLevel::Warn);
+        assert_eq!(config.min_level(), TaskLevel::Warn);
+    }
 }
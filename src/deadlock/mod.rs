@@ -1,16 +1,31 @@
 //! Deadlock detection and analysis
 //!
 //! This module provides automatic detection of deadlocks caused by circular
-//! dependencies between tasks waiting on resources (mutexes, channels, etc.).
+//! dependencies between tasks waiting on resources (mutexes, channels, etc.),
+//! and of lock-order inversions that could deadlock in a future run. A
+//! [`DeadlockMonitor`] runs detection continuously in the background.
+
+mod monitor;
+mod order;
+
+pub use monitor::DeadlockMonitor;
+pub use order::{Acquisition, LockOrder, LockOrderInversion};
 
 use crate::task::TaskId;
+use order::LockOrderGraph;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
 use std::collections::{HashMap, HashSet};
 use std::fmt;
+use std::panic::Location;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
+/// Global deadlock detector instance
+static GLOBAL_DETECTOR: once_cell::sync::Lazy<DeadlockDetector> =
+    once_cell::sync::Lazy::new(DeadlockDetector::new);
+
 /// Unique identifier for a resource (lock, channel, etc.)
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ResourceId(u64);
@@ -67,6 +82,73 @@ impl fmt::Display for ResourceKind {
     }
 }
 
+/// How a task holds, or wants to hold, a resource
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Access {
+    /// Sole access (mutex lock, write lock)
+    Exclusive,
+    /// Access shared with other shared holders (read lock)
+    Shared,
+    /// A number of permits (semaphore)
+    Permits(u32),
+    /// Sending end of a channel, held by senders and wanted by a blocked send
+    Sender,
+    /// Receiving end of a channel, held by receivers and wanted by a blocked
+    /// receive
+    Receiver,
+}
+
+impl Access {
+    /// Number of permits this access takes
+    pub fn permits(self) -> usize {
+        match self {
+            Self::Permits(permits) => permits as usize,
+            Self::Exclusive | Self::Shared | Self::Sender | Self::Receiver => 0,
+        }
+    }
+
+    /// The other end of a channel, whose holders can unblock this end
+    pub fn counterpart(self) -> Option<Access> {
+        match self {
+            Self::Sender => Some(Self::Receiver),
+            Self::Receiver => Some(Self::Sender),
+            Self::Exclusive | Self::Shared | Self::Permits(_) => None,
+        }
+    }
+
+    /// Check whether a holder with this access keeps out a task wanting
+    /// `other`; only shared access is compatible, with itself
+    pub fn conflicts_with(self, other: Access) -> bool {
+        !(self == Self::Shared && other == Self::Shared)
+    }
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Exclusive => write!(f, "exclusive"),
+            Self::Shared => write!(f, "shared"),
+            Self::Permits(1) => write!(f, "1 permit"),
+            Self::Permits(permits) => write!(f, "{permits} permits"),
+            Self::Sender => write!(f, "sender"),
+            Self::Receiver => write!(f, "receiver"),
+        }
+    }
+}
+
+/// A task holding or waiting for a resource
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Claim {
+    /// Task holding or waiting
+    pub task: TaskId,
+
+    /// Access held or wanted
+    pub access: Access,
+
+    /// Source location the resource was acquired at, for holders
+    pub site: Option<&'static Location<'static>>,
+}
+
 /// Information about a resource
 #[derive(Debug, Clone)]
 pub struct ResourceInfo {
@@ -79,11 +161,14 @@ pub struct ResourceInfo {
     /// Name or description
     pub name: String,
 
-    /// Task currently holding this resource (if any)
-    pub holder: Option<TaskId>,
+    /// Tasks currently holding this resource, in acquisition order
+    pub holders: Vec<Claim>,
+
+    /// Tasks waiting for this resource, with the access they want
+    pub waiters: Vec<Claim>,
 
-    /// Tasks waiting for this resource
-    pub waiters: Vec<TaskId>,
+    /// Total permits, for semaphores (unknown if `None`)
+    pub capacity: Option<usize>,
 
     /// Memory address (for debugging)
     pub address: Option<usize>,
@@ -96,8 +181,9 @@ impl ResourceInfo {
             id: ResourceId::new(),
             kind,
             name,
-            holder: None,
+            holders: Vec::new(),
             waiters: Vec::new(),
+            capacity: None,
             address: None,
         }
     }
@@ -108,9 +194,83 @@ impl ResourceInfo {
         self
     }
 
+    /// Set the total number of permits
+    pub fn with_capacity(mut self, permits: usize) -> Self {
+        self.capacity = Some(permits);
+        self
+    }
+
     /// Check if resource is held
     pub fn is_held(&self) -> bool {
-        self.holder.is_some()
+        !self.holders.is_empty()
+    }
+
+    /// Check if a task holds this resource
+    pub fn is_held_by(&self, task_id: TaskId) -> bool {
+        self.holders.iter().any(|holder| holder.task == task_id)
+    }
+
+    /// Total permits currently held
+    pub fn held_permits(&self) -> usize {
+        self.holders
+            .iter()
+            .map(|holder| holder.access.permits())
+            .sum()
+    }
+
+    /// Tasks that must release before a request can be granted
+    ///
+    /// For exclusive and shared requests these are the conflicting holders.
+    /// A permit request is blocked by every holder, but only while too few
+    /// permits are free. A blocked send or receive waits on the holders of
+    /// the other end of the channel.
+    pub fn blockers(&self, request: Access) -> Vec<TaskId> {
+        if !self.is_blocked_by(request, |_| true) {
+            return Vec::new();
+        }
+
+        self.holders
+            .iter()
+            .filter(|holder| match request {
+                Access::Permits(_) => true,
+                Access::Sender | Access::Receiver => Some(holder.access) == request.counterpart(),
+                Access::Exclusive | Access::Shared => holder.access.conflicts_with(request),
+            })
+            .map(|holder| holder.task)
+            .collect()
+    }
+
+    /// Check whether a request stays blocked even if every holder not
+    /// matched by `stuck` releases
+    fn is_blocked_by(&self, request: Access, stuck: impl Fn(TaskId) -> bool) -> bool {
+        match request {
+            Access::Permits(permits) => {
+                let held = self.holders.iter().filter(|holder| stuck(holder.task));
+                match self.capacity {
+                    Some(capacity) => {
+                        let held: usize = held.map(|holder| holder.access.permits()).sum();
+                        capacity.saturating_sub(held) < permits as usize
+                    }
+                    // Without a capacity, the request is only known to stay
+                    // blocked while no holder can release
+                    None => self.is_held() && held.count() == self.holders.len(),
+                }
+            }
+            // Any task holding the other end may still send or receive, so
+            // the channel only stays blocked while all of them are stuck
+            Access::Sender | Access::Receiver => {
+                let mut ends = self
+                    .holders
+                    .iter()
+                    .filter(|holder| Some(holder.access) == request.counterpart())
+                    .peekable();
+                ends.peek().is_some() && ends.all(|holder| stuck(holder.task))
+            }
+            Access::Exclusive | Access::Shared => self
+                .holders
+                .iter()
+                .any(|holder| stuck(holder.task) && holder.access.conflicts_with(request)),
+        }
     }
 
     /// Check if resource has waiters
@@ -151,6 +311,9 @@ pub struct WaitEdge {
     /// Resource being waited for
     pub resource: ResourceId,
 
+    /// Access the waiting task wants
+    pub access: Access,
+
     /// Task holding the resource
     pub holder: TaskId,
 }
@@ -162,10 +325,11 @@ impl DeadlockCycle {
 
         for (i, edge) in self.chain.iter().enumerate() {
             desc.push_str(&format!(
-                "  {} Task {} → {} → Task {}\n",
+                "  {} Task {} → {} ({}) → Task {}\n",
                 if i == 0 { "→" } else { " " },
                 edge.task,
                 edge.resource,
+                edge.access,
                 edge.holder
             ));
         }
@@ -194,10 +358,90 @@ struct DetectorState {
     /// Mapping from task to resources it's waiting for
     task_waiting: HashMap<TaskId, ResourceId>,
 
+    /// Resources each task holds, once per hold
+    task_holding: HashMap<TaskId, SmallVec<[ResourceId; 4]>>,
+
+    /// Order in which tasks acquired resources over the whole run
+    lock_order: LockOrderGraph,
+
     /// Whether detection is enabled
     enabled: bool,
 }
 
+impl DetectorState {
+    /// Locks a task holds, other than `except`
+    fn held_by(&self, task_id: TaskId, except: ResourceId) -> Vec<Acquisition> {
+        let Some(held) = self.task_holding.get(&task_id) else {
+            return Vec::new();
+        };
+
+        held.iter()
+            .enumerate()
+            .filter(|&(i, id)| *id != except && !held[..i].contains(id))
+            .filter_map(|(_, id)| self.resources.get(id))
+            .flat_map(|resource| {
+                resource
+                    .holders
+                    .iter()
+                    .filter(|h| h.task == task_id && h.access.counterpart().is_none())
+                    .map(|h| Acquisition {
+                        resource: resource.id,
+                        name: resource.name.clone(),
+                        access: h.access,
+                        site: h.site,
+                    })
+            })
+            .collect()
+    }
+
+    /// Record a task giving up one hold on a resource
+    fn unhold(&mut self, task_id: TaskId, resource_id: ResourceId) {
+        if let Some(held) = self.task_holding.get_mut(&task_id) {
+            if let Some(i) = held.iter().position(|id| *id == resource_id) {
+                held.remove(i);
+            }
+            if held.is_empty() {
+                self.task_holding.remove(&task_id);
+            }
+        }
+    }
+
+    /// Resource a task is waiting for, with the access it wants
+    fn request(&self, task_id: TaskId) -> Option<(&ResourceInfo, Access)> {
+        let resource = self.resources.get(self.task_waiting.get(&task_id)?)?;
+        let waiter = resource.waiters.iter().find(|w| w.task == task_id)?;
+        Some((resource, waiter.access))
+    }
+
+    /// Waiting tasks that can never proceed
+    ///
+    /// Starting from every waiting task, repeatedly drops the tasks whose
+    /// request could be granted once every task outside the set has released
+    /// what it holds.
+    fn deadlocked_tasks(&self) -> HashSet<TaskId> {
+        let mut stuck: HashSet<TaskId> = self.task_waiting.keys().copied().collect();
+
+        loop {
+            let free: Vec<TaskId> = stuck
+                .iter()
+                .copied()
+                .filter(|&task| {
+                    !self.request(task).is_some_and(|(resource, access)| {
+                        resource.is_blocked_by(access, |holder| stuck.contains(&holder))
+                    })
+                })
+                .collect();
+
+            if free.is_empty() {
+                return stuck;
+            }
+            for task in free {
+                stuck.remove(&task);
+            }
+        }
+    }
+}
+
 impl DeadlockDetector {
     /// Create a new deadlock detector
     pub fn new() -> Self {
@@ -205,11 +449,19 @@ impl DeadlockDetector {
             state: Arc::new(RwLock::new(DetectorState {
                 resources: HashMap::new(),
                 task_waiting: HashMap::new(),
+                task_holding: HashMap::new(),
+                lock_order: LockOrderGraph::default(),
                 enabled: true,
             })),
         }
     }
 
+    /// Get the global deadlock detector, fed by the instrumented primitives
+    /// in [`crate::sync`]
+    pub fn global() -> &'static Self {
+        &GLOBAL_DETECTOR
+    }
+
     /// Enable deadlock detection
     pub fn enable(&self) {
         self.state.write().enabled = true;
@@ -236,8 +488,39 @@ impl DeadlockDetector {
         resource_id
     }
 
-    /// Record a task acquiring a resource
+    /// Stop tracking a resource, forgetting any task waiting for it
+    pub fn unregister_resource(&self, resource_id: ResourceId) {
+        let mut state = self.state.write();
+        if let Some(resource) = state.resources.remove(&resource_id) {
+            for holder in &resource.holders {
+                state.unhold(holder.task, resource_id);
+            }
+        }
+        state
+            .task_waiting
+            .retain(|_, waiting| *waiting != resource_id);
+    }
+
+    /// Record a task acquiring a resource exclusively
+    #[track_caller]
     pub fn acquire(&self, task_id: TaskId, resource_id: ResourceId) {
+        self.acquire_at(task_id, resource_id, Access::Exclusive, Location::caller());
+    }
+
+    /// Record a task acquiring a resource with the given access
+    #[track_caller]
+    pub fn acquire_access(&self, task_id: TaskId, resource_id: ResourceId, access: Access) {
+        self.acquire_at(task_id, resource_id, access, Location::caller());
+    }
+
+    /// Record a task acquiring a resource at a given source location
+    pub fn acquire_at(
+        &self,
+        task_id: TaskId,
+        resource_id: ResourceId,
+        access: Access,
+        site: &'static Location<'static>,
+    ) {
         if !self.is_enabled() {
             return;
         }
@@ -247,14 +530,40 @@ impl DeadlockDetector {
         // Remove from waiting
         state.task_waiting.remove(&task_id);
 
-        // Set as holder
+        let Some(resource) = state.resources.get(&resource_id) else {
+            return;
+        };
+
+        // Record the order against every lock the task already holds;
+        // channel endpoints are not acquired in any order
+        if access.counterpart().is_none() {
+            let acquired = Acquisition {
+                resource: resource_id,
+                name: resource.name.clone(),
+                access,
+                site: Some(site),
+            };
+            let held = state.held_by(task_id, resource_id);
+            state.lock_order.record(task_id, held, &acquired);
+        }
+
+        // Add as holder
         if let Some(resource) = state.resources.get_mut(&resource_id) {
-            resource.holder = Some(task_id);
-            resource.waiters.retain(|&t| t != task_id);
+            resource.holders.push(Claim {
+                task: task_id,
+                access,
+                site: Some(site),
+            });
+            resource.waiters.retain(|w| w.task != task_id);
+            state
+                .task_holding
+                .entry(task_id)
+                .or_default()
+                .push(resource_id);
         }
     }
 
-    /// Record a task releasing a resource
+    /// Record a task releasing its most recent hold on a resource
     pub fn release(&self, task_id: TaskId, resource_id: ResourceId) {
         if !self.is_enabled() {
             return;
@@ -263,14 +572,41 @@ impl DeadlockDetector {
         let mut state = self.state.write();
 
         if let Some(resource) = state.resources.get_mut(&resource_id) {
-            if resource.holder == Some(task_id) {
-                resource.holder = None;
+            if let Some(i) = resource.holders.iter().rposition(|h| h.task == task_id) {
+                resource.holders.remove(i);
+                state.unhold(task_id, resource_id);
+            }
+        }
+    }
+
+    /// Record a task releasing its most recent hold on a resource with the
+    /// given access
+    pub fn release_access(&self, task_id: TaskId, resource_id: ResourceId, access: Access) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let mut state = self.state.write();
+
+        if let Some(resource) = state.resources.get_mut(&resource_id) {
+            if let Some(i) = resource
+                .holders
+                .iter()
+                .rposition(|h| h.task == task_id && h.access == access)
+            {
+                resource.holders.remove(i);
+                state.unhold(task_id, resource_id);
             }
         }
     }
 
-    /// Record a task waiting for a resource
+    /// Record a task waiting for exclusive access to a resource
     pub fn wait_for(&self, task_id: TaskId, resource_id: ResourceId) {
+        self.wait_for_access(task_id, resource_id, Access::Exclusive);
+    }
+
+    /// Record a task waiting for a resource with the given access
+    pub fn wait_for_access(&self, task_id: TaskId, resource_id: ResourceId, access: Access) {
         if !self.is_enabled() {
             return;
         }
@@ -282,9 +618,36 @@ impl DeadlockDetector {
 
         // Add to waiters list
         if let Some(resource) = state.resources.get_mut(&resource_id) {
-            if !resource.waiters.contains(&task_id) {
-                resource.waiters.push(task_id);
-            }
+            resource.waiters.retain(|w| w.task != task_id);
+            resource.waiters.push(Claim {
+                task: task_id,
+                access,
+                site: None,
+            });
+        }
+    }
+
+    /// Record permits being added to a resource's capacity
+    pub fn add_capacity(&self, resource_id: ResourceId, permits: usize) {
+        if let Some(resource) = self.state.write().resources.get_mut(&resource_id) {
+            resource.capacity = resource.capacity.map(|capacity| capacity + permits);
+        }
+    }
+
+    /// Record a task giving up waiting for a resource without acquiring it
+    pub fn cancel_wait(&self, task_id: TaskId, resource_id: ResourceId) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let mut state = self.state.write();
+
+        if state.task_waiting.get(&task_id) == Some(&resource_id) {
+            state.task_waiting.remove(&task_id);
+        }
+
+        if let Some(resource) = state.resources.get_mut(&resource_id) {
+            resource.waiters.retain(|w| w.task != task_id);
         }
     }
 
@@ -292,16 +655,18 @@ impl DeadlockDetector {
     pub fn detect_deadlocks(&self) -> Vec<DeadlockCycle> {
         let state = self.state.read();
 
-        // Build wait-for graph: Task -> Task via Resource
+        // Build wait-for graph between tasks that can never proceed:
+        // Task -> deadlocked holders of its Resource that block it
+        let stuck = state.deadlocked_tasks();
         let mut graph: HashMap<TaskId, Vec<TaskId>> = HashMap::new();
-        let mut task_to_resource: HashMap<TaskId, ResourceId> = HashMap::new();
 
-        for (&waiting_task, &resource_id) in &state.task_waiting {
-            if let Some(resource) = state.resources.get(&resource_id) {
-                if let Some(holder_task) = resource.holder {
-                    graph.entry(waiting_task).or_default().push(holder_task);
-                    task_to_resource.insert(waiting_task, resource_id);
-                }
+        for &waiting_task in &stuck {
+            if let Some((resource, access)) = state.request(waiting_task) {
+                let blockers = resource.blockers(access);
+                graph.insert(
+                    waiting_task,
+                    blockers.into_iter().filter(|t| stuck.contains(t)).collect(),
+                );
             }
         }
 
@@ -312,10 +677,12 @@ impl DeadlockDetector {
 
         for &task in graph.keys() {
             if !visited.contains(&task) {
+                // A search that found a cycle returns without unwinding
+                rec_stack.clear();
                 if let Some(cycle) = self.find_cycle_dfs(
                     task,
                     &graph,
-                    &task_to_resource,
+                    &state,
                     &mut visited,
                     &mut rec_stack,
                     &mut Vec::new(),
@@ -333,7 +700,7 @@ impl DeadlockDetector {
         &self,
         task: TaskId,
         graph: &HashMap<TaskId, Vec<TaskId>>,
-        task_to_resource: &HashMap<TaskId, ResourceId>,
+        state: &DetectorState,
         visited: &mut HashSet<TaskId>,
         rec_stack: &mut HashSet<TaskId>,
         path: &mut Vec<TaskId>,
@@ -345,19 +712,14 @@ impl DeadlockDetector {
         if let Some(neighbors) = graph.get(&task) {
             for &neighbor in neighbors {
                 if !visited.contains(&neighbor) {
-                    if let Some(cycle) = self.find_cycle_dfs(
-                        neighbor,
-                        graph,
-                        task_to_resource,
-                        visited,
-                        rec_stack,
-                        path,
-                    ) {
+                    if let Some(cycle) =
+                        self.find_cycle_dfs(neighbor, graph, state, visited, rec_stack, path)
+                    {
                         return Some(cycle);
                     }
                 } else if rec_stack.contains(&neighbor) {
                     // Found a cycle!
-                    return Some(self.build_cycle(neighbor, path, task_to_resource));
+                    return Some(self.build_cycle(neighbor, path, state));
                 }
             }
         }
@@ -372,7 +734,7 @@ impl DeadlockDetector {
         &self,
         start_task: TaskId,
         path: &[TaskId],
-        task_to_resource: &HashMap<TaskId, ResourceId>,
+        state: &DetectorState,
     ) -> DeadlockCycle {
         // Find where the cycle starts
         let cycle_start = path.iter().position(|&t| t == start_task).unwrap_or(0);
@@ -385,11 +747,12 @@ impl DeadlockDetector {
             let waiting_task = cycle_tasks[i];
             let holder_task = cycle_tasks[(i + 1) % cycle_tasks.len()];
 
-            if let Some(&resource_id) = task_to_resource.get(&waiting_task) {
-                resources.push(resource_id);
+            if let Some((resource, access)) = state.request(waiting_task) {
+                resources.push(resource.id);
                 chain.push(WaitEdge {
                     task: waiting_task,
-                    resource: resource_id,
+                    resource: resource.id,
+                    access,
                     holder: holder_task,
                 });
             }
@@ -412,11 +775,27 @@ impl DeadlockDetector {
         self.state.read().resources.get(&id).cloned()
     }
 
+    /// Get every lock order seen so far
+    pub fn lock_order(&self) -> Vec<LockOrder> {
+        self.state.read().lock_order.orders()
+    }
+
+    /// Find resources acquired in opposite orders over the whole run
+    ///
+    /// Unlike [`detect_deadlocks`](Self::detect_deadlocks), this reports
+    /// potential deadlocks: each inversion can deadlock once its two paths run
+    /// concurrently, whether or not they did.
+    pub fn lock_order_inversions(&self) -> Vec<LockOrderInversion> {
+        self.state.read().lock_order.inversions()
+    }
+
     /// Clear all tracking data
     pub fn clear(&self) {
         let mut state = self.state.write();
         state.resources.clear();
         state.task_waiting.clear();
+        state.task_holding.clear();
+        state.lock_order.clear();
     }
 }
 
@@ -508,4 +887,186 @@ mod tests {
         let deadlocks = detector.detect_deadlocks();
         assert_eq!(deadlocks.len(), 0);
     }
+
+    #[test]
+    fn test_readers_only_conflict_with_writers() {
+        let detector = DeadlockDetector::new();
+        let lock =
+            detector.register_resource(ResourceInfo::new(ResourceKind::RwLock, "lock".into()));
+        let mutex =
+            detector.register_resource(ResourceInfo::new(ResourceKind::Mutex, "mutex".into()));
+
+        let reader = TaskId::new();
+        let other = TaskId::new();
+
+        detector.acquire_access(reader, lock, Access::Shared);
+        detector.wait_for(reader, mutex);
+        detector.acquire(other, mutex);
+
+        // A second reader is not blocked by the first
+        detector.wait_for_access(other, lock, Access::Shared);
+        assert!(detector.detect_deadlocks().is_empty());
+
+        // A writer is
+        detector.wait_for_access(other, lock, Access::Exclusive);
+        let deadlocks = detector.detect_deadlocks();
+        assert_eq!(deadlocks.len(), 1);
+        let edge = deadlocks[0].chain.iter().find(|e| e.task == other).unwrap();
+        assert_eq!((edge.resource, edge.access), (lock, Access::Exclusive));
+
+        // Several readers are all tracked as holders
+        let third = TaskId::new();
+        detector.acquire_access(third, lock, Access::Shared);
+        let info = detector.get_resource(lock).unwrap();
+        assert!(info.is_held_by(reader) && info.is_held_by(third));
+        detector.release(third, lock);
+        assert!(!detector.get_resource(lock).unwrap().is_held_by(third));
+    }
+
+    #[test]
+    fn test_semaphore_deadlock_needs_exhausted_permits() {
+        let detector = DeadlockDetector::new();
+        let pool = detector.register_resource(
+            ResourceInfo::new(ResourceKind::Semaphore, "pool".into()).with_capacity(2),
+        );
+        let mutex =
+            detector.register_resource(ResourceInfo::new(ResourceKind::Mutex, "mutex".into()));
+
+        let (first, second, owner) = (TaskId::new(), TaskId::new(), TaskId::new());
+
+        detector.acquire_access(first, pool, Access::Permits(1));
+        detector.wait_for(first, mutex);
+        detector.acquire(owner, mutex);
+        detector.wait_for_access(owner, pool, Access::Permits(1));
+
+        // A permit is still free
+        assert!(detector.detect_deadlocks().is_empty());
+
+        // The last permit is held by a task that will release it
+        detector.acquire_access(second, pool, Access::Permits(1));
+        assert!(detector.detect_deadlocks().is_empty());
+
+        // Until it waits on the mutex as well
+        detector.wait_for(second, mutex);
+        let deadlocks = detector.detect_deadlocks();
+        assert_eq!(deadlocks.len(), 1);
+        assert!(deadlocks[0].tasks.contains(&owner));
+
+        // Extra permits break the deadlock
+        detector.add_capacity(pool, 1);
+        assert!(detector.detect_deadlocks().is_empty());
+    }
+
+    #[test]
+    fn test_channel_waits_on_the_other_end() {
+        let detector = DeadlockDetector::new();
+        let channel =
+            detector.register_resource(ResourceInfo::new(ResourceKind::Channel, "events".into()));
+        let mutex =
+            detector.register_resource(ResourceInfo::new(ResourceKind::Mutex, "mutex".into()));
+
+        let (receiver, first, second) = (TaskId::new(), TaskId::new(), TaskId::new());
+
+        detector.acquire_access(receiver, channel, Access::Receiver);
+        detector.acquire(receiver, mutex);
+        detector.acquire_access(first, channel, Access::Sender);
+        detector.acquire_access(second, channel, Access::Sender);
+        detector.wait_for_access(receiver, channel, Access::Receiver);
+
+        // One sender parked on the mutex, the other may still send
+        detector.wait_for(first, mutex);
+        assert!(detector.detect_deadlocks().is_empty());
+
+        // Every sender alive but parked
+        detector.wait_for(second, mutex);
+        let deadlocks = detector.detect_deadlocks();
+        assert_eq!(deadlocks.len(), 1);
+        let edge = deadlocks[0]
+            .chain
+            .iter()
+            .find(|e| e.task == receiver)
+            .unwrap();
+        assert_eq!((edge.resource, edge.access), (channel, Access::Receiver));
+
+        // Without senders a receive returns instead of blocking
+        detector.release_access(first, channel, Access::Sender);
+        detector.release_access(second, channel, Access::Sender);
+        assert!(detector.detect_deadlocks().is_empty());
+    }
+
+    #[test]
+    fn test_lock_order_inversion() {
+        let detector = DeadlockDetector::new();
+        let a = detector.register_resource(ResourceInfo::new(ResourceKind::Mutex, "a".into()));
+        let b = detector.register_resource(ResourceInfo::new(ResourceKind::RwLock, "b".into()));
+        let (first, second) = (TaskId::new(), TaskId::new());
+
+        // a then b, released before the other task takes b then a
+        detector.acquire(first, a);
+        detector.acquire(first, b);
+        detector.release(first, b);
+        detector.release(first, a);
+        assert!(detector.lock_order_inversions().is_empty());
+
+        detector.acquire_access(second, b, Access::Shared);
+        detector.acquire(second, a);
+
+        // Nothing is deadlocked, but the two orders can deadlock
+        assert!(detector.detect_deadlocks().is_empty());
+        assert_eq!(detector.lock_order().len(), 2);
+        let inversions = detector.lock_order_inversions();
+        assert_eq!(inversions.len(), 1);
+        let inversion = &inversions[0];
+        assert_eq!(
+            (inversion.forward.first.resource, inversion.forward.task),
+            (a, first)
+        );
+        assert_eq!(
+            (inversion.reverse.first.resource, inversion.reverse.task),
+            (b, second)
+        );
+        let site = inversion.forward.then.site.unwrap();
+        assert!(site.file().ends_with("mod.rs"));
+        assert!(inversion.describe().contains("'b'"));
+    }
+
+    #[test]
+    fn test_held_resources_are_indexed_per_task() {
+        let detector = DeadlockDetector::new();
+        let a = detector.register_resource(ResourceInfo::new(ResourceKind::Semaphore, "a".into()));
+        let b = detector.register_resource(ResourceInfo::new(ResourceKind::Mutex, "b".into()));
+        let task = TaskId::new();
+
+        // Both permits of `a` are held, and `b` is the lock being taken
+        detector.acquire(task, a);
+        detector.acquire(task, a);
+        detector.acquire(task, b);
+        let held = detector.state.read().held_by(task, b);
+        assert_eq!(held.len(), 2);
+        assert!(held.iter().all(|acquisition| acquisition.resource == a));
+
+        detector.release(task, a);
+        detector.unregister_resource(a);
+        detector.release(task, b);
+        assert!(detector.state.read().task_holding.is_empty());
+    }
+
+    #[test]
+    fn test_shared_acquisitions_do_not_invert() {
+        let detector = DeadlockDetector::new();
+        let a = detector.register_resource(ResourceInfo::new(ResourceKind::RwLock, "a".into()));
+        let b = detector.register_resource(ResourceInfo::new(ResourceKind::Mutex, "b".into()));
+        let (first, second) = (TaskId::new(), TaskId::new());
+
+        detector.acquire_access(first, a, Access::Shared);
+        detector.acquire(first, b);
+        detector.release(first, b);
+        detector.release(first, a);
+
+        detector.acquire(second, b);
+        detector.acquire_access(second, a, Access::Shared);
+
+        // Both tasks can hold a at once, so neither waits on the other for it
+        assert!(detector.lock_order_inversions().is_empty());
+    }
 }
@@ -0,0 +1,190 @@
+//! Continuous deadlock monitoring
+//!
+//! A [`DeadlockMonitor`] periodically runs
+//! [`DeadlockDetector::detect_deadlocks`], records each newly found cycle as
+//! an [`EventKind::DeadlockDetected`] event on every task involved, hands it to
+//! the registered callbacks, and publishes the cycles currently present as the
+//! inspector's active deadlocks.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use async_inspect::deadlock::{DeadlockDetector, DeadlockMonitor};
+//! use async_inspect::inspector::Inspector;
+//!
+//! let handle = DeadlockMonitor::new(
+//!     DeadlockDetector::global().clone(),
+//!     Inspector::global().clone(),
+//! )
+//! .on_deadlock(|cycle| eprintln!("{}", cycle.describe()))
+//! .spawn_thread();
+//! ```
+
+use super::{DeadlockCycle, DeadlockDetector, ResourceId};
+use crate::background::MonitorHandle;
+use crate::inspector::Inspector;
+use crate::task::TaskId;
+use crate::timeline::EventKind;
+use std::collections::HashSet;
+use std::time::Duration;
+
+/// Callback invoked for each newly found deadlock
+type DeadlockCallback = Box<dyn Fn(&DeadlockCycle) + Send + Sync>;
+
+/// Tasks and resources of a cycle, independent of where it was entered
+type CycleKey = (Vec<u64>, Vec<u64>);
+
+fn cycle_key(cycle: &DeadlockCycle) -> CycleKey {
+    let mut tasks: Vec<u64> = cycle.tasks.iter().map(TaskId::as_u64).collect();
+    let mut resources: Vec<u64> = cycle.resources.iter().map(ResourceId::as_u64).collect();
+    tasks.sort_unstable();
+    resources.sort_unstable();
+    (tasks, resources)
+}
+
+/// Periodic deadlock checker
+///
+/// Each deadlock is reported once, for as long as it persists; a cycle that
+/// resolves and forms again is reported again.
+pub struct DeadlockMonitor {
+    detector: DeadlockDetector,
+    inspector: Inspector,
+    interval: Duration,
+    callbacks: Vec<DeadlockCallback>,
+    /// Cycles present at the last scan
+    reported: HashSet<CycleKey>,
+}
+
+impl DeadlockMonitor {
+    /// Create a monitor reporting a detector's deadlocks to an inspector
+    pub fn new(detector: DeadlockDetector, inspector: Inspector) -> Self {
+        Self {
+            detector,
+            inspector,
+            interval: Duration::from_secs(1),
+            callbacks: Vec::new(),
+            reported: HashSet::new(),
+        }
+    }
+
+    /// Set how often the background monitor scans (default 1s)
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Register a callback invoked for each newly found deadlock
+    pub fn on_deadlock(
+        mut self,
+        callback: impl Fn(&DeadlockCycle) + Send + Sync + 'static,
+    ) -> Self {
+        self.callbacks.push(Box::new(callback));
+        self
+    }
+
+    /// Scan once, returning the deadlocks not reported before
+    pub fn check(&mut self) -> Vec<DeadlockCycle> {
+        let cycles = self.detector.detect_deadlocks();
+        let keys: HashSet<CycleKey> = cycles.iter().map(cycle_key).collect();
+
+        let new: Vec<DeadlockCycle> = cycles
+            .iter()
+            .filter(|cycle| !self.reported.contains(&cycle_key(cycle)))
+            .cloned()
+            .collect();
+
+        for cycle in &new {
+            for &task in &cycle.tasks {
+                self.inspector.add_event(
+                    task,
+                    EventKind::DeadlockDetected {
+                        tasks: cycle.tasks.clone(),
+                        resources: cycle.resources.clone(),
+                    },
+                );
+            }
+            for callback in &self.callbacks {
+                callback(cycle);
+            }
+        }
+
+        // Resolved deadlocks may form again later
+        self.reported = keys;
+        self.inspector.set_active_deadlocks(cycles);
+
+        new
+    }
+
+    /// Run the monitor on a dedicated thread until the handle is stopped
+    ///
+    /// # Panics
+    ///
+    /// Panics if the operating system fails to create the thread.
+    pub fn spawn_thread(mut self) -> MonitorHandle {
+        let interval = self.interval;
+        MonitorHandle::spawn("async-inspect-deadlock-monitor", interval, move || {
+            self.check();
+        })
+    }
+
+    /// Run the monitor as a Tokio task until it is aborted
+    #[cfg(feature = "tokio")]
+    pub fn spawn_tokio(mut self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(self.interval);
+            loop {
+                interval.tick().await;
+                self.check();
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::deadlock::{ResourceInfo, ResourceKind};
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_deadlocks_are_reported_once() {
+        let detector = DeadlockDetector::new();
+        let inspector = Inspector::with_config(Config::new());
+        let a = detector.register_resource(ResourceInfo::new(ResourceKind::Mutex, "a".into()));
+        let b = detector.register_resource(ResourceInfo::new(ResourceKind::Mutex, "b".into()));
+        let first = inspector.register_task("first".to_string());
+        let second = inspector.register_task("second".to_string());
+
+        detector.acquire(first, a);
+        detector.acquire(second, b);
+        detector.wait_for(first, b);
+        detector.wait_for(second, a);
+
+        let (tx, rx) = mpsc::channel();
+        let mut monitor = DeadlockMonitor::new(detector.clone(), inspector.clone())
+            .on_deadlock(move |cycle| tx.send(cycle.tasks.len()).unwrap());
+
+        assert_eq!(monitor.check().len(), 1);
+        assert_eq!(rx.try_recv(), Ok(2));
+        assert_eq!(inspector.stats().active_deadlocks, 1);
+        for task in [first, second] {
+            assert!(inspector
+                .get_task_events(task)
+                .iter()
+                .any(|e| matches!(e.kind, EventKind::DeadlockDetected { .. })));
+        }
+
+        // Still the same deadlock
+        assert!(monitor.check().is_empty());
+        assert!(rx.try_recv().is_err());
+        assert_eq!(inspector.active_deadlocks().len(), 1);
+
+        // Resolving it clears the active deadlocks, forming it again reports it
+        detector.cancel_wait(second, a);
+        assert!(monitor.check().is_empty());
+        assert_eq!(inspector.stats().active_deadlocks, 0);
+        detector.wait_for(second, a);
+        assert_eq!(monitor.check().len(), 1);
+    }
+}
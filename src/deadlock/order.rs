@@ -0,0 +1,144 @@
+//! Lock-order analysis
+//!
+//! Every time a task acquires a resource while holding others, the order is
+//! recorded as an edge of a global lock-order graph. Two resources taken in
+//! opposite orders by different code paths can deadlock once those paths run
+//! concurrently, even if the recorded run never did.
+
+use super::{Access, ResourceId};
+use crate::task::TaskId;
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+use std::panic::Location;
+
+/// A resource acquisition recorded in the lock-order graph
+#[derive(Debug, Clone)]
+pub struct Acquisition {
+    /// Resource acquired
+    pub resource: ResourceId,
+
+    /// Resource name at the time
+    pub name: String,
+
+    /// Access taken
+    pub access: Access,
+
+    /// Source location of the acquisition, if known
+    pub site: Option<&'static Location<'static>>,
+}
+
+impl fmt::Display for Acquisition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "'{}' ({}, {})", self.name, self.resource, self.access)?;
+        if let Some(site) = self.site {
+            write!(f, " at {site}")?;
+        }
+        Ok(())
+    }
+}
+
+/// One resource acquired while another was held
+#[derive(Debug, Clone)]
+pub struct LockOrder {
+    /// Resource held, as first acquired in this order
+    pub first: Acquisition,
+
+    /// Resource acquired while holding `first`
+    pub then: Acquisition,
+
+    /// First task seen taking this order
+    pub task: TaskId,
+
+    /// Number of times this order was seen
+    pub count: u64,
+}
+
+/// Two resources acquired in opposite orders
+#[derive(Debug, Clone)]
+pub struct LockOrderInversion {
+    /// One order, `first` then `then`
+    pub forward: LockOrder,
+
+    /// The opposite order
+    pub reverse: LockOrder,
+}
+
+impl LockOrderInversion {
+    /// Get a human-readable description of the inversion
+    pub fn describe(&self) -> String {
+        let mut desc = String::from("Lock order inversion detected:\n");
+
+        for order in [&self.forward, &self.reverse] {
+            writeln!(
+                desc,
+                "  Task {} took {}\n    then {} ({} time(s))",
+                order.task, order.first, order.then, order.count
+            )
+            .unwrap();
+        }
+
+        desc.push_str("\nRunning both paths concurrently can deadlock");
+        desc
+    }
+}
+
+/// Orders seen so far, keyed by (held, acquired)
+#[derive(Default)]
+pub(crate) struct LockOrderGraph {
+    edges: HashMap<(ResourceId, ResourceId), LockOrder>,
+}
+
+impl LockOrderGraph {
+    /// Record `then` being acquired by a task holding each of `held`
+    pub(crate) fn record(&mut self, task: TaskId, held: Vec<Acquisition>, then: &Acquisition) {
+        for first in held {
+            self.edges
+                .entry((first.resource, then.resource))
+                .and_modify(|order| order.count += 1)
+                .or_insert_with(|| LockOrder {
+                    first,
+                    then: then.clone(),
+                    task,
+                    count: 1,
+                });
+        }
+    }
+
+    /// All recorded orders
+    pub(crate) fn orders(&self) -> Vec<LockOrder> {
+        self.edges.values().cloned().collect()
+    }
+
+    /// Pairs of orders that can deadlock each other
+    ///
+    /// Opposite orders only conflict when each resource's two acquisitions
+    /// exclude each other, so two read locks never form an inversion.
+    pub(crate) fn inversions(&self) -> Vec<LockOrderInversion> {
+        let mut inversions: Vec<LockOrderInversion> = self
+            .edges
+            .iter()
+            .filter(|((a, b), _)| a.as_u64() < b.as_u64())
+            .filter_map(|(&(a, b), forward)| {
+                let reverse = self.edges.get(&(b, a))?;
+                let conflicts = forward.first.access.conflicts_with(reverse.then.access)
+                    && forward.then.access.conflicts_with(reverse.first.access);
+                conflicts.then(|| LockOrderInversion {
+                    forward: forward.clone(),
+                    reverse: reverse.clone(),
+                })
+            })
+            .collect();
+
+        inversions.sort_by_key(|inversion| {
+            (
+                inversion.forward.first.resource.as_u64(),
+                inversion.forward.then.resource.as_u64(),
+            )
+        });
+        inversions
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.edges.clear();
+    }
+}
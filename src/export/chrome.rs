@@ -0,0 +1,423 @@
+//! Chrome Trace Event export
+//!
+//! [`ChromeTraceExporter`] writes a capture in the Trace Event format read by
+//! `chrome://tracing` and <https://ui.perfetto.dev>:
+//!
+//! - every task gets a track with its lifetime, its polls and its inspection
+//!   points, plus an async track of the await points it waited at
+//! - every worker thread gets a track of the polls it ran
+//! - spawns are drawn as flow arrows from the parent to the child
+
+use crate::graph::{Relationship, RelationshipType};
+use crate::inspector::Inspector;
+use crate::task::{TaskId, TaskInfo};
+use crate::timeline::{Event, EventKind, ThreadIndex};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeSet, HashMap};
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Process holding one track per task
+const TASKS_PID: u64 = 1;
+
+/// Process holding one track per worker thread
+const THREADS_PID: u64 = 2;
+
+/// One entry of the `traceEvents` array
+#[derive(Debug, Serialize)]
+struct TraceEvent {
+    name: String,
+    cat: &'static str,
+    ph: &'static str,
+    /// Microseconds since the start of the capture
+    ts: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dur: Option<f64>,
+    pid: u64,
+    tid: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<u64>,
+    /// Scope of an instant event
+    #[serde(skip_serializing_if = "Option::is_none")]
+    s: Option<&'static str>,
+    /// Binding point of a flow event
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bp: Option<&'static str>,
+    #[serde(skip_serializing_if = "Value::is_null")]
+    args: Value,
+}
+
+impl TraceEvent {
+    fn new(
+        ph: &'static str,
+        cat: &'static str,
+        name: impl Into<String>,
+        track: (u64, u64),
+    ) -> Self {
+        Self {
+            name: name.into(),
+            cat,
+            ph,
+            ts: 0.0,
+            dur: None,
+            pid: track.0,
+            tid: track.1,
+            id: None,
+            s: None,
+            bp: None,
+            args: Value::Null,
+        }
+    }
+
+    /// Name a process or thread track
+    fn metadata(name: &'static str, track: (u64, u64), value: &str) -> Self {
+        Self::new("M", "__metadata", name, track).with_args(json!({ "name": value }))
+    }
+
+    fn at(mut self, ts: f64) -> Self {
+        self.ts = ts;
+        self
+    }
+
+    fn with_dur(mut self, dur: Duration) -> Self {
+        self.dur = Some(micros(dur));
+        self
+    }
+
+    fn with_id(mut self, id: u64) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    fn with_args(mut self, args: Value) -> Self {
+        self.args = args;
+        self
+    }
+}
+
+/// A complete trace file
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Trace {
+    trace_events: Vec<TraceEvent>,
+    display_time_unit: &'static str,
+}
+
+fn micros(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1_000_000.0
+}
+
+fn task_track(task_id: TaskId) -> (u64, u64) {
+    (TASKS_PID, task_id.as_u64())
+}
+
+/// Converts a capture into trace events
+struct TraceBuilder<'a> {
+    origin: Instant,
+    names: HashMap<TaskId, &'a str>,
+    events: Vec<TraceEvent>,
+    threads: BTreeSet<ThreadIndex>,
+    /// Await points entered and not yet left, per task
+    open_awaits: HashMap<TaskId, Vec<(&'a str, Instant)>>,
+}
+
+impl<'a> TraceBuilder<'a> {
+    fn new(origin: Instant, tasks: &'a [TaskInfo]) -> Self {
+        Self {
+            origin,
+            names: tasks
+                .iter()
+                .map(|task| (task.id, task.name.as_str()))
+                .collect(),
+            events: Vec::new(),
+            threads: BTreeSet::new(),
+            open_awaits: HashMap::new(),
+        }
+    }
+
+    fn ts(&self, at: Instant) -> f64 {
+        micros(at.saturating_duration_since(self.origin))
+    }
+
+    /// When something that ended at `end` after `duration` began
+    fn began(&self, end: Instant, duration: Duration) -> Instant {
+        end.checked_sub(duration).unwrap_or(self.origin)
+    }
+
+    fn add_tasks(&mut self, tasks: &[TaskInfo]) {
+        for task in tasks {
+            let track = task_track(task.id);
+            self.events.push(TraceEvent::metadata(
+                "thread_name",
+                track,
+                &format!("{} {}", task.name, task.id),
+            ));
+            self.events.push(
+                TraceEvent::new("X", "task", task.name.as_str(), track)
+                    .at(self.ts(task.created_at))
+                    .with_dur(task.duration())
+                    .with_args(json!({
+                        "state": format!("{:?}", task.state),
+                        "polls": task.poll_count,
+                        "location": task.location,
+                    })),
+            );
+        }
+    }
+
+    fn add_event(&mut self, event: &'a Event) {
+        let track = task_track(event.task_id);
+        match &event.kind {
+            EventKind::PollEnded { duration } => {
+                let start = self.ts(self.began(event.timestamp, *duration));
+                let name = self.names.get(&event.task_id).copied().unwrap_or("poll");
+                let thread = event.thread.as_u64();
+                self.threads.insert(event.thread);
+
+                self.events.push(
+                    TraceEvent::new("X", "poll", "poll", track)
+                        .at(start)
+                        .with_dur(*duration)
+                        .with_args(json!({ "thread": thread })),
+                );
+                self.events.push(
+                    TraceEvent::new("X", "poll", name, (THREADS_PID, thread))
+                        .at(start)
+                        .with_dur(*duration)
+                        .with_args(json!({ "task": event.task_id.as_u64() })),
+                );
+            }
+            EventKind::AwaitStarted { await_point, .. } => {
+                self.open_awaits
+                    .entry(event.task_id)
+                    .or_default()
+                    .push((await_point.as_str(), event.timestamp));
+            }
+            EventKind::AwaitEnded {
+                await_point,
+                duration,
+            } => {
+                if let Some(open) = self.open_awaits.get_mut(&event.task_id) {
+                    if let Some(index) = open.iter().rposition(|(point, _)| point == await_point) {
+                        open.remove(index);
+                    }
+                }
+                let start = self.began(event.timestamp, *duration);
+                self.add_await(event.task_id, await_point, start, event.timestamp, false);
+            }
+            EventKind::InspectionPoint { label, message } => {
+                let mut instant = TraceEvent::new("i", "inspect", label.as_str(), track)
+                    .at(self.ts(event.timestamp))
+                    .with_args(json!({ "message": message }));
+                instant.s = Some("t");
+                self.events.push(instant);
+            }
+            _ => {}
+        }
+    }
+
+    /// Add an await as a slice of the task's async track
+    fn add_await(
+        &mut self,
+        task_id: TaskId,
+        point: &str,
+        start: Instant,
+        end: Instant,
+        open: bool,
+    ) {
+        let track = task_track(task_id);
+        let id = task_id.as_u64();
+        self.events.push(
+            TraceEvent::new("b", "await", point, track)
+                .at(self.ts(start))
+                .with_id(id)
+                .with_args(json!({ "unfinished": open })),
+        );
+        self.events.push(
+            TraceEvent::new("e", "await", point, track)
+                .at(self.ts(end))
+                .with_id(id),
+        );
+    }
+
+    fn add_spawns(&mut self, tasks: &[TaskInfo], relationships: &[Relationship]) {
+        let created: HashMap<TaskId, Instant> = tasks
+            .iter()
+            .map(|task| (task.id, task.created_at))
+            .collect();
+
+        for rel in relationships {
+            if rel.relationship_type != RelationshipType::Spawned
+                || !created.contains_key(&rel.from)
+            {
+                continue;
+            }
+            let Some(&spawned_at) = created.get(&rel.to) else {
+                continue;
+            };
+            let ts = self.ts(spawned_at);
+            let id = rel.to.as_u64();
+            self.events.push(
+                TraceEvent::new("s", "spawn", "spawn", task_track(rel.from))
+                    .at(ts)
+                    .with_id(id),
+            );
+            let mut end = TraceEvent::new("f", "spawn", "spawn", task_track(rel.to))
+                .at(ts)
+                .with_id(id);
+            end.bp = Some("e");
+            self.events.push(end);
+        }
+    }
+
+    fn finish(mut self, end: Instant) -> Trace {
+        // Awaits still in progress last until the end of the capture
+        let open: Vec<(TaskId, &str, Instant)> = self
+            .open_awaits
+            .drain()
+            .flat_map(|(task, awaits)| awaits.into_iter().map(move |(point, at)| (task, point, at)))
+            .collect();
+        for (task, point, start) in open {
+            self.add_await(task, point, start, end, true);
+        }
+
+        self.events.push(TraceEvent::metadata(
+            "process_name",
+            (TASKS_PID, 0),
+            "Tasks",
+        ));
+        self.events.push(TraceEvent::metadata(
+            "process_name",
+            (THREADS_PID, 0),
+            "Worker threads",
+        ));
+        for thread in &self.threads {
+            let index = thread.as_u64();
+            let name = match thread.name() {
+                Some(name) => format!("{name} #{index}"),
+                None => format!("thread #{index}"),
+            };
+            self.events.push(TraceEvent::metadata(
+                "thread_name",
+                (THREADS_PID, index),
+                &name,
+            ));
+        }
+
+        Trace {
+            trace_events: self.events,
+            display_time_unit: "ms",
+        }
+    }
+}
+
+/// Chrome Trace Event exporter, for `chrome://tracing` and Perfetto
+pub struct ChromeTraceExporter;
+
+impl ChromeTraceExporter {
+    /// Export to a trace JSON string
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the trace fails to serialize.
+    pub fn export_to_string(inspector: &Inspector) -> serde_json::Result<String> {
+        serde_json::to_string(&Self::prepare_trace(inspector))
+    }
+
+    /// Export to a trace JSON file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be created or written.
+    pub fn export_to_file<P: AsRef<Path>>(inspector: &Inspector, path: P) -> io::Result<()> {
+        let trace = Self::prepare_trace(inspector);
+        let file = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(file, &trace)?;
+        Ok(())
+    }
+
+    fn prepare_trace(inspector: &Inspector) -> Trace {
+        let tasks = inspector.get_all_tasks();
+        let relationships = inspector.relationships();
+
+        inspector.with_timeline(|timeline| {
+            let end = Instant::now();
+            let origin = tasks
+                .iter()
+                .map(|task| task.created_at)
+                .chain(timeline.iter_events().map(|event| event.timestamp))
+                .min()
+                .unwrap_or(end);
+
+            let mut builder = TraceBuilder::new(origin, &tasks);
+            builder.add_tasks(&tasks);
+            for event in timeline.iter_events() {
+                builder.add_event(event);
+            }
+            builder.add_spawns(&tasks, &relationships);
+            builder.finish(end)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events_with<'a>(trace: &'a Value, ph: &str) -> Vec<&'a Value> {
+        trace["traceEvents"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|event| event["ph"] == ph)
+            .collect()
+    }
+
+    #[test]
+    fn test_chrome_trace() {
+        let inspector = Inspector::new();
+        let parent = inspector.register_task("handler".to_string());
+        let child = inspector
+            .register_task_with_info(TaskInfo::new("fetch_user".to_string()).with_parent(parent));
+
+        inspector.poll_started(child);
+        inspector.await_started(child, "db_query".to_string(), None);
+        inspector.poll_ended(child, Duration::from_micros(50));
+        inspector.inspection_point(child, "cache_miss".to_string(), None);
+        inspector.await_ended(child, "db_query".to_string(), Duration::from_micros(80));
+        inspector.await_started(child, "send".to_string(), None);
+        inspector.task_completed(child);
+
+        let json = ChromeTraceExporter::export_to_string(&inspector).unwrap();
+        let trace: Value = serde_json::from_str(&json).unwrap();
+
+        let slices = events_with(&trace, "X");
+        assert!(slices.iter().any(|e| e["name"] == "handler"));
+        assert!(slices
+            .iter()
+            .any(|e| e["name"] == "fetch_user" && e["pid"] == THREADS_PID));
+
+        let awaits = events_with(&trace, "b");
+        assert_eq!(awaits.len(), 2);
+        assert!(awaits
+            .iter()
+            .any(|e| e["name"] == "send" && e["args"]["unfinished"] == true));
+        assert_eq!(events_with(&trace, "e").len(), 2);
+
+        let points = events_with(&trace, "i");
+        assert_eq!(points.len(), 1);
+        assert_eq!(points[0]["name"], "cache_miss");
+
+        let flow = events_with(&trace, "s");
+        assert_eq!(flow.len(), 1);
+        assert_eq!(flow[0]["tid"], parent.as_u64());
+        assert_eq!(events_with(&trace, "f")[0]["tid"], child.as_u64());
+
+        assert!(events_with(&trace, "M")
+            .iter()
+            .any(|e| e["args"]["name"] == format!("fetch_user {child}")));
+    }
+}
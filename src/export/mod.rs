@@ -1,12 +1,19 @@
 //! Export functionality for various formats
 //!
 //! This module provides exporters for task data in industry-standard formats
-//! like JSON, CSV, and others.
+//! like JSON, CSV, and the Chrome Trace Event format.
 
+mod chrome;
+
+pub use chrome::ChromeTraceExporter;
+
+use crate::deadlock::ResourceId;
+use crate::graph::GraphEdge;
 use crate::inspector::Inspector;
-use crate::task::TaskInfo;
+use crate::task::{TaskId, TaskInfo, TaskState};
 use crate::timeline::{Event, EventKind};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::{self, Write};
 use std::path::Path;
@@ -19,7 +26,7 @@ pub struct ExportTask {
     /// Task name (function name)
     pub name: String,
     /// Current task state (Running, Blocked, etc.)
-    pub state: String,
+    pub state: TaskState,
     /// Task creation timestamp in milliseconds
     pub created_at_ms: u128,
     /// Total duration in milliseconds
@@ -30,6 +37,11 @@ pub struct ExportTask {
     pub run_time_ms: f64,
     /// Parent task ID if this is a spawned task
     pub parent_id: Option<u64>,
+    /// How the task relates to its parent (awaited or spawned)
+    pub child_kind: Option<String>,
+    /// Recorded task attributes
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, String>,
 }
 
 impl From<&TaskInfo> for ExportTask {
@@ -37,12 +49,14 @@ impl From<&TaskInfo> for ExportTask {
         Self {
             id: task.id.as_u64(),
             name: task.name.clone(),
-            state: format!("{:?}", task.state),
+            state: task.state.clone(),
             created_at_ms: task.created_at.elapsed().as_millis(),
-            duration_ms: task.age().as_secs_f64() * 1000.0,
+            duration_ms: task.duration().as_secs_f64() * 1000.0,
             poll_count: task.poll_count,
             run_time_ms: task.total_run_time.as_secs_f64() * 1000.0,
             parent_id: task.parent.map(|id| id.as_u64()),
+            child_kind: task.child_kind.map(|kind| kind.to_string()),
+            fields: task.fields.iter().cloned().collect(),
         }
     }
 }
@@ -63,6 +77,7 @@ pub struct ExportEvent {
 }
 
 impl From<&Event> for ExportEvent {
+    #[allow(clippy::too_many_lines)] // One arm per event kind
     fn from(event: &Event) -> Self {
         let (kind, details) = match &event.kind {
             EventKind::TaskSpawned {
@@ -81,6 +96,17 @@ impl From<&Event> for ExportEvent {
                 "PollEnded".to_string(),
                 Some(format!("duration={}ms", duration.as_secs_f64() * 1000.0)),
             ),
+            EventKind::LongPoll {
+                duration,
+                await_point,
+                location,
+            } => (
+                "LongPoll".to_string(),
+                Some(format!(
+                    "duration={}ms, point={await_point:?}, location={location:?}",
+                    duration.as_secs_f64() * 1000.0
+                )),
+            ),
             EventKind::AwaitStarted {
                 await_point,
                 location,
@@ -107,6 +133,42 @@ impl From<&Event> for ExportEvent {
                 "TaskFailed".to_string(),
                 error.as_ref().map(|e| format!("error={}", e)),
             ),
+            EventKind::TaskWoken { by_ref, self_wake } => (
+                "TaskWoken".to_string(),
+                Some(format!("by_ref={by_ref}, self_wake={self_wake}")),
+            ),
+            EventKind::WakerCloned => ("WakerCloned".to_string(), None),
+            EventKind::WakerDropped => ("WakerDropped".to_string(), None),
+            EventKind::TaskScheduled { latency } => (
+                "TaskScheduled".to_string(),
+                Some(format!("latency={}ms", latency.as_secs_f64() * 1000.0)),
+            ),
+            EventKind::TaskStalled {
+                await_point,
+                duration,
+            } => (
+                "TaskStalled".to_string(),
+                Some(format!(
+                    "point={await_point}, duration={}ms",
+                    duration.as_secs_f64() * 1000.0
+                )),
+            ),
+            EventKind::DeadlockDetected { tasks, resources } => (
+                "DeadlockDetected".to_string(),
+                Some(format!(
+                    "tasks={:?}, resources={:?}",
+                    tasks.iter().map(TaskId::as_u64).collect::<Vec<_>>(),
+                    resources.iter().map(ResourceId::as_u64).collect::<Vec<_>>()
+                )),
+            ),
+            EventKind::TaskPanicked { message } => (
+                "TaskPanicked".to_string(),
+                Some(format!("message={message}")),
+            ),
+            EventKind::TaskCancelled { duration } => (
+                "TaskCancelled".to_string(),
+                Some(format!("duration={}ms", duration.as_secs_f64() * 1000.0)),
+            ),
             EventKind::InspectionPoint { label, message } => (
                 "InspectionPoint".to_string(),
                 Some(format!("label={}, message={:?}", label, message)),
@@ -121,7 +183,7 @@ impl From<&Event> for ExportEvent {
         };
 
         Self {
-            event_id: 0, // Event IDs are internal, use 0 for export
+            event_id: event.id.as_u64(),
             task_id: event.task_id.as_u64(),
             timestamp_ms: event.timestamp.elapsed().as_millis(),
             kind,
@@ -137,6 +199,9 @@ pub struct ExportData {
     pub tasks: Vec<ExportTask>,
     /// List of all events
     pub events: Vec<ExportEvent>,
+    /// Relationships recorded between tasks
+    #[serde(default)]
+    pub relationships: Vec<GraphEdge>,
     /// Export metadata
     pub metadata: ExportMetadata,
 }
@@ -182,9 +247,12 @@ impl JsonExporter {
             .collect();
 
         let events: Vec<ExportEvent> = inspector
-            .get_events()
+            .with_timeline(|timeline| timeline.iter_events().map(ExportEvent::from).collect());
+
+        let relationships: Vec<GraphEdge> = inspector
+            .relationships()
             .iter()
-            .map(ExportEvent::from)
+            .map(GraphEdge::from)
             .collect();
 
         let stats = inspector.stats();
@@ -192,6 +260,7 @@ impl JsonExporter {
         ExportData {
             tasks,
             events,
+            relationships,
             metadata: ExportMetadata {
                 version: env!("CARGO_PKG_VERSION").to_string(),
                 timestamp: chrono::Utc::now().to_rfc3339(),
@@ -214,7 +283,7 @@ impl CsvExporter {
         // Write header
         writeln!(
             file,
-            "id,name,state,created_at_ms,duration_ms,poll_count,run_time_ms,parent_id"
+            "id,name,state,created_at_ms,duration_ms,poll_count,run_time_ms,parent_id,child_kind"
         )?;
 
         // Write tasks
@@ -222,17 +291,18 @@ impl CsvExporter {
             let export_task = ExportTask::from(&task);
             writeln!(
                 file,
-                "{},{},{},{},{},{},{},{}",
+                "{},{},{},{},{},{},{},{},{}",
                 export_task.id,
                 Self::escape_csv(&export_task.name),
-                export_task.state,
+                Self::escape_csv(&format!("{:?}", export_task.state)),
                 export_task.created_at_ms,
                 export_task.duration_ms,
                 export_task.poll_count,
                 export_task.run_time_ms,
                 export_task
                     .parent_id
-                    .map_or("".to_string(), |id| id.to_string())
+                    .map_or("".to_string(), |id| id.to_string()),
+                export_task.child_kind.as_deref().unwrap_or("")
             )?;
         }
 
@@ -246,9 +316,12 @@ impl CsvExporter {
         // Write header
         writeln!(file, "event_id,task_id,timestamp_ms,kind,details")?;
 
+        // Convert under the timeline lock, write once it is released
+        let events: Vec<ExportEvent> = inspector
+            .with_timeline(|timeline| timeline.iter_events().map(ExportEvent::from).collect());
+
         // Write events
-        for event in inspector.get_events() {
-            let export_event = ExportEvent::from(&event);
+        for export_event in events {
             writeln!(
                 file,
                 "{},{},{},{},{}",
@@ -0,0 +1,598 @@
+//! Time-weighted critical path analysis
+//!
+//! [`TaskGraph::find_critical_path`] counts hops. The analysis here walks
+//! back from the moment a root task finished, using timeline timestamps to
+//! find what held it up at each point: a child it awaited inline, a join
+//! handle of a spawned task, or one of its own await points. The result is
+//! the chain of tasks and await points that determined the root's
+//! end-to-end latency, split into timed segments, along with the slack of
+//! the awaited tasks that finished early enough not to matter.
+
+use super::{RelationshipType, TaskGraph};
+use crate::task::{ChildKind, TaskId, TaskInfo};
+use crate::timeline::{EventKind, Timeline};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+/// Await point recorded on a task while it waits on a spawned task's
+/// join handle
+pub(crate) fn join_point(task: &TaskInfo) -> String {
+    format!("join {} {}", task.name, task.id)
+}
+
+/// What a task was doing during a segment of the critical path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SegmentKind {
+    /// Executing or ready to run, outside any recorded await point
+    Running,
+    /// Suspended at an await point
+    Awaiting {
+        /// Name of the await point
+        await_point: String,
+    },
+}
+
+/// A stretch of the critical path spent in one task
+#[derive(Debug, Clone)]
+pub struct PathSegment {
+    /// Task the time was spent in
+    pub task_id: TaskId,
+
+    /// Task name
+    pub task_name: String,
+
+    /// What the task was doing
+    pub kind: SegmentKind,
+
+    /// Offset of the segment from the root task's start
+    pub start: Duration,
+
+    /// Length of the segment
+    pub duration: Duration,
+}
+
+/// An awaited task that finished off the critical path
+#[derive(Debug, Clone)]
+pub struct TaskSlack {
+    /// The awaited task
+    pub task_id: TaskId,
+
+    /// Task name
+    pub task_name: String,
+
+    /// Task on the critical path that awaited it
+    pub awaited_by: TaskId,
+
+    /// Offset from the root task's start at which it finished
+    pub finished_at: Duration,
+
+    /// How much later it could have finished without delaying the root
+    pub slack: Duration,
+}
+
+/// Chain of tasks and await points that determined a task's latency
+#[derive(Debug, Clone)]
+pub struct CriticalPath {
+    /// Task the path was computed for
+    pub root: TaskId,
+
+    /// Time from the root's spawn to its end, or to now if still running
+    pub latency: Duration,
+
+    /// Segments of the path, in chronological order, covering the latency
+    pub segments: Vec<PathSegment>,
+
+    /// Awaited tasks off the path, by increasing slack
+    pub slack: Vec<TaskSlack>,
+}
+
+impl CriticalPath {
+    /// Tasks on the path, in the order they first appear
+    pub fn tasks(&self) -> Vec<TaskId> {
+        let mut seen = HashSet::new();
+        self.segments
+            .iter()
+            .map(|segment| segment.task_id)
+            .filter(|task| seen.insert(*task))
+            .collect()
+    }
+
+    /// Total time the path spent in a task
+    pub fn time_in(&self, task_id: TaskId) -> Duration {
+        self.segments
+            .iter()
+            .filter(|segment| segment.task_id == task_id)
+            .map(|segment| segment.duration)
+            .sum()
+    }
+
+    /// Get a human-readable description of the path
+    pub fn describe(&self) -> String {
+        let mut desc = String::new();
+        writeln!(
+            desc,
+            "Critical path of task {} ({}):",
+            self.root,
+            format_ms(self.latency)
+        )
+        .unwrap();
+
+        for segment in &self.segments {
+            let activity = match &segment.kind {
+                SegmentKind::Running => "running".to_string(),
+                SegmentKind::Awaiting { await_point } => format!("awaiting {await_point}"),
+            };
+            writeln!(
+                desc,
+                "  +{:<10} {} {} {activity} for {}",
+                format_ms(segment.start),
+                segment.task_id,
+                segment.task_name,
+                format_ms(segment.duration)
+            )
+            .unwrap();
+        }
+
+        if !self.slack.is_empty() {
+            writeln!(desc, "Slack:").unwrap();
+            for slack in &self.slack {
+                writeln!(
+                    desc,
+                    "  {} {} finished at +{}, {} before {} needed it",
+                    slack.task_id,
+                    slack.task_name,
+                    format_ms(slack.finished_at),
+                    format_ms(slack.slack),
+                    slack.awaited_by
+                )
+                .unwrap();
+            }
+        }
+
+        desc
+    }
+}
+
+fn format_ms(duration: Duration) -> String {
+    format!("{:.2}ms", duration.as_secs_f64() * 1000.0)
+}
+
+/// An await point of a task, open if it never ended
+struct AwaitSpan {
+    await_point: String,
+    start: Instant,
+    end: Option<Instant>,
+}
+
+/// What the timeline recorded about one task
+struct Timing {
+    awaits: Vec<AwaitSpan>,
+    finished: Option<Instant>,
+}
+
+impl Timing {
+    fn of(task: &TaskInfo, timeline: &Timeline) -> Self {
+        let mut awaits: Vec<AwaitSpan> = Vec::new();
+        let mut finished = None;
+
+        for event in timeline.events_for_task(task.id) {
+            match &event.kind {
+                EventKind::AwaitStarted { await_point, .. } => awaits.push(AwaitSpan {
+                    await_point: await_point.clone(),
+                    start: event.timestamp,
+                    end: None,
+                }),
+                EventKind::AwaitEnded { await_point, .. } => {
+                    if let Some(span) = awaits
+                        .iter_mut()
+                        .rev()
+                        .find(|span| span.end.is_none() && span.await_point == *await_point)
+                    {
+                        span.end = Some(event.timestamp);
+                    }
+                }
+                kind if kind.is_terminal() => finished = Some(event.timestamp),
+                _ => {}
+            }
+        }
+
+        // The terminal event may have been evicted
+        if finished.is_none() && task.state.is_terminal() {
+            finished = Some(task.last_updated);
+        }
+
+        Self { awaits, finished }
+    }
+}
+
+/// A task awaited by another
+struct Join {
+    child: TaskId,
+    started: Instant,
+    finished: Instant,
+    /// When the awaiting task started waiting, if it had to
+    waited_from: Option<Instant>,
+}
+
+struct Analysis<'a> {
+    graph: &'a TaskGraph,
+    timeline: &'a Timeline,
+    origin: Instant,
+    timings: HashMap<TaskId, Timing>,
+    /// Segments found so far, latest first
+    segments: Vec<PathSegment>,
+    on_path: HashSet<TaskId>,
+    /// For each task on the path, the times it resumed on the path and the
+    /// tasks it awaited
+    visited: Vec<(TaskId, Vec<Instant>, Vec<Join>)>,
+}
+
+impl Analysis<'_> {
+    fn timing(&mut self, task: &TaskInfo) -> &Timing {
+        self.timings
+            .entry(task.id)
+            .or_insert_with(|| Timing::of(task, self.timeline))
+    }
+
+    /// Tasks awaited by a task that have finished
+    fn joins(&mut self, task_id: TaskId) -> Vec<Join> {
+        let graph = self.graph;
+        let mut joins = Vec::new();
+
+        for (child_id, relationship) in graph.get_related_tasks(task_id) {
+            if relationship != RelationshipType::AwaitsOn {
+                continue;
+            }
+            let (Some(parent), Some(child)) = (graph.get_task(&task_id), graph.get_task(&child_id))
+            else {
+                continue;
+            };
+            let Some(finished) = self.timing(child).finished else {
+                continue;
+            };
+
+            // A child awaited inline holds its parent up for its whole life;
+            // a join handle only from the await recorded on the parent
+            let waited_from =
+                if child.parent == Some(task_id) && child.child_kind == Some(ChildKind::Inline) {
+                    Some(child.created_at)
+                } else {
+                    let point = join_point(child);
+                    self.timing(parent)
+                        .awaits
+                        .iter()
+                        .find(|span| span.await_point == point)
+                        .map(|span| span.start)
+                };
+
+            joins.push(Join {
+                child: child_id,
+                started: child.created_at,
+                finished,
+                waited_from,
+            });
+        }
+
+        joins
+    }
+
+    /// Walk back through a task from `end`
+    fn walk(&mut self, task_id: TaskId, end: Instant) {
+        let graph = self.graph;
+        let Some(task) = graph.get_task(&task_id) else {
+            return;
+        };
+        self.on_path.insert(task_id);
+
+        let start = task.created_at;
+        let joins = self.joins(task_id);
+        let mut resumed = vec![end];
+        let mut cursor = end;
+
+        // The task resumed when the last of the tasks it was waiting on
+        // finished; before that, the path runs through that task
+        while cursor > start {
+            let critical = joins
+                .iter()
+                .filter(|join| {
+                    !self.on_path.contains(&join.child)
+                        && join.finished <= cursor
+                        && join.finished > start
+                        && join.waited_from.is_some_and(|from| from < join.finished)
+                })
+                .max_by_key(|join| join.finished);
+
+            let Some(join) = critical else {
+                break;
+            };
+
+            self.own_segments(task, join.finished, cursor);
+            resumed.push(join.finished);
+            let (child, finished, started) = (join.child, join.finished, join.started);
+            self.walk(child, finished);
+            cursor = started.max(start);
+        }
+
+        self.own_segments(task, start, cursor);
+        self.visited.push((task_id, resumed, joins));
+    }
+
+    /// Record the time a task spent on its own between `from` and `to`,
+    /// split at its await points
+    fn own_segments(&mut self, task: &TaskInfo, from: Instant, to: Instant) {
+        if to <= from {
+            return;
+        }
+
+        let mut spans: Vec<(Instant, Instant, String)> = self
+            .timing(task)
+            .awaits
+            .iter()
+            .filter_map(|span| {
+                let start = span.start.max(from);
+                let end = span.end.unwrap_or(to).min(to);
+                (start < end).then(|| (start, end, span.await_point.clone()))
+            })
+            .collect();
+        spans.sort_by_key(|(start, _, _)| *start);
+
+        let mut forward = Vec::new();
+        let mut cursor = from;
+        for (start, end, await_point) in spans {
+            // Nested await points are covered by the outer one
+            if end <= cursor {
+                continue;
+            }
+            if start > cursor {
+                forward.push((cursor, start, SegmentKind::Running));
+            }
+            let start = start.max(cursor);
+            forward.push((start, end, SegmentKind::Awaiting { await_point }));
+            cursor = end;
+        }
+        if cursor < to {
+            forward.push((cursor, to, SegmentKind::Running));
+        }
+
+        for (start, end, kind) in forward.into_iter().rev() {
+            self.segments.push(PathSegment {
+                task_id: task.id,
+                task_name: task.name.clone(),
+                kind,
+                start: start.saturating_duration_since(self.origin),
+                duration: end - start,
+            });
+        }
+    }
+
+    /// Slack of the awaited tasks left off the path
+    fn slack(&self) -> Vec<TaskSlack> {
+        let mut slack = Vec::new();
+
+        for (task_id, resumed, joins) in &self.visited {
+            for join in joins {
+                if self.on_path.contains(&join.child) {
+                    continue;
+                }
+                // Needed by the next point the awaiting task resumed on the path
+                let Some(needed) = resumed.iter().filter(|at| **at >= join.finished).min() else {
+                    continue;
+                };
+                let Some(child) = self.graph.get_task(&join.child) else {
+                    continue;
+                };
+                slack.push(TaskSlack {
+                    task_id: join.child,
+                    task_name: child.name.clone(),
+                    awaited_by: *task_id,
+                    finished_at: join.finished.saturating_duration_since(self.origin),
+                    slack: *needed - join.finished,
+                });
+            }
+        }
+
+        slack.sort_by_key(|slack| (slack.slack, slack.task_id.as_u64()));
+        slack
+    }
+}
+
+impl TaskGraph {
+    /// Find the chain of tasks and await points that determined a task's
+    /// latency, using the timestamps recorded in a timeline
+    ///
+    /// Follows [`RelationshipType::AwaitsOn`] relationships to children
+    /// awaited inline and to spawned tasks whose join handle was awaited.
+    /// The latency of a task still running is measured up to now. Returns
+    /// `None` if the task is not in the graph.
+    pub fn find_timed_critical_path(
+        &self,
+        root: TaskId,
+        timeline: &Timeline,
+    ) -> Option<CriticalPath> {
+        let task = self.get_task(&root)?;
+        let mut analysis = Analysis {
+            graph: self,
+            timeline,
+            origin: task.created_at,
+            timings: HashMap::new(),
+            segments: Vec::new(),
+            on_path: HashSet::new(),
+            visited: Vec::new(),
+        };
+
+        let end = analysis.timing(task).finished.unwrap_or_else(Instant::now);
+        analysis.walk(root, end);
+
+        let slack = analysis.slack();
+        let mut segments = analysis.segments;
+        segments.reverse();
+
+        Some(CriticalPath {
+            root,
+            latency: end.saturating_duration_since(task.created_at),
+            segments,
+            slack,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::Relationship;
+    use crate::task::TaskState;
+    use crate::timeline::Event;
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    struct Trace {
+        origin: Instant,
+        graph: TaskGraph,
+        timeline: Timeline,
+        events: u64,
+    }
+
+    impl Trace {
+        fn new() -> Self {
+            Self {
+                origin: Instant::now(),
+                graph: TaskGraph::new(),
+                timeline: Timeline::new(),
+                events: 0,
+            }
+        }
+
+        fn task(
+            &mut self,
+            name: &str,
+            start: u64,
+            end: u64,
+            parent: Option<(TaskId, ChildKind)>,
+        ) -> TaskId {
+            let mut task = TaskInfo::new(name.to_string());
+            if let Some((parent, kind)) = parent {
+                task = task.with_parent_kind(parent, kind);
+                self.graph.add_relationship(Relationship::new(
+                    parent,
+                    task.id,
+                    RelationshipType::AwaitsOn,
+                ));
+            }
+            task.created_at = self.origin + ms(start);
+            task.state = TaskState::Completed;
+            let id = task.id;
+            self.graph.add_task(task);
+            self.event(
+                id,
+                end,
+                EventKind::TaskCompleted {
+                    duration: ms(end - start),
+                },
+            );
+            id
+        }
+
+        fn await_point(&mut self, task: TaskId, point: &str, start: u64, end: u64) {
+            let await_point = point.to_string();
+            self.event(
+                task,
+                start,
+                EventKind::AwaitStarted {
+                    await_point: await_point.clone(),
+                    location: None,
+                },
+            );
+            self.event(
+                task,
+                end,
+                EventKind::AwaitEnded {
+                    await_point,
+                    duration: ms(end - start),
+                },
+            );
+        }
+
+        fn event(&mut self, task: TaskId, at: u64, kind: EventKind) {
+            self.events += 1;
+            let mut event = Event::new(self.events, task, kind);
+            event.timestamp = self.origin + ms(at);
+            self.timeline.add_event(event);
+        }
+    }
+
+    #[test]
+    fn test_path_follows_the_last_child_to_finish() {
+        let mut trace = Trace::new();
+        let root = trace.task("root", 0, 100, None);
+        let spawned = Some((root, ChildKind::Spawned));
+        let fast = trace.task("fast", 10, 30, spawned);
+        let slow = trace.task("slow", 10, 80, spawned);
+        let slow_io = trace.task("slow_io", 20, 70, Some((slow, ChildKind::Inline)));
+        trace.await_point(slow_io, "db::query", 25, 70);
+
+        // The root joins both handles, finding `fast` already done
+        let slow_info = trace.graph.get_task(&slow).unwrap().clone();
+        trace.await_point(root, &join_point(&slow_info), 40, 85);
+
+        let path = trace
+            .graph
+            .find_timed_critical_path(root, &trace.timeline)
+            .unwrap();
+        assert_eq!(path.latency, ms(100));
+        assert_eq!(path.tasks(), vec![root, slow, slow_io]);
+        assert_eq!(
+            path.segments.iter().map(|s| s.duration).sum::<Duration>(),
+            ms(100)
+        );
+
+        let kinds: Vec<(TaskId, Duration, &SegmentKind)> = path
+            .segments
+            .iter()
+            .map(|s| (s.task_id, s.start, &s.kind))
+            .collect();
+        let query = SegmentKind::Awaiting {
+            await_point: "db::query".to_string(),
+        };
+        assert_eq!(kinds[0], (root, ms(0), &SegmentKind::Running));
+        assert_eq!(kinds[1], (slow, ms(10), &SegmentKind::Running));
+        assert_eq!(kinds[2], (slow_io, ms(20), &SegmentKind::Running));
+        assert_eq!(kinds[3], (slow_io, ms(25), &query));
+        assert_eq!(kinds[4], (slow, ms(70), &SegmentKind::Running));
+        assert!(
+            matches!(kinds[5], (task, at, SegmentKind::Awaiting { .. }) if task == root && at == ms(80))
+        );
+        assert_eq!(kinds[6], (root, ms(85), &SegmentKind::Running));
+
+        assert_eq!(path.time_in(slow_io), ms(50));
+        assert_eq!(path.slack.len(), 1);
+        assert_eq!(path.slack[0].task_id, fast);
+        assert_eq!(path.slack[0].awaited_by, root);
+        assert_eq!(path.slack[0].slack, ms(50));
+        assert!(path.describe().contains("awaiting db::query for 45.00ms"));
+    }
+
+    #[test]
+    fn test_children_finished_before_the_join_are_off_the_path() {
+        let mut trace = Trace::new();
+        let root = trace.task("root", 0, 50, None);
+        let child = trace.task("child", 5, 10, Some((root, ChildKind::Spawned)));
+        trace.await_point(root, "sleep", 0, 40);
+
+        let path = trace
+            .graph
+            .find_timed_critical_path(root, &trace.timeline)
+            .unwrap();
+        assert_eq!(path.tasks(), vec![root]);
+        assert_eq!(path.segments.len(), 2);
+        assert_eq!(path.slack[0].task_id, child);
+        assert_eq!(path.slack[0].slack, ms(40));
+
+        assert!(trace
+            .graph
+            .find_timed_critical_path(TaskId::new(), &trace.timeline)
+            .is_none());
+    }
+}
@@ -0,0 +1,512 @@
+//! Graph export formats
+//!
+//! Besides DOT and text, a [`TaskGraph`] renders as a Mermaid flowchart, as
+//! `GraphML`, or as a serde-serializable node/edge model ([`GraphData`]) for
+//! JSON. A graph can also be rebuilt from a trace saved by
+//! [`JsonExporter`](crate::export::JsonExporter).
+
+use super::{Relationship, RelationshipType, TaskGraph};
+use crate::export::ExportData;
+use crate::task::{ChildKind, TaskId, TaskInfo, TaskState};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+/// A task in the JSON graph model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphNode {
+    /// Task ID
+    pub id: u64,
+    /// Task name
+    pub name: String,
+    /// Task state (Running, Blocked, etc.)
+    pub state: String,
+    /// How long the task ran, in milliseconds
+    pub duration_ms: f64,
+    /// Time spent being polled, in milliseconds
+    pub run_time_ms: f64,
+    /// Number of times the task was polled
+    pub poll_count: u64,
+    /// Parent task ID, if any
+    pub parent_id: Option<u64>,
+}
+
+impl From<&TaskInfo> for GraphNode {
+    fn from(task: &TaskInfo) -> Self {
+        Self {
+            id: task.id.as_u64(),
+            name: task.name.clone(),
+            state: format!("{:?}", task.state),
+            duration_ms: task.duration().as_secs_f64() * 1000.0,
+            run_time_ms: task.total_run_time.as_secs_f64() * 1000.0,
+            poll_count: task.poll_count,
+            parent_id: task.parent.map(|id| id.as_u64()),
+        }
+    }
+}
+
+/// A relationship in the JSON graph model
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphEdge {
+    /// Source task ID
+    pub from: u64,
+    /// Target task ID
+    pub to: u64,
+    /// Type of relationship
+    pub relationship: RelationshipType,
+    /// Resource the relationship is about, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource: Option<String>,
+    /// Data passed along the relationship, if described
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+}
+
+impl From<&Relationship> for GraphEdge {
+    fn from(relationship: &Relationship) -> Self {
+        Self {
+            from: relationship.from.as_u64(),
+            to: relationship.to.as_u64(),
+            relationship: relationship.relationship_type,
+            resource: relationship.resource_name.clone(),
+            data: relationship.data_description.clone(),
+        }
+    }
+}
+
+impl From<&GraphEdge> for Relationship {
+    fn from(edge: &GraphEdge) -> Self {
+        Self {
+            from: TaskId::from_u64(edge.from),
+            to: TaskId::from_u64(edge.to),
+            relationship_type: edge.relationship,
+            resource_name: edge.resource.clone(),
+            data_description: edge.data.clone(),
+        }
+    }
+}
+
+/// Serializable node/edge view of a task graph
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphData {
+    /// Tasks, by increasing ID
+    pub nodes: Vec<GraphNode>,
+    /// Relationships, in the order they were recorded
+    pub edges: Vec<GraphEdge>,
+}
+
+/// Short edge label shared by the Mermaid and `GraphML` output
+fn edge_label(relationship_type: RelationshipType) -> &'static str {
+    match relationship_type {
+        RelationshipType::Spawned => "spawned",
+        RelationshipType::ChannelSend => "sends",
+        RelationshipType::ChannelReceive => "receives",
+        RelationshipType::SharedResource => "shares",
+        RelationshipType::DataFlow => "data",
+        RelationshipType::AwaitsOn => "awaits",
+        RelationshipType::Dependency => "depends",
+    }
+}
+
+/// Escape text for a quoted Mermaid label
+fn escape_mermaid(text: &str) -> String {
+    text.replace('"', "#quot;")
+        .replace('|', "#124;")
+        .replace('<', "#lt;")
+        .replace('>', "#gt;")
+}
+
+/// Escape text for XML content and attributes
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Convert exported milliseconds back to a duration
+///
+/// Negative, non-finite and out of range values become zero.
+fn from_millis(ms: f64) -> Duration {
+    Duration::try_from_secs_f64(ms / 1000.0).unwrap_or_default()
+}
+
+impl TaskGraph {
+    /// Rebuild a graph from an exported trace
+    ///
+    /// Traces saved without relationships get `Spawned` and `AwaitsOn`
+    /// relationships from the tasks' parents. Task timings are rebuilt
+    /// relative to now: finished tasks keep their duration, and tasks still
+    /// running when the trace was saved keep aging from there.
+    pub fn from_export(data: &ExportData) -> Self {
+        let mut graph = Self::new();
+        let now = Instant::now();
+
+        for exported in &data.tasks {
+            let mut task = TaskInfo::new(exported.name.clone());
+            task.id = TaskId::from_u64(exported.id);
+            task.state = exported.state.clone();
+            task.created_at = now
+                .checked_sub(from_millis(exported.duration_ms))
+                .unwrap_or(now);
+            task.last_updated = now;
+            task.poll_count = exported.poll_count;
+            task.total_run_time = from_millis(exported.run_time_ms);
+            task.parent = exported.parent_id.map(TaskId::from_u64);
+            task.child_kind = match exported.child_kind.as_deref() {
+                Some("awaited") => Some(ChildKind::Inline),
+                Some(_) => Some(ChildKind::Spawned),
+                None => None,
+            };
+            task.fields = exported
+                .fields
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect();
+
+            if data.relationships.is_empty() {
+                if let Some(parent) = task.parent {
+                    let kind = match task.child_kind {
+                        Some(ChildKind::Inline) => RelationshipType::AwaitsOn,
+                        _ => RelationshipType::Spawned,
+                    };
+                    graph.add_relationship(Relationship::new(parent, task.id, kind));
+                }
+            }
+            graph.add_task(task);
+        }
+
+        for edge in &data.relationships {
+            graph.add_relationship(edge.into());
+        }
+
+        graph
+    }
+
+    /// Tasks sorted by ID, for stable output
+    fn sorted_tasks(&self) -> Vec<&TaskInfo> {
+        let mut tasks: Vec<&TaskInfo> = self.tasks.values().collect();
+        tasks.sort_by_key(|task| task.id.as_u64());
+        tasks
+    }
+
+    /// Get the serializable node/edge view of the graph
+    pub fn to_graph_data(&self) -> GraphData {
+        GraphData {
+            nodes: self
+                .sorted_tasks()
+                .into_iter()
+                .map(GraphNode::from)
+                .collect(),
+            edges: self.relationships.iter().map(GraphEdge::from).collect(),
+        }
+    }
+
+    /// Generate the JSON node/edge model
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization fails.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(&self.to_graph_data())
+    }
+
+    /// Generate a Mermaid flowchart
+    pub fn to_mermaid(&self) -> String {
+        let mut mermaid = String::from("flowchart LR\n");
+
+        for task in self.sorted_tasks() {
+            let class = match task.state {
+                TaskState::Pending => "pending",
+                TaskState::Running => "running",
+                TaskState::Blocked { .. } => "blocked",
+                TaskState::Completed => "completed",
+                TaskState::Failed { .. } => "failed",
+                TaskState::Panicked { .. } => "panicked",
+                TaskState::Cancelled => "cancelled",
+            };
+            writeln!(
+                mermaid,
+                "    t{}[\"{}<br/>{:.2}ms\"]:::{class}",
+                task.id.as_u64(),
+                escape_mermaid(&task.name),
+                task.duration().as_secs_f64() * 1000.0
+            )
+            .unwrap();
+        }
+
+        for rel in &self.relationships {
+            let arrow = match rel.relationship_type {
+                RelationshipType::AwaitsOn | RelationshipType::Dependency => "==>",
+                RelationshipType::ChannelSend
+                | RelationshipType::ChannelReceive
+                | RelationshipType::SharedResource => "-.->",
+                RelationshipType::Spawned | RelationshipType::DataFlow => "-->",
+            };
+            let mut label = edge_label(rel.relationship_type).to_string();
+            if let Some(resource) = &rel.resource_name {
+                write!(label, "<br/>{}", escape_mermaid(resource)).unwrap();
+            }
+            writeln!(
+                mermaid,
+                "    t{} {arrow}|\"{label}\"| t{}",
+                rel.from.as_u64(),
+                rel.to.as_u64()
+            )
+            .unwrap();
+        }
+
+        for (class, color) in [
+            ("pending", "#d3d3d3"),
+            ("running", "#add8e6"),
+            ("blocked", "#ffff99"),
+            ("completed", "#90ee90"),
+            ("failed", "#f08080"),
+            ("panicked", "#cd5c5c"),
+            ("cancelled", "#dda0dd"),
+        ] {
+            writeln!(mermaid, "    classDef {class} fill:{color}").unwrap();
+        }
+        mermaid
+    }
+
+    /// Generate `GraphML`
+    pub fn to_graphml(&self) -> String {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+        for (id, target, name, kind) in [
+            ("name", "node", "name", "string"),
+            ("state", "node", "state", "string"),
+            ("duration_ms", "node", "duration_ms", "double"),
+            ("run_time_ms", "node", "run_time_ms", "double"),
+            ("poll_count", "node", "poll_count", "long"),
+            ("relationship", "edge", "relationship", "string"),
+            ("resource", "edge", "resource", "string"),
+            ("data", "edge", "data", "string"),
+        ] {
+            writeln!(
+                xml,
+                "  <key id=\"{id}\" for=\"{target}\" attr.name=\"{name}\" attr.type=\"{kind}\"/>"
+            )
+            .unwrap();
+        }
+        xml.push_str("  <graph id=\"TaskGraph\" edgedefault=\"directed\">\n");
+
+        for node in self.to_graph_data().nodes {
+            writeln!(xml, "    <node id=\"t{}\">", node.id).unwrap();
+            writeln!(
+                xml,
+                "      <data key=\"name\">{}</data>",
+                escape_xml(&node.name)
+            )
+            .unwrap();
+            writeln!(
+                xml,
+                "      <data key=\"state\">{}</data>",
+                escape_xml(&node.state)
+            )
+            .unwrap();
+            writeln!(
+                xml,
+                "      <data key=\"duration_ms\">{}</data>",
+                node.duration_ms
+            )
+            .unwrap();
+            writeln!(
+                xml,
+                "      <data key=\"run_time_ms\">{}</data>",
+                node.run_time_ms
+            )
+            .unwrap();
+            writeln!(
+                xml,
+                "      <data key=\"poll_count\">{}</data>",
+                node.poll_count
+            )
+            .unwrap();
+            xml.push_str("    </node>\n");
+        }
+
+        for (i, rel) in self.relationships.iter().enumerate() {
+            writeln!(
+                xml,
+                "    <edge id=\"e{i}\" source=\"t{}\" target=\"t{}\">",
+                rel.from.as_u64(),
+                rel.to.as_u64()
+            )
+            .unwrap();
+            writeln!(
+                xml,
+                "      <data key=\"relationship\">{}</data>",
+                edge_label(rel.relationship_type)
+            )
+            .unwrap();
+            if let Some(resource) = &rel.resource_name {
+                writeln!(
+                    xml,
+                    "      <data key=\"resource\">{}</data>",
+                    escape_xml(resource)
+                )
+                .unwrap();
+            }
+            if let Some(data) = &rel.data_description {
+                writeln!(xml, "      <data key=\"data\">{}</data>", escape_xml(data)).unwrap();
+            }
+            xml.push_str("    </edge>\n");
+        }
+
+        xml.push_str("  </graph>\n</graphml>\n");
+        xml
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::{ExportMetadata, ExportTask};
+
+    fn sample() -> (TaskGraph, TaskId, TaskId) {
+        let mut graph = TaskGraph::new();
+        let mut parent = TaskInfo::new("server".to_string());
+        parent.state = TaskState::Completed;
+        let child = TaskInfo::new("handle \"req\" <1>".to_string()).with_parent(parent.id);
+        let (parent_id, child_id) = (parent.id, child.id);
+        graph.add_task(parent);
+        graph.add_task(child);
+        graph.add_relationship(Relationship::new(
+            parent_id,
+            child_id,
+            RelationshipType::Spawned,
+        ));
+        graph.add_relationship(
+            Relationship::new(child_id, parent_id, RelationshipType::SharedResource)
+                .with_resource("db & cache"),
+        );
+        (graph, parent_id, child_id)
+    }
+
+    #[test]
+    fn test_mermaid_and_graphml() {
+        let (graph, parent, child) = sample();
+        let (p, c) = (parent.as_u64(), child.as_u64());
+
+        let mermaid = graph.to_mermaid();
+        assert!(mermaid.starts_with("flowchart LR\n"));
+        assert!(mermaid.contains(&format!("t{c}[\"handle #quot;req#quot; #lt;1#gt;<br/>")));
+        assert!(mermaid.contains(&format!("t{p} -->|\"spawned\"| t{c}")));
+        assert!(mermaid.contains(&format!("t{c} -.->|\"shares<br/>db & cache\"| t{p}")));
+        assert!(mermaid.contains(":::completed"));
+
+        let graphml = graph.to_graphml();
+        assert!(graphml.contains(&format!("<edge id=\"e0\" source=\"t{p}\" target=\"t{c}\">")));
+        assert!(graphml.contains("handle &quot;req&quot; &lt;1&gt;"));
+        assert!(graphml.contains("<data key=\"resource\">db &amp; cache</data>"));
+    }
+
+    #[test]
+    fn test_graph_data_round_trips_through_export() {
+        let (graph, parent, child) = sample();
+
+        let json = graph.to_json().unwrap();
+        let data: GraphData = serde_json::from_str(&json).unwrap();
+        assert_eq!(data.edges, graph.to_graph_data().edges);
+        assert_eq!(data.nodes[0].id, parent.as_u64());
+        assert_eq!(data.nodes[1].parent_id, Some(parent.as_u64()));
+        assert_eq!(data.edges[1].relationship, RelationshipType::SharedResource);
+        assert!(json.contains("\"relationship\": \"SharedResource\""));
+
+        let tasks: Vec<ExportTask> = graph
+            .sorted_tasks()
+            .into_iter()
+            .map(ExportTask::from)
+            .collect();
+        let export = ExportData {
+            tasks,
+            events: Vec::new(),
+            relationships: data.edges.clone(),
+            metadata: ExportMetadata {
+                version: String::new(),
+                timestamp: String::new(),
+                total_tasks: 2,
+                total_events: 0,
+                duration_ms: 0.0,
+            },
+        };
+        let rebuilt = TaskGraph::from_export(&export);
+        assert_eq!(rebuilt.to_graph_data().edges, data.edges);
+        assert_eq!(
+            rebuilt.get_task(&parent).unwrap().state,
+            TaskState::Completed
+        );
+        assert_eq!(rebuilt.get_task(&child).unwrap().parent, Some(parent));
+
+        // Without relationships, they come from the tasks' parents
+        let export = ExportData {
+            relationships: Vec::new(),
+            ..export
+        };
+        let rebuilt = TaskGraph::from_export(&export);
+        assert_eq!(
+            rebuilt.get_related_tasks(parent),
+            vec![(child, RelationshipType::Spawned)]
+        );
+    }
+
+    #[test]
+    fn test_exported_states_and_timings_are_restored() {
+        let states = [
+            TaskState::Pending,
+            TaskState::Running,
+            TaskState::Blocked {
+                await_point: "fetch".to_string(),
+            },
+            TaskState::Completed,
+            TaskState::Failed {
+                error: Some("timeout".to_string()),
+            },
+            TaskState::Failed { error: None },
+            TaskState::Panicked {
+                message: "oops".to_string(),
+            },
+            TaskState::Cancelled,
+        ];
+        let tasks = states
+            .iter()
+            .map(|state| {
+                let mut task = TaskInfo::new("task".to_string());
+                task.state = state.clone();
+                let mut exported = ExportTask::from(&task);
+                exported.duration_ms = f64::MAX;
+                exported.run_time_ms = -1.0;
+                exported
+            })
+            .collect();
+        let json = serde_json::to_string(&ExportData {
+            tasks,
+            events: Vec::new(),
+            relationships: Vec::new(),
+            metadata: ExportMetadata {
+                version: String::new(),
+                timestamp: String::new(),
+                total_tasks: states.len(),
+                total_events: 0,
+                duration_ms: 0.0,
+            },
+        })
+        .unwrap();
+
+        // Bad timings read back as zero instead of panicking
+        let graph = TaskGraph::from_export(&serde_json::from_str(&json).unwrap());
+        let rebuilt: Vec<TaskState> = graph
+            .sorted_tasks()
+            .into_iter()
+            .map(|task| task.state.clone())
+            .collect();
+        assert_eq!(rebuilt, states);
+        assert!(graph
+            .sorted_tasks()
+            .iter()
+            .all(|task| task.total_run_time == Duration::ZERO));
+    }
+}
@@ -3,14 +3,24 @@
 //! This module provides comprehensive relationship tracking between async tasks,
 //! including spawning, channels, shared resources, data flow, and dependencies.
 
+mod critical_path;
+mod format;
+mod query;
+
+pub(crate) use critical_path::join_point;
+pub use critical_path::{CriticalPath, PathSegment, SegmentKind, TaskSlack};
+pub use format::{GraphData, GraphEdge, GraphNode};
+pub use query::{Direction, GraphQuery};
+
 use crate::task::{TaskId, TaskInfo, TaskState};
 use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt;
 use std::sync::Arc;
 
 /// Types of relationships between tasks
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum RelationshipType {
     /// Parent-child spawn relationship
     Spawned,
@@ -43,7 +53,7 @@ impl fmt::Display for RelationshipType {
 }
 
 /// A relationship between two tasks
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Relationship {
     /// Source task
     pub from: TaskId,
@@ -57,6 +67,25 @@ pub struct Relationship {
     pub data_description: Option<String>,
 }
 
+impl Relationship {
+    /// Create a relationship without a resource or data description
+    pub fn new(from: TaskId, to: TaskId, relationship_type: RelationshipType) -> Self {
+        Self {
+            from,
+            to,
+            relationship_type,
+            resource_name: None,
+            data_description: None,
+        }
+    }
+
+    /// Set the resource the relationship is about
+    pub fn with_resource(mut self, name: impl Into<String>) -> Self {
+        self.resource_name = Some(name.into());
+        self
+    }
+}
+
 /// Graph of task relationships
 #[derive(Debug, Clone)]
 pub struct TaskGraph {
@@ -91,12 +120,12 @@ impl TaskGraph {
         // Update adjacency lists
         self.adjacency
             .entry(relationship.from)
-            .or_insert_with(Vec::new)
+            .or_default()
             .push((relationship.to, relationship.relationship_type));
 
         self.reverse_adjacency
             .entry(relationship.to)
-            .or_insert_with(Vec::new)
+            .or_default()
             .push((relationship.from, relationship.relationship_type));
 
         self.relationships.push(relationship);
@@ -129,6 +158,10 @@ impl TaskGraph {
     }
 
     /// Find the critical path (longest dependency chain)
+    ///
+    /// This counts relationships, not time; see
+    /// [`TaskGraph::find_timed_critical_path`] for the path that determined
+    /// a task's latency.
     pub fn find_critical_path(&self) -> Vec<TaskId> {
         let mut longest_path = Vec::new();
         let mut visited = HashSet::new();
@@ -186,10 +219,9 @@ impl TaskGraph {
                     if matches!(
                         rel_type,
                         RelationshipType::Dependency | RelationshipType::AwaitsOn
-                    ) {
-                        if dependencies.insert(*next_id) {
-                            queue.push_back(*next_id);
-                        }
+                    ) && dependencies.insert(*next_id)
+                    {
+                        queue.push_back(*next_id);
                     }
                 }
             }
@@ -295,7 +327,9 @@ impl TaskGraph {
                 TaskState::Running => "lightblue",
                 TaskState::Blocked { .. } => "yellow",
                 TaskState::Completed => "lightgreen",
-                TaskState::Failed => "lightcoral",
+                TaskState::Failed { .. } => "lightcoral",
+                TaskState::Panicked { .. } => "indianred",
+                TaskState::Cancelled => "plum",
             };
 
             dot.push_str(&format!(
@@ -307,7 +341,7 @@ impl TaskGraph {
             ));
         }
 
-        dot.push_str("\n");
+        dot.push('\n');
 
         // Add edges with different styles for different relationship types
         for rel in &self.relationships {
@@ -410,14 +444,8 @@ impl TaskGraph {
         for rel in &self.relationships {
             if rel.relationship_type == RelationshipType::SharedResource {
                 if let Some(ref name) = rel.resource_name {
-                    resources
-                        .entry(name.clone())
-                        .or_insert_with(Vec::new)
-                        .push(rel.from);
-                    resources
-                        .entry(name.clone())
-                        .or_insert_with(Vec::new)
-                        .push(rel.to);
+                    resources.entry(name.clone()).or_default().push(rel.from);
+                    resources.entry(name.clone()).or_default().push(rel.to);
                 }
             }
         }
@@ -454,6 +482,11 @@ static GRAPH: once_cell::sync::Lazy<Arc<RwLock<TaskGraph>>> =
     once_cell::sync::Lazy::new(|| Arc::new(RwLock::new(TaskGraph::new())));
 
 /// Get the global task graph
+///
+/// This graph is only filled in by hand. For the tasks and relationships an
+/// inspector recorded, use [`Inspector::build_graph`].
+///
+/// [`Inspector::build_graph`]: crate::inspector::Inspector::build_graph
 pub fn global_graph() -> Arc<RwLock<TaskGraph>> {
     Arc::clone(&GRAPH)
 }
@@ -461,7 +494,7 @@ pub fn global_graph() -> Arc<RwLock<TaskGraph>> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::task::TaskId;
+    use crate::task::{TaskId, TaskLevel, WakerStats};
     use std::time::{Duration, Instant};
 
     #[test]
@@ -482,7 +515,14 @@ mod tests {
             created_at: now,
             last_updated: now,
             parent: None,
+            child_kind: None,
             location: None,
+            await_point: None,
+            await_location: None,
+            await_since: None,
+            level: TaskLevel::default(),
+            fields: Vec::new(),
+            wakers: WakerStats::default(),
             poll_count: 0,
             total_run_time: Duration::ZERO,
         });
@@ -493,7 +533,14 @@ mod tests {
             created_at: now,
             last_updated: now,
             parent: None,
+            child_kind: None,
             location: None,
+            await_point: None,
+            await_location: None,
+            await_since: None,
+            level: TaskLevel::default(),
+            fields: Vec::new(),
+            wakers: WakerStats::default(),
             poll_count: 0,
             total_run_time: Duration::ZERO,
         });
@@ -504,7 +551,14 @@ mod tests {
             created_at: now,
             last_updated: now,
             parent: None,
+            child_kind: None,
             location: None,
+            await_point: None,
+            await_location: None,
+            await_since: None,
+            level: TaskLevel::default(),
+            fields: Vec::new(),
+            wakers: WakerStats::default(),
             poll_count: 0,
             total_run_time: Duration::ZERO,
         });
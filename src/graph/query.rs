@@ -0,0 +1,494 @@
+//! Graph queries
+//!
+//! A [`GraphQuery`] selects part of a [`TaskGraph`]: it walks the graph from
+//! a set of starting tasks along chosen relationship types, keeps the reached
+//! tasks passing its filters, and returns them as a new graph that can be
+//! rendered or queried again.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use async_inspect::graph::RelationshipType;
+//! use async_inspect::task::TaskState;
+//!
+//! let graph = inspector.build_graph();
+//!
+//! // Blocked database tasks working for one request
+//! let stuck = graph
+//!     .subtree(request)
+//!     .query()
+//!     .with_name("db")
+//!     .with_state(&TaskState::Blocked { await_point: String::new() })
+//!     .subgraph();
+//! println!("{}", stuck.to_dot());
+//! ```
+
+use super::{Relationship, RelationshipType, TaskGraph};
+use crate::task::{TaskId, TaskInfo, TaskState};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::mem::{self, Discriminant};
+use std::time::Instant;
+
+/// Relationships through which one task can hold up another
+const BLOCKING: [RelationshipType; 3] = [
+    RelationshipType::AwaitsOn,
+    RelationshipType::Dependency,
+    RelationshipType::SharedResource,
+];
+
+/// Task predicate added with [`GraphQuery::with_filter`]
+type TaskFilter<'a> = Box<dyn Fn(&TaskInfo) -> bool + 'a>;
+
+/// Which way a query follows relationships
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// From a task to the tasks it relates to
+    Outgoing,
+    /// From a task to the tasks relating to it
+    Incoming,
+    /// Both ways
+    Both,
+}
+
+/// A composable selection of tasks from a [`TaskGraph`]
+///
+/// Without starting tasks the query considers every task in the graph.
+/// Task filters decide which reached tasks are kept; they don't stop the
+/// traversal, so a filtered-out task can still lead to kept ones.
+pub struct GraphQuery<'a> {
+    graph: &'a TaskGraph,
+    roots: Vec<TaskId>,
+    direction: Direction,
+    following: Option<HashSet<RelationshipType>>,
+    max_depth: Option<usize>,
+    states: Vec<Discriminant<TaskState>>,
+    name: Option<String>,
+    window: Option<(Instant, Instant)>,
+    resource: Option<String>,
+    filters: Vec<TaskFilter<'a>>,
+    relationships: Option<HashSet<RelationshipType>>,
+}
+
+impl<'a> GraphQuery<'a> {
+    fn new(graph: &'a TaskGraph) -> Self {
+        Self {
+            graph,
+            roots: Vec::new(),
+            direction: Direction::Outgoing,
+            following: None,
+            max_depth: None,
+            states: Vec::new(),
+            name: None,
+            window: None,
+            resource: None,
+            filters: Vec::new(),
+            relationships: None,
+        }
+    }
+
+    /// Start the traversal from a task
+    pub fn from(mut self, task_id: TaskId) -> Self {
+        self.roots.push(task_id);
+        self
+    }
+
+    /// Start the traversal from several tasks
+    pub fn from_tasks(mut self, task_ids: impl IntoIterator<Item = TaskId>) -> Self {
+        self.roots.extend(task_ids);
+        self
+    }
+
+    /// Set which way relationships are followed (default outgoing)
+    pub fn with_direction(mut self, direction: Direction) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    /// Only traverse relationships of these types (default all)
+    pub fn following(mut self, types: impl IntoIterator<Item = RelationshipType>) -> Self {
+        self.following = Some(types.into_iter().collect());
+        self
+    }
+
+    /// Stop the traversal this many relationships away from the start
+    pub fn with_max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Keep tasks in this state
+    ///
+    /// Only the kind of state is compared, so any `Blocked` state matches
+    /// regardless of its await point. Calling this again accepts more states.
+    pub fn with_state(mut self, state: &TaskState) -> Self {
+        self.states.push(mem::discriminant(state));
+        self
+    }
+
+    /// Keep tasks whose name contains `pattern`
+    pub fn with_name(mut self, pattern: impl Into<String>) -> Self {
+        self.name = Some(pattern.into());
+        self
+    }
+
+    /// Keep tasks that were alive at some point between `start` and `end`
+    pub fn with_time_window(mut self, start: Instant, end: Instant) -> Self {
+        self.window = Some((start, end));
+        self
+    }
+
+    /// Keep tasks with a relationship about the named resource
+    pub fn touching_resource(mut self, name: impl Into<String>) -> Self {
+        self.resource = Some(name.into());
+        self
+    }
+
+    /// Keep tasks matching a custom predicate
+    pub fn with_filter(mut self, filter: impl Fn(&TaskInfo) -> bool + 'a) -> Self {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    /// Only keep relationships of these types in the result (default all)
+    pub fn with_relationships(mut self, types: impl IntoIterator<Item = RelationshipType>) -> Self {
+        self.relationships = Some(types.into_iter().collect());
+        self
+    }
+
+    /// Tasks one relationship away from `task_id` in the query's direction
+    fn neighbors(&self, task_id: TaskId) -> Vec<TaskId> {
+        let outgoing = self.graph.adjacency.get(&task_id);
+        let incoming = self.graph.reverse_adjacency.get(&task_id);
+        let edges = match self.direction {
+            Direction::Outgoing => [outgoing, None],
+            Direction::Incoming => [incoming, None],
+            Direction::Both => [outgoing, incoming],
+        };
+
+        edges
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|(_, rel_type)| {
+                self.following
+                    .as_ref()
+                    .map_or(true, |types| types.contains(rel_type))
+            })
+            .map(|&(next, _)| next)
+            .collect()
+    }
+
+    /// Breadth-first walk from the roots, recording how each task was reached
+    fn traverse(&self, target: Option<TaskId>) -> HashMap<TaskId, Option<TaskId>> {
+        let mut reached = HashMap::new();
+        let mut queue = VecDeque::new();
+
+        for &root in &self.roots {
+            if self.graph.tasks.contains_key(&root) && !reached.contains_key(&root) {
+                reached.insert(root, None);
+                queue.push_back((root, 0));
+            }
+        }
+
+        while let Some((current, depth)) = queue.pop_front() {
+            if Some(current) == target || self.max_depth.is_some_and(|max| depth >= max) {
+                continue;
+            }
+            for next in self.neighbors(current) {
+                if self.graph.tasks.contains_key(&next) && !reached.contains_key(&next) {
+                    reached.insert(next, Some(current));
+                    queue.push_back((next, depth + 1));
+                }
+            }
+        }
+
+        reached
+    }
+
+    /// Whether a task passes the filters, given the tasks touching the
+    /// selected resource, if any
+    fn matches(&self, task: &TaskInfo, touching: Option<&HashSet<TaskId>>) -> bool {
+        if !self.states.is_empty() && !self.states.contains(&mem::discriminant(&task.state)) {
+            return false;
+        }
+        if let Some(pattern) = &self.name {
+            if !task.name.contains(pattern.as_str()) {
+                return false;
+            }
+        }
+        if let Some((start, end)) = self.window {
+            let ended = task.created_at + task.duration();
+            if task.created_at > end || ended < start {
+                return false;
+            }
+        }
+        if touching.is_some_and(|touching| !touching.contains(&task.id)) {
+            return false;
+        }
+        self.filters.iter().all(|filter| filter(task))
+    }
+
+    /// IDs of the selected tasks, in ID order
+    pub fn task_ids(&self) -> Vec<TaskId> {
+        let candidates: Vec<TaskId> = if self.roots.is_empty() {
+            self.graph.tasks.keys().copied().collect()
+        } else {
+            self.traverse(None).into_keys().collect()
+        };
+        let touching: Option<HashSet<TaskId>> = self.resource.as_ref().map(|resource| {
+            self.graph
+                .find_tasks_sharing_resource(resource)
+                .into_iter()
+                .collect()
+        });
+
+        let mut ids: Vec<TaskId> = candidates
+            .into_iter()
+            .filter(|id| {
+                self.graph
+                    .tasks
+                    .get(id)
+                    .is_some_and(|task| self.matches(task, touching.as_ref()))
+            })
+            .collect();
+        ids.sort_by_key(TaskId::as_u64);
+        ids
+    }
+
+    /// The selected tasks and the relationships between them
+    pub fn subgraph(&self) -> TaskGraph {
+        let kept: HashSet<TaskId> = self.task_ids().into_iter().collect();
+        let mut graph = self.graph.subgraph(kept);
+        if let Some(types) = &self.relationships {
+            graph = graph.retain_relationships(|rel| types.contains(&rel.relationship_type));
+        }
+        graph
+    }
+
+    /// Shortest chain of relationships from the starting tasks to `target`
+    ///
+    /// Only the traversal settings (direction, followed types and depth)
+    /// apply; task filters do not constrain the tasks along the path.
+    pub fn path_to(&self, target: TaskId) -> Option<Vec<TaskId>> {
+        let reached = self.traverse(Some(target));
+        let mut path = vec![target];
+        let mut current = *reached.get(&target)?;
+
+        while let Some(previous) = current {
+            path.push(previous);
+            current = reached[&previous];
+        }
+
+        path.reverse();
+        Some(path)
+    }
+}
+
+impl TaskGraph {
+    /// Start a query over this graph
+    pub fn query(&self) -> GraphQuery<'_> {
+        GraphQuery::new(self)
+    }
+
+    /// Graph of the given tasks and the relationships among them
+    pub fn subgraph(&self, task_ids: impl IntoIterator<Item = TaskId>) -> TaskGraph {
+        let kept: HashSet<TaskId> = task_ids.into_iter().collect();
+        let mut graph = TaskGraph::new();
+
+        for id in &kept {
+            if let Some(task) = self.tasks.get(id) {
+                graph.add_task(task.clone());
+            }
+        }
+        for rel in &self.relationships {
+            if graph.tasks.contains_key(&rel.from) && graph.tasks.contains_key(&rel.to) {
+                graph.add_relationship(rel.clone());
+            }
+        }
+
+        graph
+    }
+
+    /// Copy of the graph keeping only the relationships matching `keep`
+    fn retain_relationships(&self, keep: impl Fn(&Relationship) -> bool) -> TaskGraph {
+        let mut graph = TaskGraph::new();
+        for task in self.tasks.values() {
+            graph.add_task(task.clone());
+        }
+        for rel in self.relationships.iter().filter(|rel| keep(rel)) {
+            graph.add_relationship(rel.clone());
+        }
+        graph
+    }
+
+    /// A task and everything it spawned or awaited, transitively
+    pub fn subtree(&self, root: TaskId) -> TaskGraph {
+        self.query()
+            .from(root)
+            .following([RelationshipType::Spawned, RelationshipType::AwaitsOn])
+            .subgraph()
+    }
+
+    /// Find all tasks held up, directly or transitively, by a task
+    ///
+    /// These are the tasks awaiting it, depending on it, or waiting for a
+    /// resource it holds, and in turn the tasks held up by those.
+    pub fn find_blocked_tasks(&self, task_id: TaskId) -> HashSet<TaskId> {
+        let mut blocked: HashSet<TaskId> = self
+            .query()
+            .from(task_id)
+            .with_direction(Direction::Incoming)
+            .following(BLOCKING)
+            .task_ids()
+            .into_iter()
+            .collect();
+        blocked.remove(&task_id);
+        blocked
+    }
+
+    /// Find the shortest chain of relationships from one task to another
+    pub fn find_shortest_path(&self, from: TaskId, to: TaskId) -> Option<Vec<TaskId>> {
+        self.query().from(from).path_to(to)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::{TaskLevel, WakerStats};
+    use std::time::Duration;
+
+    fn task(id: u64, name: &str, state: TaskState, created_at: Instant) -> TaskInfo {
+        TaskInfo {
+            id: TaskId::from_u64(id),
+            name: name.to_string(),
+            state,
+            created_at,
+            last_updated: created_at,
+            parent: None,
+            child_kind: None,
+            location: None,
+            await_point: None,
+            await_location: None,
+            await_since: None,
+            level: TaskLevel::default(),
+            fields: Vec::new(),
+            wakers: WakerStats::default(),
+            poll_count: 0,
+            total_run_time: Duration::ZERO,
+        }
+    }
+
+    /// request(1) spawns db(2) and cache(3); db awaits query(4); report(5)
+    /// waits on the "pool" mutex held by query
+    fn sample() -> (TaskGraph, Instant) {
+        let now = Instant::now();
+        let blocked = || TaskState::Blocked {
+            await_point: "pool".to_string(),
+        };
+        let mut graph = TaskGraph::new();
+        graph.add_task(task(1, "request", TaskState::Running, now));
+        graph.add_task(task(2, "db-client", blocked(), now));
+        graph.add_task(task(3, "cache", TaskState::Completed, now));
+        graph.add_task(task(4, "db-query", TaskState::Running, now));
+        graph.add_task(task(5, "report", blocked(), now + Duration::from_secs(10)));
+
+        let id = TaskId::from_u64;
+        graph.add_relationship(Relationship::new(id(1), id(2), RelationshipType::Spawned));
+        graph.add_relationship(Relationship::new(id(1), id(3), RelationshipType::Spawned));
+        graph.add_relationship(Relationship::new(id(2), id(4), RelationshipType::AwaitsOn));
+        graph.add_relationship(
+            Relationship::new(id(5), id(4), RelationshipType::SharedResource).with_resource("pool"),
+        );
+        (graph, now)
+    }
+
+    fn ids(ids: &[u64]) -> Vec<TaskId> {
+        ids.iter().copied().map(TaskId::from_u64).collect()
+    }
+
+    #[test]
+    fn test_query_filters_reached_tasks() {
+        let (graph, now) = sample();
+        let request = TaskId::from_u64(1);
+
+        assert_eq!(
+            graph.subtree(request).query().task_ids(),
+            ids(&[1, 2, 3, 4])
+        );
+        assert_eq!(
+            graph.query().from(request).with_max_depth(1).task_ids(),
+            ids(&[1, 2, 3])
+        );
+
+        let blocked_db = graph
+            .query()
+            .from(request)
+            .with_name("db")
+            .with_state(&TaskState::Blocked {
+                await_point: String::new(),
+            })
+            .task_ids();
+        assert_eq!(blocked_db, ids(&[2]));
+
+        let early = graph
+            .query()
+            .with_time_window(now, now + Duration::from_secs(1))
+            .task_ids();
+        assert_eq!(early, ids(&[1, 2, 3, 4]));
+
+        assert_eq!(
+            graph.query().touching_resource("pool").task_ids(),
+            ids(&[4, 5])
+        );
+        // Tasks touching the pool within the request's subtree
+        let in_request = graph
+            .query()
+            .from(request)
+            .following([RelationshipType::Spawned, RelationshipType::AwaitsOn])
+            .touching_resource("pool")
+            .task_ids();
+        assert_eq!(in_request, ids(&[4]));
+    }
+
+    #[test]
+    fn test_subgraph_keeps_relationships_between_kept_tasks() {
+        let (graph, _) = sample();
+
+        let subtree = graph.subtree(TaskId::from_u64(2));
+        assert_eq!(subtree.query().task_ids(), ids(&[2, 4]));
+        assert_eq!(subtree.relationships.len(), 1);
+        assert!(subtree.to_dot().contains("db-query"));
+
+        let spawns = graph
+            .query()
+            .with_relationships([RelationshipType::Spawned])
+            .subgraph();
+        assert_eq!(spawns.query().task_ids(), ids(&[1, 2, 3, 4, 5]));
+        assert_eq!(spawns.relationships.len(), 2);
+    }
+
+    #[test]
+    fn test_blocked_tasks_and_shortest_path() {
+        let (graph, _) = sample();
+        let id = TaskId::from_u64;
+
+        let blocked = graph.find_blocked_tasks(id(4));
+        assert_eq!(blocked, [id(2), id(5)].into_iter().collect());
+        assert!(graph.find_blocked_tasks(id(3)).is_empty());
+
+        assert_eq!(
+            graph.find_shortest_path(id(1), id(4)),
+            Some(ids(&[1, 2, 4]))
+        );
+        assert_eq!(graph.find_shortest_path(id(4), id(1)), None);
+        assert_eq!(
+            graph
+                .query()
+                .from(id(5))
+                .with_direction(Direction::Both)
+                .path_to(id(1)),
+            Some(ids(&[5, 4, 2, 1]))
+        );
+    }
+}
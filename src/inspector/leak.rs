@@ -0,0 +1,110 @@
+//! Leaked task reports
+//!
+//! A task is leaked when it is still unfinished at a point where it should
+//! have completed, such as the end of a test or application shutdown.
+
+use crate::task::{TaskId, TaskInfo};
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Leaked tasks sharing a name and spawn location
+#[derive(Debug, Clone)]
+pub struct LeakGroup {
+    /// Task name
+    pub name: String,
+    /// Source location the tasks were created at, if known
+    pub location: Option<String>,
+    /// Leaked tasks, oldest first
+    pub task_ids: Vec<TaskId>,
+    /// Age of the oldest task
+    pub oldest: Duration,
+    /// Age of the youngest task
+    pub youngest: Duration,
+}
+
+impl LeakGroup {
+    /// Number of leaked tasks in the group
+    pub fn count(&self) -> usize {
+        self.task_ids.len()
+    }
+}
+
+/// Unfinished tasks created since a point in time, grouped by name and
+/// location
+#[derive(Debug, Clone)]
+pub struct LeakReport {
+    /// Start of the checked window
+    pub since: Instant,
+    /// Groups of leaked tasks, largest first
+    pub groups: Vec<LeakGroup>,
+}
+
+impl LeakReport {
+    /// Group unfinished tasks by name and location
+    pub(crate) fn new<'a>(since: Instant, tasks: impl IntoIterator<Item = &'a TaskInfo>) -> Self {
+        let mut by_site: HashMap<(&str, Option<&str>), Vec<&TaskInfo>> = HashMap::new();
+        for task in tasks {
+            if !task.state.is_terminal() {
+                by_site
+                    .entry((task.name.as_str(), task.location.as_deref()))
+                    .or_default()
+                    .push(task);
+            }
+        }
+
+        let mut groups: Vec<LeakGroup> = by_site
+            .into_iter()
+            .map(|((name, location), mut tasks)| {
+                tasks.sort_by_key(|task| task.created_at);
+                LeakGroup {
+                    name: name.to_string(),
+                    location: location.map(str::to_string),
+                    task_ids: tasks.iter().map(|task| task.id).collect(),
+                    oldest: tasks[0].age(),
+                    youngest: tasks[tasks.len() - 1].age(),
+                }
+            })
+            .collect();
+
+        groups.sort_by(|a, b| b.count().cmp(&a.count()).then_with(|| a.name.cmp(&b.name)));
+
+        Self { since, groups }
+    }
+
+    /// Check whether no task leaked
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// Total number of leaked tasks
+    pub fn total(&self) -> usize {
+        self.groups.iter().map(LeakGroup::count).sum()
+    }
+}
+
+impl fmt::Display for LeakReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} leaked task(s) created in the last {:.2}s",
+            self.total(),
+            self.since.elapsed().as_secs_f64()
+        )?;
+
+        for group in &self.groups {
+            write!(f, "\n  {} x {}", group.count(), group.name)?;
+            if let Some(location) = &group.location {
+                write!(f, " at {location}")?;
+            }
+            write!(
+                f,
+                ", age {:.2}s..{:.2}s",
+                group.youngest.as_secs_f64(),
+                group.oldest.as_secs_f64()
+            )?;
+        }
+
+        Ok(())
+    }
+}
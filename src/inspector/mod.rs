@@ -1,15 +1,29 @@
 //! Core inspection functionality
 //!
 //! This module provides the main `Inspector` type that manages task tracking
-//! and event collection.
+//! and event collection. Retention limits, sampling and poll/await tracking
+//! are governed by the inspector's [`Config`].
+//!
+//! Recording is built to scale with the number of worker threads: tasks are
+//! kept in a sharded map and events are appended to per-thread buffers, which
+//! are merged into the [`Timeline`] whenever it is read or a buffer fills up.
+
+mod leak;
+mod shards;
+
+pub use leak::{LeakGroup, LeakReport};
 
-use crate::task::{TaskId, TaskInfo, TaskState};
-use crate::timeline::{Event, EventKind, Timeline};
+use crate::config::Config;
+use crate::deadlock::DeadlockCycle;
+use crate::graph::{CriticalPath, Relationship, RelationshipType, TaskGraph};
+use crate::task::{ChildKind, TaskId, TaskInfo, TaskState};
+use crate::timeline::{Event, EventCursor, EventKind, Timeline};
 use parking_lot::RwLock;
+use shards::{EventShards, TaskShards, FLUSH_THRESHOLD};
 use std::collections::HashMap;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 /// Global inspector instance
 static GLOBAL_INSPECTOR: once_cell::sync::Lazy<Inspector> =
@@ -24,7 +38,10 @@ pub struct Inspector {
 
 struct InspectorState {
     /// All tracked tasks
-    tasks: RwLock<HashMap<TaskId, TaskInfo>>,
+    tasks: TaskShards,
+
+    /// Events not yet merged into the timeline
+    pending_events: EventShards,
 
     /// Timeline of events
     timeline: RwLock<Timeline>,
@@ -33,18 +50,48 @@ struct InspectorState {
     event_counter: AtomicU64,
 
     /// Whether the inspector is enabled
-    enabled: RwLock<bool>,
+    enabled: AtomicBool,
+
+    /// Limits, sampling and tracking switches
+    config: Config,
+
+    /// Tasks not admitted because of `max_tasks`
+    dropped_tasks: AtomicU64,
+
+    /// Tasks skipped by the sampling rate
+    unsampled_tasks: AtomicU64,
+
+    /// Events evicted by the timeline's eviction policy
+    dropped_events: AtomicU64,
+
+    /// Polls that ran past the long poll threshold
+    long_polls: AtomicU64,
+
+    /// Deadlocks found by the last deadlock monitor scan
+    deadlocks: RwLock<Vec<DeadlockCycle>>,
 }
 
 impl Inspector {
-    /// Create a new inspector
+    /// Create a new inspector using the global configuration
     pub fn new() -> Self {
+        Self::with_config(Config::global().clone())
+    }
+
+    /// Create a new inspector governed by a specific configuration
+    pub fn with_config(config: Config) -> Self {
         Self {
             state: Arc::new(InspectorState {
-                tasks: RwLock::new(HashMap::new()),
+                tasks: TaskShards::new(),
+                pending_events: EventShards::new(),
                 timeline: RwLock::new(Timeline::new()),
                 event_counter: AtomicU64::new(1),
-                enabled: RwLock::new(true),
+                enabled: AtomicBool::new(true),
+                config,
+                dropped_tasks: AtomicU64::new(0),
+                unsampled_tasks: AtomicU64::new(0),
+                dropped_events: AtomicU64::new(0),
+                long_polls: AtomicU64::new(0),
+                deadlocks: RwLock::new(Vec::new()),
             }),
         }
     }
@@ -56,92 +103,145 @@ impl Inspector {
 
     /// Check if the inspector is enabled
     pub fn is_enabled(&self) -> bool {
-        *self.state.enabled.read()
+        self.state.enabled.load(Ordering::Relaxed)
     }
 
     /// Enable the inspector
     pub fn enable(&self) {
-        *self.state.enabled.write() = true;
+        self.state.enabled.store(true, Ordering::Relaxed);
     }
 
     /// Disable the inspector
     pub fn disable(&self) {
-        *self.state.enabled.write() = false;
+        self.state.enabled.store(false, Ordering::Relaxed);
+    }
+
+    /// Get the configuration governing this inspector
+    pub fn config(&self) -> &Config {
+        &self.state.config
     }
 
     /// Register a new task
     pub fn register_task(&self, name: String) -> TaskId {
-        if !self.is_enabled() {
-            return TaskId::new();
-        }
-
-        let task = TaskInfo::new(name.clone());
-        let task_id = task.id;
-
-        // Add event
-        self.add_event(
-            task_id,
-            EventKind::TaskSpawned {
-                name,
-                parent: None,
-                location: None,
-            },
-        );
-
-        // Store task
-        self.state.tasks.write().insert(task_id, task);
-
-        task_id
+        self.register_task_with_info(TaskInfo::new(name))
     }
 
     /// Register a child task with a parent
     pub fn register_child_task(&self, name: String, parent_id: TaskId) -> TaskId {
-        if !self.is_enabled() {
-            return TaskId::new();
-        }
+        self.register_task_with_info(TaskInfo::new(name).with_parent(parent_id))
+    }
 
-        let mut task = TaskInfo::new(name.clone());
-        task.parent = Some(parent_id);
+    /// Register a task with additional metadata
+    ///
+    /// The task is only tracked if its level is at least `min_level`, it
+    /// passes the sampling rate and it fits in `max_tasks`. Untracked tasks
+    /// still get an ID, but every later call for that ID is ignored, so a
+    /// task is either recorded for its whole lifetime or not at all.
+    pub fn register_task_with_info(&self, task: TaskInfo) -> TaskId {
         let task_id = task.id;
 
-        // Add event
-        self.add_event(
-            task_id,
-            EventKind::TaskSpawned {
-                name,
-                parent: Some(parent_id),
-                location: None,
-            },
-        );
+        if !self.is_enabled() || task.level < self.state.config.min_level() {
+            return task_id;
+        }
+
+        if !self.state.config.should_sample() {
+            self.state.unsampled_tasks.fetch_add(1, Ordering::Relaxed);
+            return task_id;
+        }
+
+        let spawned = EventKind::TaskSpawned {
+            name: task.name.clone(),
+            parent: task.parent,
+            location: task.location.clone(),
+        };
+
+        // The limit is approximate when many threads register at once
+        let tasks = &self.state.tasks;
+        let max_tasks = self.state.config.max_tasks();
+        if max_tasks > 0 && tasks.len() >= max_tasks && !tasks.evict_oldest_finished() {
+            self.state.dropped_tasks.fetch_add(1, Ordering::Relaxed);
+            return task_id;
+        }
 
-        // Store task
-        self.state.tasks.write().insert(task_id, task);
+        let (parent, child_kind) = (task.parent, task.child_kind);
+        tasks.insert(task);
+        self.add_event(task_id, spawned);
+
+        if let Some(parent) = parent {
+            // A child awaited inline is awaited by its parent
+            let kind = match child_kind {
+                Some(ChildKind::Inline) => RelationshipType::AwaitsOn,
+                _ => RelationshipType::Spawned,
+            };
+            self.add_relationship(Relationship::new(parent, task_id, kind));
+        }
 
         task_id
     }
 
-    /// Register a task with additional metadata
-    pub fn register_task_with_info(&self, task: TaskInfo) -> TaskId {
-        if !self.is_enabled() {
-            return task.id;
+    /// Record a relationship between two tasks
+    ///
+    /// Relationships involving a task that is not tracked are ignored, one
+    /// already recorded is kept once, and evicting a task drops its
+    /// relationships.
+    pub fn add_relationship(&self, relationship: Relationship) {
+        if self.is_enabled() {
+            self.state.tasks.add_relationship(relationship);
         }
+    }
 
-        let task_id = task.id;
+    /// Get all recorded relationships, in the order they were first seen
+    pub fn relationships(&self) -> Vec<Relationship> {
+        self.state.tasks.relationships()
+    }
 
-        // Add event
-        self.add_event(
-            task_id,
-            EventKind::TaskSpawned {
-                name: task.name.clone(),
-                parent: task.parent,
-                location: task.location.clone(),
-            },
-        );
+    /// Build a snapshot of the task graph
+    ///
+    /// The graph holds every tracked task and the relationships recorded
+    /// between them: spawns, children and join handles awaited, and tasks
+    /// waiting on instrumented primitives held by others.
+    pub fn build_graph(&self) -> TaskGraph {
+        let mut graph = TaskGraph::new();
+        for task in self.get_all_tasks() {
+            graph.add_task(task);
+        }
 
-        // Store task
-        self.state.tasks.write().insert(task_id, task);
+        // Tasks may be evicted between the two snapshots
+        for relationship in self.relationships() {
+            if graph.get_task(&relationship.from).is_some()
+                && graph.get_task(&relationship.to).is_some()
+            {
+                graph.add_relationship(relationship);
+            }
+        }
 
-        task_id
+        graph
+    }
+
+    /// Find the chain of tasks and await points that determined a task's
+    /// latency
+    ///
+    /// See [`TaskGraph::find_timed_critical_path`]. Returns `None` if the
+    /// task is not tracked.
+    pub fn critical_path(&self, root: TaskId) -> Option<CriticalPath> {
+        let graph = self.build_graph();
+        self.with_timeline(|timeline| graph.find_timed_critical_path(root, timeline))
+    }
+
+    /// Find the critical path of the slowest finished top-level task
+    pub fn slowest_critical_path(&self) -> Option<CriticalPath> {
+        let slowest = self
+            .get_all_tasks()
+            .into_iter()
+            .filter(|task| task.state.is_terminal())
+            .filter(|task| task.parent.map_or(true, |parent| !self.is_tracked(parent)))
+            .max_by_key(TaskInfo::duration)?;
+        self.critical_path(slowest.id)
+    }
+
+    /// Check whether a task is currently being tracked
+    pub fn is_tracked(&self, task_id: TaskId) -> bool {
+        self.state.tasks.contains(task_id)
     }
 
     /// Update task state
@@ -150,47 +250,94 @@ impl Inspector {
             return;
         }
 
-        if let Some(task) = self.state.tasks.write().get_mut(&task_id) {
+        self.transition(task_id, new_state);
+    }
+
+    /// Change a task's state and record it, returning whether the task is tracked
+    fn transition(&self, task_id: TaskId, new_state: TaskState) -> bool {
+        let Some(old_state) = self.state.tasks.with_mut(task_id, |task| {
             let old_state = task.state.clone();
             task.update_state(new_state.clone());
+            old_state
+        }) else {
+            return false;
+        };
 
-            // Add event
-            self.add_event(
-                task_id,
-                EventKind::StateChanged {
-                    old_state,
-                    new_state,
-                },
-            );
-        }
+        self.push_event(
+            task_id,
+            EventKind::StateChanged {
+                old_state,
+                new_state,
+            },
+        );
+        true
     }
 
     /// Record a poll start
     pub fn poll_started(&self, task_id: TaskId) {
-        if !self.is_enabled() {
+        if !self.is_enabled() || !self.state.config.track_polls() {
             return;
         }
 
-        self.update_task_state(task_id, TaskState::Running);
-        self.add_event(task_id, EventKind::PollStarted);
+        if self.transition(task_id, TaskState::Running) {
+            self.push_event(task_id, EventKind::PollStarted);
+        }
     }
 
     /// Record a poll end
     pub fn poll_ended(&self, task_id: TaskId, duration: Duration) {
-        if !self.is_enabled() {
+        if !self.is_enabled() || !self.state.config.track_polls() {
             return;
         }
 
-        if let Some(task) = self.state.tasks.write().get_mut(&task_id) {
-            task.record_poll(duration);
+        let tracked = self
+            .state
+            .tasks
+            .with_mut(task_id, |task| task.record_poll(duration))
+            .is_some();
+
+        if tracked {
+            self.push_event(task_id, EventKind::PollEnded { duration });
+
+            let threshold = self.state.config.long_poll_threshold();
+            if !threshold.is_zero() && duration >= threshold {
+                self.long_poll(task_id, duration);
+            }
+        }
+    }
+
+    /// Record a poll that blocked the executor past the long poll threshold
+    fn long_poll(&self, task_id: TaskId, duration: Duration) {
+        let Some((name, await_point, location)) = self.suspended_at(task_id) else {
+            return;
+        };
+
+        self.state.long_polls.fetch_add(1, Ordering::Relaxed);
+
+        if self.state.config.log_long_polls() {
+            tracing::warn!(
+                task_id = task_id.as_u64(),
+                task = %name,
+                duration_ms = duration.as_secs_f64() * 1000.0,
+                await_point = ?await_point,
+                location = ?location,
+                "poll blocked the executor"
+            );
         }
 
-        self.add_event(task_id, EventKind::PollEnded { duration });
+        self.push_event(
+            task_id,
+            EventKind::LongPoll {
+                duration,
+                await_point,
+                location,
+            },
+        );
     }
 
     /// Record an await start
     pub fn await_started(&self, task_id: TaskId, await_point: String, location: Option<String>) {
-        if !self.is_enabled() {
+        if !self.is_enabled() || !self.state.config.track_awaits() {
             return;
         }
 
@@ -200,6 +347,11 @@ impl Inspector {
                 await_point: await_point.clone(),
             },
         );
+        self.state.tasks.with_mut(task_id, |task| {
+            task.await_point = Some(await_point.clone());
+            task.await_location.clone_from(&location);
+            task.await_since = Some(Instant::now());
+        });
 
         self.add_event(
             task_id,
@@ -211,12 +363,31 @@ impl Inspector {
     }
 
     /// Record an await end
+    ///
+    /// A task still blocked on this await point goes back to running.
     pub fn await_ended(&self, task_id: TaskId, await_point: String, duration: Duration) {
-        if !self.is_enabled() {
+        if !self.is_enabled() || !self.state.config.track_awaits() {
             return;
         }
 
-        self.add_event(
+        let resumed = self.state.tasks.with_mut(task_id, |task| {
+            if task.await_point.as_ref() == Some(&await_point) {
+                task.await_point = None;
+                task.await_location = None;
+                task.await_since = None;
+            }
+            matches!(&task.state, TaskState::Blocked { await_point: p } if *p == await_point)
+        });
+
+        match resumed {
+            None => return,
+            Some(true) => {
+                self.transition(task_id, TaskState::Running);
+            }
+            Some(false) => {}
+        }
+
+        self.push_event(
             task_id,
             EventKind::AwaitEnded {
                 await_point,
@@ -225,14 +396,238 @@ impl Inspector {
         );
     }
 
+    /// Record a wake of a task's waker
+    pub fn task_woken(&self, task_id: TaskId, by_ref: bool, self_wake: bool) {
+        if !self.is_enabled() || !self.state.config.track_wakers() {
+            return;
+        }
+
+        let tracked = self.state.tasks.with_mut(task_id, |task| {
+            let wakers = &mut task.wakers;
+            if by_ref {
+                wakers.wakes_by_ref += 1;
+            } else {
+                wakers.wakes += 1;
+            }
+            if self_wake {
+                wakers.self_wakes += 1;
+            }
+            wakers.woken_at.get_or_insert_with(Instant::now);
+        });
+
+        if tracked.is_some() {
+            self.push_event(task_id, EventKind::TaskWoken { by_ref, self_wake });
+        }
+    }
+
+    /// Record a clone of a task's waker
+    pub fn waker_cloned(&self, task_id: TaskId) {
+        if !self.is_enabled() || !self.state.config.track_wakers() {
+            return;
+        }
+
+        let tracked = self
+            .state
+            .tasks
+            .with_mut(task_id, |task| task.wakers.clones += 1);
+
+        if tracked.is_some() {
+            self.push_event(task_id, EventKind::WakerCloned);
+        }
+    }
+
+    /// Record a clone of a task's waker being dropped or consumed by a wake
+    pub fn waker_dropped(&self, task_id: TaskId) {
+        if !self.is_enabled() || !self.state.config.track_wakers() {
+            return;
+        }
+
+        let tracked = self
+            .state
+            .tasks
+            .with_mut(task_id, |task| task.wakers.drops += 1);
+
+        if tracked.is_some() {
+            self.push_event(task_id, EventKind::WakerDropped);
+        }
+    }
+
+    /// Record that a task is about to be polled, measuring the time since
+    /// the wake that scheduled it
+    pub fn poll_scheduled(&self, task_id: TaskId) {
+        if !self.is_enabled() || !self.state.config.track_wakers() {
+            return;
+        }
+
+        let latency = self
+            .state
+            .tasks
+            .with_mut(task_id, |task| task.wakers.record_poll())
+            .flatten();
+
+        if let Some(latency) = latency {
+            self.push_event(task_id, EventKind::TaskScheduled { latency });
+        }
+    }
+
+    /// Record the outcome of a poll made with an instrumented waker
+    pub fn poll_returned(&self, task_id: TaskId, pending: bool) {
+        if !self.is_enabled() || !self.state.config.track_wakers() {
+            return;
+        }
+
+        self.state.tasks.with_mut(task_id, |task| {
+            task.wakers.last_poll_pending = pending;
+        });
+    }
+
+    /// Get the deadlocks found by the last scan of a
+    /// [`DeadlockMonitor`](crate::deadlock::DeadlockMonitor)
+    pub fn active_deadlocks(&self) -> Vec<DeadlockCycle> {
+        self.state.deadlocks.read().clone()
+    }
+
+    /// Replace the active deadlocks with the result of a scan
+    pub(crate) fn set_active_deadlocks(&self, cycles: Vec<DeadlockCycle>) {
+        *self.state.deadlocks.write() = cycles;
+    }
+
+    /// Find tasks that are pending but can never be woken again
+    ///
+    /// A task is flagged when its last poll returned `Poll::Pending` while
+    /// no wake happened since and no clone of its waker is still alive.
+    /// The reported await point is the innermost one the task, or a child it
+    /// awaits inline, is blocked on.
+    pub fn lost_wakeups(&self) -> Vec<LostWakeup> {
+        let tasks = self.get_all_tasks();
+
+        // Unfinished children awaited inline, by parent
+        let mut inline_children: HashMap<TaskId, &TaskInfo> = HashMap::new();
+        for task in &tasks {
+            if let (Some(parent), Some(ChildKind::Inline)) = (task.parent, task.child_kind) {
+                if !task.state.is_terminal() {
+                    inline_children.entry(parent).or_insert(task);
+                }
+            }
+        }
+
+        let mut lost: Vec<LostWakeup> = tasks
+            .iter()
+            .filter(|task| !task.state.is_terminal() && task.wakers.lost_wakeup())
+            .map(|task| {
+                let blocked_on = Self::innermost_blocked(&inline_children, task);
+                let await_point = blocked_on.await_point.clone();
+                let location = blocked_on
+                    .await_location
+                    .clone()
+                    .or_else(|| blocked_on.location.clone());
+
+                LostWakeup {
+                    task_id: task.id,
+                    name: task.name.clone(),
+                    await_point,
+                    location,
+                    stuck_for: task.time_since_update(),
+                }
+            })
+            .collect();
+
+        lost.sort_by_key(|l| l.task_id.as_u64());
+        lost
+    }
+
+    /// List unfinished tasks created since a point in time
+    ///
+    /// Called at shutdown with the application's start time, this reports
+    /// every task that never completed, grouped by name and location.
+    pub fn leaked_tasks(&self, since: Instant) -> LeakReport {
+        let tasks = self.get_all_tasks();
+        LeakReport::new(since, tasks.iter().filter(|task| task.created_at >= since))
+    }
+
+    /// List unfinished tasks spawned or awaited, directly or transitively,
+    /// by a task
+    pub fn leaked_descendants(&self, root: TaskId) -> LeakReport {
+        let tasks = self.get_all_tasks();
+        let since = tasks
+            .iter()
+            .find(|task| task.id == root)
+            .map_or_else(Instant::now, |task| task.created_at);
+
+        let mut children: HashMap<TaskId, Vec<&TaskInfo>> = HashMap::new();
+        for task in &tasks {
+            if let Some(parent) = task.parent {
+                children.entry(parent).or_default().push(task);
+            }
+        }
+
+        let mut descendants = Vec::new();
+        let mut queue = vec![root];
+        while let Some(parent) = queue.pop() {
+            for &child in children.remove(&parent).iter().flatten() {
+                queue.push(child.id);
+                descendants.push(child);
+            }
+        }
+
+        LeakReport::new(since, descendants)
+    }
+
+    /// Name of a task with the await point it is suspended at and the
+    /// source location of that point
+    ///
+    /// A task not suspended at a recorded await point reports the nearest
+    /// one of the parents awaiting it inline, and falls back to its own
+    /// location. Only the task and its parent chain are read.
+    fn suspended_at(&self, task_id: TaskId) -> Option<(String, Option<String>, Option<String>)> {
+        let (name, location) = self
+            .state
+            .tasks
+            .with(task_id, |task| (task.name.clone(), task.location.clone()))?;
+
+        let mut next = Some(task_id);
+        while let Some(current) = next.take() {
+            let suspended = self.state.tasks.with(current, |task| {
+                if task.child_kind == Some(ChildKind::Inline) {
+                    next = task.parent;
+                }
+                let point = task.await_point.clone()?;
+                Some((point, task.await_location.clone()))
+            });
+            if let Some(Some((await_point, await_location))) = suspended {
+                return Some((name, Some(await_point), await_location.or(location)));
+            }
+        }
+
+        Some((name, None, location))
+    }
+
+    /// Follow unfinished inline children down to the innermost task
+    /// suspended at an await point
+    fn innermost_blocked<'a>(
+        inline_children: &HashMap<TaskId, &'a TaskInfo>,
+        task: &'a TaskInfo,
+    ) -> &'a TaskInfo {
+        let mut current = task;
+        let mut blocked = task;
+
+        while let Some(&child) = inline_children.get(&current.id) {
+            if child.await_point.is_some() {
+                blocked = child;
+            }
+            current = child;
+        }
+
+        blocked
+    }
+
     /// Mark task as completed
     pub fn task_completed(&self, task_id: TaskId) {
         if !self.is_enabled() {
             return;
         }
 
-        // Get duration while holding read lock, then release it
-        let duration = { self.state.tasks.read().get(&task_id).map(|task| task.age()) };
+        let duration = self.state.tasks.with(task_id, TaskInfo::age);
 
         if let Some(duration) = duration {
             self.update_task_state(task_id, TaskState::Completed);
@@ -246,10 +641,44 @@ impl Inspector {
             return;
         }
 
-        self.update_task_state(task_id, TaskState::Failed);
+        self.update_task_state(
+            task_id,
+            TaskState::Failed {
+                error: error.clone(),
+            },
+        );
         self.add_event(task_id, EventKind::TaskFailed { error });
     }
 
+    /// Mark task as panicked
+    pub fn task_panicked(&self, task_id: TaskId, message: String) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        self.update_task_state(
+            task_id,
+            TaskState::Panicked {
+                message: message.clone(),
+            },
+        );
+        self.add_event(task_id, EventKind::TaskPanicked { message });
+    }
+
+    /// Mark task as cancelled, i.e. dropped before it completed
+    pub fn task_cancelled(&self, task_id: TaskId) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let duration = self.state.tasks.with(task_id, TaskInfo::age);
+
+        if let Some(duration) = duration {
+            self.update_task_state(task_id, TaskState::Cancelled);
+            self.add_event(task_id, EventKind::TaskCancelled { duration });
+        }
+    }
+
     /// Record an inspection point
     pub fn inspection_point(&self, task_id: TaskId, label: String, message: Option<String>) {
         if !self.is_enabled() {
@@ -260,32 +689,107 @@ impl Inspector {
     }
 
     /// Add an event to the timeline
+    ///
+    /// Events for tasks that are not tracked (unsampled, rejected or
+    /// evicted) are discarded, as are poll, await and waker events when the
+    /// corresponding tracking switch is off. Events are buffered per thread
+    /// and become visible in the timeline on the next read or flush. Once the
+    /// timeline exceeds `max_events`, the configured eviction policy makes room.
     pub fn add_event(&self, task_id: TaskId, kind: EventKind) {
-        let event_id = self.state.event_counter.fetch_add(1, Ordering::Relaxed);
-        let event = Event::new(event_id, task_id, kind);
-        self.state.timeline.write().add_event(event);
+        let config = &self.state.config;
+        let suppressed = match kind {
+            EventKind::PollStarted | EventKind::PollEnded { .. } => !config.track_polls(),
+            EventKind::AwaitStarted { .. } | EventKind::AwaitEnded { .. } => !config.track_awaits(),
+            EventKind::TaskWoken { .. }
+            | EventKind::WakerCloned
+            | EventKind::WakerDropped
+            | EventKind::TaskScheduled { .. } => !config.track_wakers(),
+            _ => false,
+        };
+
+        if suppressed || !self.is_tracked(task_id) {
+            return;
+        }
+
+        self.push_event(task_id, kind);
+    }
+
+    /// Buffer an event for a task already known to be tracked
+    fn push_event(&self, task_id: TaskId, kind: EventKind) {
+        let buffered = self
+            .state
+            .pending_events
+            .push(&self.state.event_counter, task_id, kind);
+
+        if buffered >= FLUSH_THRESHOLD {
+            self.flush_events();
+        }
+    }
+
+    /// Merge the per-thread event buffers into the timeline
+    ///
+    /// Called automatically before the timeline is read, and by writers
+    /// whose buffer has filled up.
+    pub fn flush_events(&self) {
+        let config = &self.state.config;
+        let mut timeline = self.state.timeline.write();
+        timeline.set_capacity(config.max_events());
+        timeline.set_policy(config.eviction_policy());
+
+        let evicted = self.state.pending_events.drain_into(&mut timeline);
+        if evicted > 0 {
+            self.state
+                .dropped_events
+                .fetch_add(evicted as u64, Ordering::Relaxed);
+        }
+    }
+
+    /// Flush pending events, then lock the timeline for reading
+    fn read_timeline(&self) -> parking_lot::RwLockReadGuard<'_, Timeline> {
+        self.flush_events();
+        self.state.timeline.read()
     }
 
     /// Get a task by ID
     pub fn get_task(&self, task_id: TaskId) -> Option<TaskInfo> {
-        self.state.tasks.read().get(&task_id).cloned()
+        self.state.tasks.get(task_id)
     }
 
     /// Get all tasks
     pub fn get_all_tasks(&self) -> Vec<TaskInfo> {
-        self.state.tasks.read().values().cloned().collect()
+        self.state.tasks.snapshot()
     }
 
     /// Get all events
     pub fn get_events(&self) -> Vec<Event> {
-        self.state.timeline.read().events().to_vec()
+        self.read_timeline().events().to_vec()
+    }
+
+    /// Get the events recorded since the cursor's last read and advance it
+    ///
+    /// Only new events are cloned, which makes this suitable for readers
+    /// polling the timeline periodically.
+    pub fn events_since(&self, cursor: &mut EventCursor) -> Vec<Event> {
+        let events: Vec<Event> = self.read_timeline().events_after(cursor).cloned().collect();
+
+        if let Some(last) = events.last() {
+            cursor.advance(last.id);
+        }
+
+        events
+    }
+
+    /// Run a closure with read access to the timeline, without cloning it
+    ///
+    /// The timeline is locked for the duration of the closure, so keep it
+    /// short to avoid stalling instrumented tasks.
+    pub fn with_timeline<R>(&self, f: impl FnOnce(&Timeline) -> R) -> R {
+        f(&self.read_timeline())
     }
 
     /// Get events for a specific task
     pub fn get_task_events(&self, task_id: TaskId) -> Vec<Event> {
-        self.state
-            .timeline
-            .read()
+        self.read_timeline()
             .events_for_task(task_id)
             .into_iter()
             .cloned()
@@ -298,20 +802,24 @@ impl Inspector {
         use crate::timeline::EventKind;
 
         let mut profiler = Profiler::new();
-        let tasks = self.state.tasks.read();
-        let timeline = self.state.timeline.read();
+        let tasks = self.get_all_tasks();
+        let timeline = self.read_timeline();
+
+        let mut events_by_task: HashMap<TaskId, Vec<&Event>> = HashMap::new();
+        for event in timeline.iter_events() {
+            events_by_task.entry(event.task_id).or_default().push(event);
+        }
 
-        for task in tasks.values() {
+        for task in &tasks {
             let mut metrics = TaskMetrics::new(task.id, task.name.clone());
 
             // Calculate durations
             metrics.total_duration = task.age();
             metrics.running_time = task.total_run_time;
-            metrics.blocked_time = if metrics.total_duration > task.total_run_time {
-                metrics.total_duration - task.total_run_time
-            } else {
-                Duration::ZERO
-            };
+            metrics.blocked_time = metrics
+                .total_duration
+                .checked_sub(task.total_run_time)
+                .unwrap_or(Duration::ZERO);
 
             // Set poll count
             metrics.poll_count = task.poll_count;
@@ -325,25 +833,20 @@ impl Inspector {
             metrics.completed = matches!(task.state, TaskState::Completed);
 
             // Collect await durations from events
-            let task_events: Vec<&Event> = timeline
-                .events()
-                .into_iter()
-                .filter(|e| e.task_id == task.id)
-                .collect();
-
-            let mut await_start_times: HashMap<String, std::time::Instant> = HashMap::new();
+            let task_events = events_by_task.remove(&task.id).unwrap_or_default();
 
+            // Await ends carry the measured wait, so a start evicted from
+            // the timeline does not lose the sample
             for event in task_events {
                 match &event.kind {
-                    EventKind::AwaitStarted { await_point, .. } => {
-                        await_start_times.insert(await_point.clone(), event.timestamp);
+                    EventKind::AwaitEnded { duration, .. } => {
+                        metrics.await_durations.push(*duration);
+                        metrics.await_count += 1;
                     }
-                    EventKind::AwaitEnded { await_point, .. } => {
-                        if let Some(start_time) = await_start_times.remove(&await_point.clone()) {
-                            let duration = event.timestamp.duration_since(start_time);
-                            metrics.await_durations.push(duration);
-                            metrics.await_count += 1;
-                        }
+                    EventKind::LongPoll { duration, .. } => {
+                        metrics.long_polls += 1;
+                        metrics.blocking_time += *duration;
+                        metrics.max_long_poll = metrics.max_long_poll.max(*duration);
                     }
                     _ => {}
                 }
@@ -357,30 +860,26 @@ impl Inspector {
 
     /// Get statistics
     pub fn stats(&self) -> InspectorStats {
-        let tasks = self.state.tasks.read();
-        let timeline = self.state.timeline.read();
-
-        let total = tasks.len();
-        let pending = tasks
-            .values()
-            .filter(|t| matches!(t.state, TaskState::Pending))
-            .count();
-        let running = tasks
-            .values()
-            .filter(|t| matches!(t.state, TaskState::Running))
-            .count();
-        let blocked = tasks
-            .values()
-            .filter(|t| matches!(t.state, TaskState::Blocked { .. }))
-            .count();
-        let completed = tasks
-            .values()
-            .filter(|t| matches!(t.state, TaskState::Completed))
-            .count();
-        let failed = tasks
-            .values()
-            .filter(|t| matches!(t.state, TaskState::Failed))
-            .count();
+        let timeline = self.read_timeline();
+
+        let (mut total, mut pending, mut running, mut blocked) = (0, 0, 0, 0);
+        let (mut completed, mut failed, mut panicked, mut cancelled) = (0, 0, 0, 0);
+        let mut lost_wakeups = 0;
+        self.state.tasks.for_each(|task| {
+            total += 1;
+            if !task.state.is_terminal() && task.wakers.lost_wakeup() {
+                lost_wakeups += 1;
+            }
+            match task.state {
+                TaskState::Pending => pending += 1,
+                TaskState::Running => running += 1,
+                TaskState::Blocked { .. } => blocked += 1,
+                TaskState::Completed => completed += 1,
+                TaskState::Failed { .. } => failed += 1,
+                TaskState::Panicked { .. } => panicked += 1,
+                TaskState::Cancelled => cancelled += 1,
+            }
+        });
 
         InspectorStats {
             total_tasks: total,
@@ -389,16 +888,30 @@ impl Inspector {
             blocked_tasks: blocked,
             completed_tasks: completed,
             failed_tasks: failed,
+            panicked_tasks: panicked,
+            cancelled_tasks: cancelled,
+            lost_wakeups,
+            long_polls: self.state.long_polls.load(Ordering::Relaxed),
+            active_deadlocks: self.state.deadlocks.read().len(),
             total_events: timeline.len(),
             timeline_duration: timeline.duration(),
+            dropped_tasks: self.state.dropped_tasks.load(Ordering::Relaxed),
+            unsampled_tasks: self.state.unsampled_tasks.load(Ordering::Relaxed),
+            dropped_events: self.state.dropped_events.load(Ordering::Relaxed),
         }
     }
 
     /// Clear all data
     pub fn clear(&self) {
-        self.state.tasks.write().clear();
+        self.state.tasks.clear();
+        self.state.pending_events.clear();
         self.state.timeline.write().clear();
         self.state.event_counter.store(1, Ordering::Relaxed);
+        self.state.dropped_tasks.store(0, Ordering::Relaxed);
+        self.state.unsampled_tasks.store(0, Ordering::Relaxed);
+        self.state.dropped_events.store(0, Ordering::Relaxed);
+        self.state.long_polls.store(0, Ordering::Relaxed);
+        self.state.deadlocks.write().clear();
     }
 
     /// Reset the inspector
@@ -414,6 +927,34 @@ impl Default for Inspector {
     }
 }
 
+/// A task that is pending but can never be woken again
+#[derive(Debug, Clone)]
+pub struct LostWakeup {
+    /// The stuck task
+    pub task_id: TaskId,
+    /// Task name
+    pub name: String,
+    /// Await point the task is blocked on, if known
+    pub await_point: Option<String>,
+    /// Source location of the await point, or of the blocked task
+    pub location: Option<String>,
+    /// Time since the task was last updated
+    pub stuck_for: Duration,
+}
+
+impl std::fmt::Display for LostWakeup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.task_id, self.name)?;
+        if let Some(await_point) = &self.await_point {
+            write!(f, " at {await_point}")?;
+        }
+        if let Some(location) = &self.location {
+            write!(f, " ({location})")?;
+        }
+        write!(f, ", stuck for {:.2}s", self.stuck_for.as_secs_f64())
+    }
+}
+
 /// Inspector statistics
 #[derive(Debug, Clone)]
 pub struct InspectorStats {
@@ -427,17 +968,34 @@ pub struct InspectorStats {
     pub blocked_tasks: usize,
     /// Completed tasks
     pub completed_tasks: usize,
-    /// Failed tasks
+    /// Tasks that returned an error
     pub failed_tasks: usize,
+    /// Tasks that panicked
+    pub panicked_tasks: usize,
+    /// Tasks dropped before completing
+    pub cancelled_tasks: usize,
+    /// Pending tasks that can never be woken again
+    pub lost_wakeups: usize,
+    /// Polls that ran past the long poll threshold
+    pub long_polls: u64,
+    /// Deadlock cycles found by the last deadlock monitor scan
+    pub active_deadlocks: usize,
     /// Total number of events
     pub total_events: usize,
     /// Total timeline duration
     pub timeline_duration: Duration,
+    /// Tasks not admitted because of the `max_tasks` limit
+    pub dropped_tasks: u64,
+    /// Tasks skipped because of the sampling rate
+    pub unsampled_tasks: u64,
+    /// Events evicted because of the `max_events` limit
+    pub dropped_events: u64,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::task::TaskLevel;
 
     #[test]
     fn test_inspector_creation() {
@@ -476,4 +1034,322 @@ mod tests {
         let stats = inspector.stats();
         assert_eq!(stats.total_tasks, 2);
     }
+
+    #[test]
+    fn test_concurrent_recording_is_ordered() {
+        let config = Config::new();
+        config.set_max_events(0);
+        let inspector = Inspector::with_config(config);
+
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let inspector = inspector.clone();
+                std::thread::spawn(move || {
+                    let task_id = inspector.register_task(format!("worker_{i}"));
+                    for _ in 0..500 {
+                        inspector.poll_started(task_id);
+                        inspector.poll_ended(task_id, Duration::from_micros(1));
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let events = inspector.get_events();
+        assert!(events.windows(2).all(|w| w[0].id < w[1].id));
+        assert!(inspector
+            .get_all_tasks()
+            .iter()
+            .all(|t| t.poll_count == 500));
+    }
+
+    #[test]
+    fn test_max_events_evicts_oldest() {
+        let config = Config::new();
+        config.set_max_events(5);
+        let inspector = Inspector::with_config(config);
+        let task_id = inspector.register_task("chatty".to_string());
+
+        for i in 0..10 {
+            inspector.inspection_point(task_id, format!("point_{i}"), None);
+        }
+
+        let stats = inspector.stats();
+        assert_eq!(stats.total_events, 5);
+        assert_eq!(stats.dropped_events, 6);
+        assert!(matches!(
+            &inspector.get_events()[4].kind,
+            EventKind::InspectionPoint { label, .. } if label == "point_9"
+        ));
+    }
+
+    #[test]
+    fn test_events_since_cursor() {
+        let inspector = Inspector::with_config(Config::new());
+        let task_id = inspector.register_task("reader".to_string());
+        let mut cursor = EventCursor::new();
+
+        assert_eq!(inspector.events_since(&mut cursor).len(), 1);
+        assert!(inspector.events_since(&mut cursor).is_empty());
+
+        inspector.inspection_point(task_id, "a".to_string(), None);
+        inspector.inspection_point(task_id, "b".to_string(), None);
+
+        let new_events = inspector.events_since(&mut cursor);
+        assert_eq!(new_events.len(), 2);
+        assert_eq!(cursor.last_seen(), Some(new_events[1].id));
+    }
+
+    #[test]
+    fn test_max_tasks_evicts_finished_then_rejects() {
+        let config = Config::new();
+        config.set_max_tasks(2);
+        let inspector = Inspector::with_config(config);
+
+        let first = inspector.register_task("first".to_string());
+        let second = inspector.register_task("second".to_string());
+        inspector.task_completed(first);
+
+        // Room is made by evicting the completed task
+        let third = inspector.register_task("third".to_string());
+        assert!(inspector.get_task(first).is_none());
+        assert!(inspector.is_tracked(second));
+        assert!(inspector.is_tracked(third));
+
+        // Nothing left to evict: the new task is not admitted
+        let fourth = inspector.register_task("fourth".to_string());
+        assert!(!inspector.is_tracked(fourth));
+        inspector.poll_started(fourth);
+        assert!(inspector.get_task_events(fourth).is_empty());
+
+        let stats = inspector.stats();
+        assert_eq!(stats.total_tasks, 2);
+        assert_eq!(stats.dropped_tasks, 1);
+    }
+
+    #[test]
+    fn test_evicted_tasks_drop_their_relationships() {
+        let config = Config::new();
+        config.set_max_tasks(3);
+        let inspector = Inspector::with_config(config);
+
+        let parent = inspector.register_task("parent".to_string());
+        let first = inspector.register_child_task("first".to_string(), parent);
+        let second = inspector.register_child_task("second".to_string(), parent);
+        inspector.add_relationship(Relationship::new(
+            first,
+            second,
+            RelationshipType::Dependency,
+        ));
+        inspector.task_completed(first);
+
+        // Evicting the first child drops the relationships on both ends
+        let third = inspector.register_child_task("third".to_string(), parent);
+        assert!(!inspector.is_tracked(first));
+        let relationships: Vec<_> = inspector
+            .relationships()
+            .into_iter()
+            .map(|r| (r.from, r.to))
+            .collect();
+        assert_eq!(relationships, vec![(parent, second), (parent, third)]);
+    }
+
+    #[test]
+    fn test_tasks_below_min_level_are_not_tracked() {
+        let config = Config::new();
+        config.set_min_level(TaskLevel::Info);
+        let inspector = Inspector::with_config(config);
+
+        let quiet = inspector.register_task_with_info(
+            TaskInfo::new("quiet".to_string()).with_level(TaskLevel::Debug),
+        );
+        let loud = inspector
+            .register_task_with_info(TaskInfo::new("loud".to_string()).with_level(TaskLevel::Warn));
+
+        assert!(!inspector.is_tracked(quiet));
+        assert_eq!(inspector.get_task(loud).unwrap().level, TaskLevel::Warn);
+    }
+
+    #[test]
+    fn test_unsampled_tasks_record_nothing() {
+        let config = Config::new();
+        config.set_sampling_rate(2);
+        let inspector = Inspector::with_config(config);
+
+        let sampled = inspector.register_task("sampled".to_string());
+        let skipped = inspector.register_task("skipped".to_string());
+
+        inspector.poll_started(skipped);
+        inspector.await_started(skipped, "io".to_string(), None);
+        inspector.task_completed(skipped);
+
+        assert!(inspector.is_tracked(sampled));
+        assert!(!inspector.is_tracked(skipped));
+        assert!(inspector.get_task_events(skipped).is_empty());
+        assert_eq!(inspector.stats().unsampled_tasks, 1);
+    }
+
+    #[test]
+    fn test_tracking_switches_suppress_events() {
+        let config = Config::new();
+        config.set_track_polls(false);
+        config.set_track_awaits(false);
+        let inspector = Inspector::with_config(config);
+        let task_id = inspector.register_task("quiet".to_string());
+
+        inspector.poll_started(task_id);
+        inspector.poll_ended(task_id, Duration::from_millis(1));
+        inspector.await_started(task_id, "io".to_string(), None);
+        inspector.await_ended(task_id, "io".to_string(), Duration::from_millis(1));
+
+        let task = inspector.get_task(task_id).unwrap();
+        assert_eq!(task.poll_count, 0);
+        assert_eq!(task.state, TaskState::Pending);
+        assert!(inspector
+            .get_task_events(task_id)
+            .iter()
+            .all(|e| matches!(e.kind, EventKind::TaskSpawned { .. })));
+    }
+
+    #[test]
+    fn test_leaked_tasks_are_grouped() {
+        let inspector = Inspector::with_config(Config::new());
+        let before = inspector.register_task("old".to_string());
+        let since = Instant::now();
+
+        let worker =
+            || TaskInfo::new("worker".to_string()).with_location("src/jobs.rs:4:1".to_string());
+        let leaked: Vec<TaskId> = (0..3)
+            .map(|_| inspector.register_task_with_info(worker()))
+            .collect();
+        let finished = inspector.register_task_with_info(worker());
+        inspector.task_completed(finished);
+        inspector.register_task("poller".to_string());
+
+        let report = inspector.leaked_tasks(since);
+        assert_eq!(report.total(), 4);
+        assert_eq!(report.groups[0].name, "worker");
+        assert_eq!(
+            report.groups[0].location.as_deref(),
+            Some("src/jobs.rs:4:1")
+        );
+        assert_eq!(report.groups[0].task_ids, leaked);
+        assert!(report.groups[0].oldest >= report.groups[0].youngest);
+        assert_eq!(report.groups[1].name, "poller");
+        assert!(!report.groups.iter().any(|g| g.task_ids.contains(&before)));
+    }
+
+    #[test]
+    fn test_long_polls_are_flagged() {
+        let config = Config::new();
+        config.set_long_poll_threshold(Duration::from_millis(2));
+        let inspector = Inspector::with_config(config);
+        let task_id = inspector.register_task("busy".to_string());
+
+        inspector.poll_started(task_id);
+        inspector.await_started(
+            task_id,
+            "read".to_string(),
+            Some("src/io.rs:7:9".to_string()),
+        );
+        inspector.poll_ended(task_id, Duration::from_millis(5));
+        inspector.poll_started(task_id);
+        inspector.poll_ended(task_id, Duration::from_millis(1));
+
+        let long_polls: Vec<EventKind> = inspector
+            .get_task_events(task_id)
+            .into_iter()
+            .map(|e| e.kind)
+            .filter(|kind| matches!(kind, EventKind::LongPoll { .. }))
+            .collect();
+        assert_eq!(long_polls.len(), 1);
+        assert!(matches!(
+            &long_polls[0],
+            EventKind::LongPoll { duration, await_point, location }
+                if *duration == Duration::from_millis(5)
+                    && await_point.as_deref() == Some("read")
+                    && location.as_deref() == Some("src/io.rs:7:9")
+        ));
+        assert_eq!(inspector.stats().long_polls, 1);
+
+        let profiler = inspector.build_profiler();
+        let blocking = profiler.top_blocking_tasks(5);
+        assert_eq!(blocking.len(), 1);
+        assert_eq!(blocking[0].task_id, task_id);
+        assert_eq!(blocking[0].blocking_time, Duration::from_millis(5));
+    }
+
+    #[test]
+    fn test_long_polls_report_the_inline_parent_await_point() {
+        let config = Config::new();
+        config.set_long_poll_threshold(Duration::from_millis(2));
+        let inspector = Inspector::with_config(config);
+        let parent = inspector.register_task("parent".to_string());
+        let child = inspector.register_task_with_info(
+            TaskInfo::new("child".to_string()).with_parent_kind(parent, ChildKind::Inline),
+        );
+
+        inspector.await_started(
+            parent,
+            "child".to_string(),
+            Some("src/a.rs:3:5".to_string()),
+        );
+        inspector.poll_started(child);
+        inspector.poll_ended(child, Duration::from_millis(5));
+
+        let long_poll = inspector
+            .get_task_events(child)
+            .into_iter()
+            .find(|e| matches!(e.kind, EventKind::LongPoll { .. }))
+            .unwrap();
+        assert!(matches!(
+            long_poll.kind,
+            EventKind::LongPoll { await_point, location, .. }
+                if await_point.as_deref() == Some("child")
+                    && location.as_deref() == Some("src/a.rs:3:5")
+        ));
+
+        // An ended await no longer counts as the current one
+        inspector.await_ended(parent, "child".to_string(), Duration::from_millis(5));
+        assert!(inspector.get_task(parent).unwrap().await_point.is_none());
+    }
+
+    #[test]
+    fn test_build_graph_from_relationships() {
+        let inspector = Inspector::with_config(Config::new());
+        let parent = inspector.register_task("parent".to_string());
+        let spawned = inspector.register_child_task("spawned".to_string(), parent);
+        let inline = inspector.register_task_with_info(
+            TaskInfo::new("inline".to_string()).with_parent_kind(parent, ChildKind::Inline),
+        );
+
+        // Recorded once, and only between tracked tasks
+        let awaits = Relationship::new(parent, spawned, RelationshipType::AwaitsOn);
+        inspector.add_relationship(awaits.clone());
+        inspector.add_relationship(awaits);
+        inspector.add_relationship(Relationship::new(
+            parent,
+            TaskId::new(),
+            RelationshipType::AwaitsOn,
+        ));
+        assert_eq!(inspector.relationships().len(), 3);
+
+        let graph = inspector.build_graph();
+        let mut related = graph.get_related_tasks(parent);
+        related.sort_by_key(|(task, _)| task.as_u64());
+        assert_eq!(
+            related,
+            vec![
+                (spawned, RelationshipType::Spawned),
+                (spawned, RelationshipType::AwaitsOn),
+                (inline, RelationshipType::AwaitsOn),
+            ]
+        );
+        assert_eq!(graph.find_critical_path().len(), 2);
+
+        inspector.clear();
+        assert!(inspector.relationships().is_empty());
+    }
 }
@@ -0,0 +1,327 @@
+//! Sharded storage backing the inspector hot path
+//!
+//! Tasks live in a map split into independently locked shards, and events are
+//! appended to per-thread buffers that a collector later merges into the
+//! [`Timeline`]. Worker threads of a multi-threaded runtime therefore only
+//! contend when they touch the same shard, instead of serialising on one lock.
+//! Relationships are stored with the task they start from, so they share its
+//! shard and are dropped when it is evicted.
+
+use crate::graph::Relationship;
+use crate::task::{TaskId, TaskInfo};
+use crate::timeline::{Event, EventKind, Timeline};
+use parking_lot::{Mutex, RwLock};
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Instant;
+
+/// Number of shards for both tasks and event buffers
+#[cfg(not(feature = "unsharded"))]
+const SHARD_COUNT: usize = 32;
+
+/// Buffered events per shard before the writer merges them into the timeline
+#[cfg(not(feature = "unsharded"))]
+pub(crate) const FLUSH_THRESHOLD: usize = 256;
+
+// The `unsharded` feature restores the design before sharding, as a baseline
+// for `benches/poll_overhead.rs`: one lock for every task, and every event
+// written straight to the timeline under its write lock.
+#[cfg(feature = "unsharded")]
+const SHARD_COUNT: usize = 1;
+#[cfg(feature = "unsharded")]
+pub(crate) const FLUSH_THRESHOLD: usize = 1;
+
+thread_local! {
+    /// Event shard assigned to the current thread
+    static THREAD_SHARD: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+/// Get the event shard of the current thread, assigning one on first use
+fn thread_shard() -> usize {
+    static NEXT_SHARD: AtomicUsize = AtomicUsize::new(0);
+
+    THREAD_SHARD.with(|shard| {
+        if let Some(index) = shard.get() {
+            return index;
+        }
+        let index = NEXT_SHARD.fetch_add(1, Ordering::Relaxed) % SHARD_COUNT;
+        shard.set(Some(index));
+        index
+    })
+}
+
+/// One lock's worth of tasks
+#[derive(Default)]
+struct TaskShard {
+    tasks: HashMap<TaskId, TaskInfo>,
+    /// Tasks of this shard in the order they finished, oldest first
+    ///
+    /// Entries of tasks that were removed since are skipped on eviction.
+    finished: VecDeque<(Instant, TaskId)>,
+    /// Relationships of the tasks of this shard
+    links: HashMap<TaskId, TaskLinks>,
+}
+
+/// Relationships of one task
+#[derive(Default)]
+struct TaskLinks {
+    /// Relationships from this task, with the order they were recorded in
+    outgoing: HashMap<Relationship, u64>,
+    /// Tasks with relationships to this task
+    incoming: HashSet<TaskId>,
+}
+
+/// Task map split into independently locked shards
+pub(crate) struct TaskShards {
+    shards: Box<[RwLock<TaskShard>]>,
+    len: AtomicUsize,
+    /// Order of the next relationship recorded
+    next_link: AtomicU64,
+}
+
+impl TaskShards {
+    pub(crate) fn new() -> Self {
+        Self {
+            shards: (0..SHARD_COUNT)
+                .map(|_| RwLock::new(TaskShard::default()))
+                .collect(),
+            len: AtomicUsize::new(0),
+            next_link: AtomicU64::new(0),
+        }
+    }
+
+    fn shard(&self, task_id: TaskId) -> &RwLock<TaskShard> {
+        // Task IDs are sequential, so the low bits spread evenly
+        &self.shards[(task_id.as_u64() as usize) % SHARD_COUNT]
+    }
+
+    /// Number of tracked tasks
+    pub(crate) fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn contains(&self, task_id: TaskId) -> bool {
+        self.shard(task_id).read().tasks.contains_key(&task_id)
+    }
+
+    pub(crate) fn get(&self, task_id: TaskId) -> Option<TaskInfo> {
+        self.shard(task_id).read().tasks.get(&task_id).cloned()
+    }
+
+    /// Run a closure on a task, if tracked
+    pub(crate) fn with<R>(&self, task_id: TaskId, f: impl FnOnce(&TaskInfo) -> R) -> Option<R> {
+        self.shard(task_id).read().tasks.get(&task_id).map(f)
+    }
+
+    /// Run a closure on a mutable task, if tracked
+    pub(crate) fn with_mut<R>(
+        &self,
+        task_id: TaskId,
+        f: impl FnOnce(&mut TaskInfo) -> R,
+    ) -> Option<R> {
+        let mut shard = self.shard(task_id).write();
+        let task = shard.tasks.get_mut(&task_id)?;
+        let was_terminal = task.state.is_terminal();
+        let result = f(task);
+        if !was_terminal && task.state.is_terminal() {
+            shard.finished.push_back((Instant::now(), task_id));
+        }
+        Some(result)
+    }
+
+    pub(crate) fn insert(&self, task: TaskInfo) {
+        let mut shard = self.shard(task.id).write();
+        if task.state.is_terminal() {
+            shard.finished.push_back((Instant::now(), task.id));
+        }
+        if shard.tasks.insert(task.id, task).is_none() {
+            self.len.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Visit every task, one shard at a time
+    pub(crate) fn for_each(&self, mut f: impl FnMut(&TaskInfo)) {
+        for shard in self.shards.iter() {
+            shard.read().tasks.values().for_each(&mut f);
+        }
+    }
+
+    /// Clone every task
+    pub(crate) fn snapshot(&self) -> Vec<TaskInfo> {
+        let mut tasks = Vec::with_capacity(self.len());
+        self.for_each(|task| tasks.push(task.clone()));
+        tasks
+    }
+
+    /// Record a relationship between two tracked tasks, keeping it once
+    pub(crate) fn add_relationship(&self, relationship: Relationship) {
+        let (from, to) = (relationship.from, relationship.to);
+        if !self.contains(to) {
+            return;
+        }
+
+        let order = {
+            let mut shard = self.shard(from).write();
+            if !shard.tasks.contains_key(&from) {
+                return;
+            }
+            let links = shard.links.entry(from).or_default();
+            if links.outgoing.contains_key(&relationship) {
+                return;
+            }
+            let order = self.next_link.fetch_add(1, Ordering::Relaxed);
+            links.outgoing.insert(relationship, order);
+            order
+        };
+
+        let mut shard = self.shard(to).write();
+        if shard.tasks.contains_key(&to) {
+            shard.links.entry(to).or_default().incoming.insert(from);
+            return;
+        }
+        drop(shard);
+
+        // The target was evicted in the meantime
+        if let Some(links) = self.shard(from).write().links.get_mut(&from) {
+            links.outgoing.retain(|_, recorded| *recorded != order);
+        }
+    }
+
+    /// Clone every relationship, in the order they were recorded
+    pub(crate) fn relationships(&self) -> Vec<Relationship> {
+        let mut relationships = Vec::new();
+        for shard in self.shards.iter() {
+            for links in shard.read().links.values() {
+                relationships.extend(
+                    links
+                        .outgoing
+                        .iter()
+                        .map(|(relationship, order)| (*order, relationship.clone())),
+                );
+            }
+        }
+
+        relationships.sort_unstable_by_key(|(order, _)| *order);
+        relationships
+            .into_iter()
+            .map(|(_, relationship)| relationship)
+            .collect()
+    }
+
+    /// Drop the relationships of a removed task from the tasks at their
+    /// other end
+    fn unlink(&self, task_id: TaskId, links: TaskLinks) {
+        for source in links.incoming {
+            if let Some(links) = self.shard(source).write().links.get_mut(&source) {
+                links
+                    .outgoing
+                    .retain(|relationship, _| relationship.to != task_id);
+            }
+        }
+        for target in links
+            .outgoing
+            .into_keys()
+            .map(|relationship| relationship.to)
+        {
+            if let Some(links) = self.shard(target).write().links.get_mut(&target) {
+                links.incoming.remove(&task_id);
+            }
+        }
+    }
+
+    /// Evict the task that finished first, returning whether one was found
+    ///
+    /// Only the head of each shard's finish queue is looked at, so this
+    /// costs the same however many tasks are tracked.
+    pub(crate) fn evict_oldest_finished(&self) -> bool {
+        loop {
+            let oldest = self
+                .shards
+                .iter()
+                .enumerate()
+                .filter_map(|(index, shard)| {
+                    let (finished_at, _) = *shard.read().finished.front()?;
+                    Some((finished_at, index))
+                })
+                .min();
+            let Some((_, index)) = oldest else {
+                return false;
+            };
+
+            let mut shard = self.shards[index].write();
+            let Some((_, task_id)) = shard.finished.pop_front() else {
+                continue;
+            };
+            let finished = shard
+                .tasks
+                .get(&task_id)
+                .is_some_and(|task| task.state.is_terminal());
+            if finished {
+                shard.tasks.remove(&task_id);
+                let links = shard.links.remove(&task_id);
+                self.len.fetch_sub(1, Ordering::Relaxed);
+                drop(shard);
+
+                if let Some(links) = links {
+                    self.unlink(task_id, links);
+                }
+                return true;
+            }
+        }
+    }
+
+    pub(crate) fn clear(&self) {
+        for shard in self.shards.iter() {
+            let mut shard = shard.write();
+            self.len.fetch_sub(shard.tasks.len(), Ordering::Relaxed);
+            shard.tasks.clear();
+            shard.finished.clear();
+            shard.links.clear();
+        }
+    }
+}
+
+/// Per-thread event buffers merged into a timeline by a collector
+pub(crate) struct EventShards {
+    shards: Box<[Mutex<Vec<Event>>]>,
+}
+
+impl EventShards {
+    pub(crate) fn new() -> Self {
+        Self {
+            shards: (0..SHARD_COUNT).map(|_| Mutex::new(Vec::new())).collect(),
+        }
+    }
+
+    /// Append an event to the current thread's buffer
+    ///
+    /// The event ID is drawn while the buffer is locked, so once a collector
+    /// holds every buffer lock all previously assigned IDs are visible to it.
+    /// Returns the buffer length, letting the caller decide when to flush.
+    pub(crate) fn push(&self, counter: &AtomicU64, task_id: TaskId, kind: EventKind) -> usize {
+        let mut shard = self.shards[thread_shard()].lock();
+        let event_id = counter.fetch_add(1, Ordering::Relaxed);
+        shard.push(Event::new(event_id, task_id, kind));
+        shard.len()
+    }
+
+    /// Merge every buffered event into the timeline in ID order
+    ///
+    /// Returns the number of events the timeline evicted to stay in capacity.
+    pub(crate) fn drain_into(&self, timeline: &mut Timeline) -> usize {
+        let mut guards: Vec<_> = self.shards.iter().map(Mutex::lock).collect();
+        let mut events: Vec<Event> = guards.iter_mut().flat_map(|g| g.drain(..)).collect();
+        drop(guards);
+
+        events.sort_unstable_by_key(|e| e.id);
+        events.into_iter().map(|e| timeline.add_event(e)).sum()
+    }
+
+    /// Discard every buffered event
+    pub(crate) fn clear(&self) {
+        for shard in self.shards.iter() {
+            shard.lock().clear();
+        }
+    }
+}
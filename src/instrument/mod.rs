@@ -2,8 +2,20 @@
 //!
 //! This module provides macros and helpers for instrumenting async code.
 
+mod waker;
+
+pub use waker::WakerTracker;
+
 use crate::inspector::Inspector;
-use crate::task::TaskId;
+use crate::task::{ChildKind, TaskId, TaskInfo};
+use std::any::Any;
+use std::cell::RefCell;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::task::{Context, Poll};
 use std::time::Instant;
 
 /// Context for tracking async operations
@@ -54,6 +66,9 @@ impl Drop for PollGuard {
 }
 
 /// Helper for tracking await operations
+///
+/// Marks the task as blocked on the await point when created and records the
+/// time spent waiting when dropped.
 pub struct AwaitGuard {
     task_id: TaskId,
     await_point: String,
@@ -63,13 +78,23 @@ pub struct AwaitGuard {
 impl AwaitGuard {
     /// Create a new await guard
     pub fn new(task_id: TaskId, await_point: String) -> Self {
-        Inspector::global().await_started(task_id, await_point.clone(), None);
+        Self::with_location(task_id, await_point, None)
+    }
+
+    /// Create a new await guard with the source location of the await
+    pub fn with_location(task_id: TaskId, await_point: String, location: Option<String>) -> Self {
+        Inspector::global().await_started(task_id, await_point.clone(), location);
         Self {
             task_id,
             await_point,
             start: Instant::now(),
         }
     }
+
+    /// Get the time spent waiting so far
+    pub fn elapsed(&self) -> std::time::Duration {
+        self.start.elapsed()
+    }
 }
 
 impl Drop for AwaitGuard {
@@ -163,24 +188,224 @@ macro_rules! inspect_task_failed {
     }};
 }
 
-// Thread-local storage for current task ID
+// Stack of tasks entered on this thread, innermost last
+//
+// A task is only current while one of its polls is running. Futures can move
+// between worker threads and interleave on one thread between polls, so the
+// context is entered on every poll and restored when the poll returns.
 thread_local! {
-    static CURRENT_TASK_ID: std::cell::RefCell<Option<TaskId>> = std::cell::RefCell::new(None);
+    static TASK_STACK: RefCell<Vec<TaskId>> = const { RefCell::new(Vec::new()) };
 }
 
 /// Get the current task ID
 pub fn current_task_id() -> Option<TaskId> {
-    CURRENT_TASK_ID.with(|id| *id.borrow())
+    TASK_STACK.with(|stack| stack.borrow().last().copied())
+}
+
+/// Check whether a task is entered anywhere on this thread's context stack
+pub(crate) fn is_task_entered(task_id: TaskId) -> bool {
+    TASK_STACK.with(|stack| stack.borrow().contains(&task_id))
 }
 
 /// Set the current task ID
+///
+/// Pushes the task onto this thread's context stack until the matching
+/// [`clear_current_task_id`]. Prefer [`enter_task`] or [`Instrumented`],
+/// which restore the previous task automatically.
 pub fn set_current_task_id(task_id: TaskId) {
-    CURRENT_TASK_ID.with(|id| *id.borrow_mut() = Some(task_id));
+    TASK_STACK.with(|stack| stack.borrow_mut().push(task_id));
 }
 
 /// Clear the current task ID
+///
+/// Pops the innermost task, making the enclosing task current again.
 pub fn clear_current_task_id() {
-    CURRENT_TASK_ID.with(|id| *id.borrow_mut() = None);
+    TASK_STACK.with(|stack| {
+        stack.borrow_mut().pop();
+    });
+}
+
+/// Register a task, as a child of the current task if there is one
+pub fn register_child_of_current(task: TaskInfo, kind: ChildKind) -> TaskId {
+    let task = match current_task_id() {
+        Some(parent) => task.with_parent_kind(parent, kind),
+        None => task,
+    };
+    Inspector::global().register_task_with_info(task)
+}
+
+/// Make a task current on this thread until the guard is dropped
+pub fn enter_task(task_id: TaskId) -> EnterGuard {
+    let depth = TASK_STACK.with(|stack| {
+        let mut stack = stack.borrow_mut();
+        stack.push(task_id);
+        stack.len() - 1
+    });
+
+    EnterGuard {
+        depth,
+        _not_send: PhantomData,
+    }
+}
+
+/// Guard restoring the previously current task when dropped
+///
+/// Returned by [`enter_task`]. It is tied to the thread it was created on.
+pub struct EnterGuard {
+    depth: usize,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for EnterGuard {
+    fn drop(&mut self) {
+        // Truncating also discards anything left unbalanced inside the scope
+        TASK_STACK.with(|stack| stack.borrow_mut().truncate(self.depth));
+    }
+}
+
+/// Future wrapper that makes a task current while the inner future is polled
+///
+/// Events recorded from inside the future are attributed to the task, on
+/// whichever thread the poll happens, and the caller's context is restored
+/// after every poll.
+pub struct Instrumented<F> {
+    future: F,
+    task_id: TaskId,
+}
+
+impl<F> Instrumented<F> {
+    /// Wrap a future so it runs in the context of a task
+    pub fn new(task_id: TaskId, future: F) -> Self {
+        Self { future, task_id }
+    }
+
+    /// Get the task ID
+    pub fn task_id(&self) -> TaskId {
+        self.task_id
+    }
+}
+
+impl<F: Future> Future for Instrumented<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // SAFETY: We don't move the future
+        let this = unsafe { self.get_unchecked_mut() };
+        let _enter = enter_task(this.task_id);
+
+        // SAFETY: We're pinning the projection
+        unsafe { Pin::new_unchecked(&mut this.future) }.poll(cx)
+    }
+}
+
+/// Future wrapper recording how a task ends
+///
+/// Runs the inner future in the task's context like [`Instrumented`]. A panic
+/// while polling marks the task as panicked before unwinding resumes, and
+/// dropping the future before it finishes (including aborting the runtime
+/// task that owns it) marks the task as cancelled. Completion is left to the
+/// caller, which can inspect the output.
+pub struct Traced<F> {
+    future: ManuallyDrop<F>,
+    task_id: TaskId,
+    finished: bool,
+}
+
+impl<F> Traced<F> {
+    /// Wrap a future so its panics and cancellation are recorded on a task
+    pub fn new(task_id: TaskId, future: F) -> Self {
+        Self {
+            future: ManuallyDrop::new(future),
+            task_id,
+            finished: false,
+        }
+    }
+}
+
+impl<F: Future> Future for Traced<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // SAFETY: We don't move the future
+        let this = unsafe { self.get_unchecked_mut() };
+        let _enter = enter_task(this.task_id);
+
+        // SAFETY: We're pinning the projection
+        let future = unsafe { Pin::new_unchecked(&mut *this.future) };
+
+        match panic::catch_unwind(AssertUnwindSafe(|| future.poll(cx))) {
+            Ok(Poll::Ready(output)) => {
+                this.finished = true;
+                Poll::Ready(output)
+            }
+            Ok(Poll::Pending) => Poll::Pending,
+            Err(payload) => {
+                this.finished = true;
+                Inspector::global().task_panicked(this.task_id, panic_message(&*payload));
+                panic::resume_unwind(payload)
+            }
+        }
+    }
+}
+
+impl<F> Drop for Traced<F> {
+    fn drop(&mut self) {
+        // Drop the inner future first so the guards it holds (such as an
+        // await in progress) are closed before the task is marked cancelled.
+        // SAFETY: The future is dropped in place exactly once and never used again
+        unsafe { ManuallyDrop::drop(&mut self.future) };
+
+        if !self.finished {
+            Inspector::global().task_cancelled(self.task_id);
+        }
+    }
+}
+
+/// Extract the message of a panic payload
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        (*message).to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Box<dyn Any>".to_string()
+    }
+}
+
+/// Failure detection for the return values of traced functions
+///
+/// `#[trace]` calls `(&Outcome(&value)).failure()` with both traits in
+/// scope. Method resolution prefers [`ResultOutcome`] when the value is a
+/// `Result` whose error implements `Display`, and falls back to
+/// [`AnyOutcome`] for every other type.
+#[doc(hidden)]
+pub mod outcome {
+    use std::fmt::Display;
+
+    /// Return value being checked for failure
+    pub struct Outcome<'a, T>(pub &'a T);
+
+    /// `Err` values are failures, described by their `Display` output
+    pub trait ResultOutcome {
+        /// Get the failure message, if the value is a failure
+        fn failure(&self) -> Option<String>;
+    }
+
+    impl<T, E: Display> ResultOutcome for Outcome<'_, Result<T, E>> {
+        fn failure(&self) -> Option<String> {
+            self.0.as_ref().err().map(ToString::to_string)
+        }
+    }
+
+    /// Any other value is a success
+    pub trait AnyOutcome {
+        /// Get the failure message, if the value is a failure
+        fn failure(&self) -> Option<String> {
+            None
+        }
+    }
+
+    impl<T> AnyOutcome for &Outcome<'_, T> {}
 }
 
 /// RAII guard for task tracking
@@ -209,37 +434,98 @@ impl Drop for TaskGuard {
     }
 }
 
-/// Helper function for await point instrumentation
-pub fn inspect_await_start(label: impl Into<String>, location: Option<String>) {
-    if let Some(task_id) = current_task_id() {
-        Inspector::global().add_event(
-            task_id,
-            crate::timeline::EventKind::AwaitStarted {
-                await_point: label.into(),
-                location,
-            },
-        );
+/// Guard failing a test that leaks tasks
+///
+/// The guard is a task of its own. Futures run through [`LeakGuard::enter`]
+/// are polled in its context, so tasks they create, and everything those
+/// create in turn, become its descendants. When the guard is dropped it
+/// panics with a [`LeakReport`](crate::inspector::LeakReport) if any of them
+/// is still unfinished. [`LeakGuard::scope`] does both for a test body.
+///
+/// ```rust,ignore
+/// #[tokio::test]
+/// async fn does_not_leak() {
+///     LeakGuard::scope("does_not_leak", async {
+///         spawn_tracked("worker", work()).await.unwrap();
+///     })
+///     .await;
+/// }
+/// ```
+pub struct LeakGuard {
+    task_id: TaskId,
+}
+
+impl LeakGuard {
+    /// Start checking for leaks
+    pub fn new(name: impl Into<String>) -> Self {
+        let task_id = Inspector::global().register_task(name.into());
+        Self { task_id }
+    }
+
+    /// Run a future, then fail if it leaked tasks
+    ///
+    /// # Panics
+    ///
+    /// Panics with the leak report if a task created by the future is still
+    /// unfinished once it completes.
+    pub async fn scope<F: Future>(name: impl Into<String>, future: F) -> F::Output {
+        let guard = Self::new(name);
+        let output = guard.enter(future).await;
+        drop(guard);
+        output
+    }
+
+    /// Wrap a future so tasks it creates are checked by this guard
+    pub fn enter<F>(&self, future: F) -> Instrumented<F> {
+        Instrumented::new(self.task_id, future)
+    }
+
+    /// Get the guard's task ID
+    pub fn task_id(&self) -> TaskId {
+        self.task_id
+    }
+
+    /// Report tasks created under the guard that are still unfinished
+    pub fn check(&self) -> crate::inspector::LeakReport {
+        Inspector::global().leaked_descendants(self.task_id)
     }
 }
 
-/// Helper function for await point completion
-pub fn inspect_await_end(label: impl Into<String>) {
-    if let Some(task_id) = current_task_id() {
-        // Calculate duration would require storing start time
-        // For now, just record completion
-        Inspector::global().add_event(
-            task_id,
-            crate::timeline::EventKind::AwaitEnded {
-                await_point: label.into(),
-                duration: std::time::Duration::from_micros(0), // TODO: track actual duration
-            },
-        );
+impl Drop for LeakGuard {
+    fn drop(&mut self) {
+        Inspector::global().task_completed(self.task_id);
+
+        // A failing test is already panicking; leaks would only add noise
+        if !std::thread::panicking() {
+            let report = self.check();
+            assert!(report.is_empty(), "{report}");
+        }
     }
 }
 
+/// Helper function for await point instrumentation
+///
+/// Starts timing an await in the current task. Pass the returned guard to
+/// [`inspect_await_end`] once the awaited future resolves.
+pub fn inspect_await_start(
+    label: impl Into<String>,
+    location: Option<String>,
+) -> Option<AwaitGuard> {
+    current_task_id().map(|task_id| AwaitGuard::with_location(task_id, label.into(), location))
+}
+
+/// Helper function for await point completion
+///
+/// Records how long the await took and marks the task as running again.
+pub fn inspect_await_end(guard: Option<AwaitGuard>) {
+    drop(guard);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::task::{TaskLevel, TaskState};
+    use crate::timeline::EventKind;
 
     #[test]
     fn test_current_task_id() {
@@ -250,6 +536,272 @@ mod tests {
         assert_eq!(current_task_id(), None);
     }
 
+    #[test]
+    fn test_enter_task_restores_outer_task() {
+        let outer = TaskId::new();
+        let inner = TaskId::new();
+
+        let outer_guard = enter_task(outer);
+        {
+            let _inner_guard = enter_task(inner);
+            assert_eq!(current_task_id(), Some(inner));
+
+            // An unbalanced set inside the scope is discarded on exit
+            set_current_task_id(TaskId::new());
+        }
+        assert_eq!(current_task_id(), Some(outer));
+
+        drop(outer_guard);
+        assert_eq!(current_task_id(), None);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_instrumented_context_follows_task() {
+        let run = |task_id: TaskId| {
+            tokio::spawn(Instrumented::new(task_id, async move {
+                for _ in 0..50 {
+                    assert_eq!(current_task_id(), Some(task_id));
+                    tokio::task::yield_now().await;
+                }
+                current_task_id()
+            }))
+        };
+
+        let a = TaskId::new();
+        let b = TaskId::new();
+        let (ra, rb) = (run(a), run(b));
+
+        assert_eq!(ra.await.unwrap(), Some(a));
+        assert_eq!(rb.await.unwrap(), Some(b));
+        assert_eq!(current_task_id(), None);
+    }
+
+    #[test]
+    fn test_await_guard_measures_wait() {
+        let task_id = Inspector::global().register_task("test_await_guard".to_string());
+        set_current_task_id(task_id);
+
+        let guard = inspect_await_start("sleep", Some("src/lib.rs:1".to_string()));
+        assert!(matches!(
+            Inspector::global().get_task(task_id).unwrap().state,
+            TaskState::Blocked { ref await_point } if await_point == "sleep"
+        ));
+
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        inspect_await_end(guard);
+        clear_current_task_id();
+
+        let task = Inspector::global().get_task(task_id).unwrap();
+        assert_eq!(task.state, TaskState::Running);
+
+        let waited = Inspector::global()
+            .get_task_events(task_id)
+            .into_iter()
+            .find_map(|e| match e.kind {
+                EventKind::AwaitEnded { duration, .. } => Some(duration),
+                _ => None,
+            })
+            .unwrap();
+        assert!(waited >= std::time::Duration::from_millis(5));
+    }
+
+    const PROBE_LINE: u32 = line!();
+    #[crate::trace]
+    async fn traced_location_probe() {
+        std::future::ready(()).await;
+    }
+
+    #[tokio::test]
+    async fn test_trace_records_user_locations() {
+        traced_location_probe().await;
+
+        let task = Inspector::global()
+            .get_all_tasks()
+            .into_iter()
+            .find(|t| t.name == "traced_location_probe")
+            .unwrap();
+        let fn_location = format!("{}:{}:", file!(), PROBE_LINE + 2);
+        assert!(task.location.unwrap().starts_with(&fn_location));
+
+        let await_location = Inspector::global()
+            .get_task_events(task.id)
+            .into_iter()
+            .find_map(|e| match e.kind {
+                EventKind::AwaitStarted { location, .. } => location,
+                _ => None,
+            })
+            .unwrap();
+        let expected = format!("{}:{}:", file!(), PROBE_LINE + 3);
+        assert!(await_location.starts_with(&expected), "{await_location}");
+    }
+
+    #[crate::trace]
+    async fn traced_child_probe() -> Option<TaskId> {
+        current_task_id()
+    }
+
+    #[crate::trace]
+    async fn traced_parent_probe() -> (Option<TaskId>, Option<TaskId>) {
+        let child = traced_child_probe().await;
+        (current_task_id(), child)
+    }
+
+    #[tokio::test]
+    async fn test_nested_trace_links_inline_child() {
+        let (parent, child) = traced_parent_probe().await;
+        let (parent, child) = (parent.unwrap(), child.unwrap());
+
+        let child = Inspector::global().get_task(child).unwrap();
+        assert_eq!(child.name, "traced_child_probe");
+        assert_eq!(child.parent, Some(parent));
+        assert_eq!(child.child_kind, Some(ChildKind::Inline));
+        assert_eq!(Inspector::global().get_task(parent).unwrap().parent, None);
+    }
+
+    #[crate::trace(
+        name = "trace_args_login",
+        fields(user = user, attempt = attempt + 1, region = %region),
+        skip(password),
+        skip_awaits,
+        level = "warn"
+    )]
+    async fn traced_args_probe(user: &str, password: &str, attempt: u32, region: &str) -> TaskId {
+        std::future::ready(password.len()).await;
+        current_task_id().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_trace_arguments() {
+        let task_id = traced_args_probe("alice", "hunter2", 1, "eu").await;
+        let task = Inspector::global().get_task(task_id).unwrap();
+
+        assert_eq!(task.name, "trace_args_login");
+        assert_eq!(task.field("user"), Some("\"alice\""));
+        assert_eq!(task.field("attempt"), Some("2"));
+        assert_eq!(task.field("region"), Some("eu"));
+        assert_eq!(task.field("password"), None);
+        assert_eq!(task.level, TaskLevel::Warn);
+
+        let awaited = Inspector::global()
+            .get_task_events(task_id)
+            .into_iter()
+            .any(|e| matches!(e.kind, EventKind::AwaitStarted { .. }));
+        assert!(!awaited);
+    }
+
+    #[crate::trace(sample = 3)]
+    async fn traced_sampled_probe() -> Option<TaskId> {
+        current_task_id()
+    }
+
+    #[tokio::test]
+    async fn test_trace_sampling() {
+        let mut recorded = 0;
+        for _ in 0..9 {
+            let task_id = traced_sampled_probe().await.unwrap();
+            if Inspector::global().is_tracked(task_id) {
+                recorded += 1;
+            }
+        }
+        assert_eq!(recorded, 3);
+    }
+
+    #[crate::trace]
+    async fn traced_outcome_probe(fail: bool) -> Result<TaskId, String> {
+        let task_id = current_task_id().unwrap();
+        if fail {
+            Err(format!("lookup failed for {task_id}"))
+        } else {
+            Ok(task_id)
+        }
+    }
+
+    #[crate::trace]
+    async fn traced_panic_probe(slot: std::sync::Arc<parking_lot::Mutex<Option<TaskId>>>) {
+        *slot.lock() = current_task_id();
+        panic!("boom");
+    }
+
+    #[crate::trace]
+    async fn traced_pending_probe(slot: std::sync::Arc<parking_lot::Mutex<Option<TaskId>>>) {
+        *slot.lock() = current_task_id();
+        std::future::pending::<()>().await;
+    }
+
+    fn last_event(task_id: TaskId) -> EventKind {
+        Inspector::global()
+            .get_task_events(task_id)
+            .pop()
+            .map(|e| e.kind)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_trace_records_result_failures() {
+        let ok = traced_outcome_probe(false).await.unwrap();
+        assert_eq!(
+            Inspector::global().get_task(ok).unwrap().state,
+            TaskState::Completed
+        );
+
+        let message = traced_outcome_probe(true).await.unwrap_err();
+        let task = Inspector::global()
+            .get_all_tasks()
+            .into_iter()
+            .find(|t| message.ends_with(&t.id.to_string()))
+            .unwrap();
+        assert_eq!(
+            task.state,
+            TaskState::Failed {
+                error: Some(message.clone())
+            }
+        );
+        assert!(matches!(
+            last_event(task.id),
+            EventKind::TaskFailed { error: Some(e) } if e == message
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_trace_records_panics_and_resumes() {
+        let slot = std::sync::Arc::new(parking_lot::Mutex::new(None));
+        let handle = tokio::spawn(traced_panic_probe(slot.clone()));
+        assert!(handle.await.unwrap_err().is_panic());
+
+        let task_id = slot.lock().unwrap();
+        assert_eq!(
+            Inspector::global().get_task(task_id).unwrap().state,
+            TaskState::Panicked {
+                message: "boom".to_string()
+            }
+        );
+        assert!(matches!(
+            last_event(task_id),
+            EventKind::TaskPanicked { message } if message == "boom"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_trace_records_cancellation() {
+        let slot = std::sync::Arc::new(parking_lot::Mutex::new(None));
+        let timed_out = tokio::time::timeout(
+            std::time::Duration::from_millis(5),
+            traced_pending_probe(slot.clone()),
+        )
+        .await;
+        assert!(timed_out.is_err());
+
+        let task_id = slot.lock().unwrap();
+        assert_eq!(
+            Inspector::global().get_task(task_id).unwrap().state,
+            TaskState::Cancelled
+        );
+        assert!(matches!(
+            last_event(task_id),
+            EventKind::TaskCancelled { .. }
+        ));
+    }
+
     #[test]
     fn test_task_guard() {
         let guard = TaskGuard::new("test".to_string());
@@ -0,0 +1,118 @@
+//! Waker instrumentation
+//!
+//! The waker handed to a tracked future is replaced by one that records wakes,
+//! clones and drops against the task before forwarding to the runtime's own
+//! waker.
+
+use super::is_task_entered;
+use crate::inspector::Inspector;
+use crate::task::TaskId;
+use std::mem::ManuallyDrop;
+use std::sync::Arc;
+use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+/// Task a waker belongs to and the runtime waker it forwards to
+struct WakerData {
+    task_id: TaskId,
+    inner: Waker,
+}
+
+impl WakerData {
+    fn record_wake(&self, by_ref: bool) {
+        // A task waking itself shows up as the task being entered while the
+        // wake happens, possibly beneath an inline child
+        let self_wake = is_task_entered(self.task_id);
+        Inspector::global().task_woken(self.task_id, by_ref, self_wake);
+        self.inner.wake_by_ref();
+    }
+}
+
+static VTABLE: RawWakerVTable = RawWakerVTable::new(clone_raw, wake_raw, wake_by_ref_raw, drop_raw);
+
+fn raw_waker(data: *const WakerData) -> RawWaker {
+    RawWaker::new(data.cast(), &VTABLE)
+}
+
+unsafe fn clone_raw(ptr: *const ()) -> RawWaker {
+    let data = ptr.cast::<WakerData>();
+    // SAFETY: `ptr` comes from `Arc::as_ptr` and the Arc is alive while any
+    // waker referencing it exists
+    unsafe { Arc::increment_strong_count(data) };
+    Inspector::global().waker_cloned(unsafe { (*data).task_id });
+    raw_waker(data)
+}
+
+unsafe fn wake_raw(ptr: *const ()) {
+    // SAFETY: waking by value consumes a clone, which owns one strong count
+    let data = unsafe { Arc::from_raw(ptr.cast::<WakerData>()) };
+    data.record_wake(false);
+    Inspector::global().waker_dropped(data.task_id);
+}
+
+unsafe fn wake_by_ref_raw(ptr: *const ()) {
+    // SAFETY: the waker being woken keeps the data alive
+    let data = unsafe { &*ptr.cast::<WakerData>() };
+    data.record_wake(true);
+}
+
+unsafe fn drop_raw(ptr: *const ()) {
+    // SAFETY: dropping a clone releases the strong count it owns
+    let data = unsafe { Arc::from_raw(ptr.cast::<WakerData>()) };
+    Inspector::global().waker_dropped(data.task_id);
+}
+
+/// Wraps the waker passed to a task's future to record its activity
+///
+/// Wakes, clones and drops are recorded against the task, and the time from
+/// a wake to the next poll is measured as scheduling latency. The wrapping is
+/// reused for as long as the runtime keeps passing the same waker, so stored
+/// clones still compare equal through [`Waker::will_wake`].
+pub struct WakerTracker {
+    task_id: TaskId,
+    current: Option<Arc<WakerData>>,
+}
+
+impl WakerTracker {
+    /// Create a tracker for a task's waker
+    pub fn new(task_id: TaskId) -> Self {
+        Self {
+            task_id,
+            current: None,
+        }
+    }
+
+    /// Run a poll with the context's waker replaced by a tracked one
+    ///
+    /// The poll outcome is recorded so tasks left pending without a live
+    /// waker can be detected. When waker tracking is disabled the context is
+    /// passed through as is.
+    pub fn poll_with<T>(
+        &mut self,
+        cx: &mut Context<'_>,
+        f: impl FnOnce(&mut Context<'_>) -> Poll<T>,
+    ) -> Poll<T> {
+        let inspector = Inspector::global();
+        if !inspector.is_enabled() || !inspector.config().track_wakers() {
+            return f(cx);
+        }
+
+        inspector.poll_scheduled(self.task_id);
+
+        let data = match &self.current {
+            Some(data) if data.inner.will_wake(cx.waker()) => data,
+            _ => self.current.insert(Arc::new(WakerData {
+                task_id: self.task_id,
+                inner: cx.waker().clone(),
+            })),
+        };
+
+        // Borrows the tracker's strong count; never dropped, so that count
+        // is not released. Clones take counts of their own.
+        // SAFETY: the vtable upholds the `RawWaker` contract for `WakerData`
+        let waker = ManuallyDrop::new(unsafe { Waker::from_raw(raw_waker(Arc::as_ptr(data))) });
+        let result = f(&mut Context::from_waker(&waker));
+
+        inspector.poll_returned(self.task_id, result.is_pending());
+        result
+    }
+}
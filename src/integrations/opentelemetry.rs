@@ -106,8 +106,15 @@ impl OtelExporter {
             TaskState::Completed => {
                 span.set_status(Status::Ok);
             }
-            TaskState::Failed => {
-                span.set_status(Status::error("Task failed"));
+            TaskState::Failed { ref error } => {
+                let error = error.clone().unwrap_or_else(|| "Task failed".to_string());
+                span.set_status(Status::error(error));
+            }
+            TaskState::Panicked { ref message } => {
+                span.set_status(Status::error(format!("Task panicked: {message}")));
+            }
+            TaskState::Cancelled => {
+                span.set_status(Status::error("Task cancelled"));
             }
             _ => {}
         }
@@ -133,10 +140,19 @@ impl OtelExporter {
             EventKind::TaskStarted => "task.started",
             EventKind::PollStarted { .. } => "poll.started",
             EventKind::PollEnded { .. } => "poll.ended",
+            EventKind::LongPoll { .. } => "poll.long",
             EventKind::AwaitStarted { .. } => "await.started",
             EventKind::AwaitEnded { .. } => "await.ended",
             EventKind::TaskCompleted { .. } => "task.completed",
             EventKind::TaskFailed { .. } => "task.failed",
+            EventKind::TaskWoken { .. } => "task.woken",
+            EventKind::WakerCloned => "waker.cloned",
+            EventKind::WakerDropped => "waker.dropped",
+            EventKind::TaskScheduled { .. } => "task.scheduled",
+            EventKind::TaskStalled { .. } => "task.stalled",
+            EventKind::DeadlockDetected { .. } => "deadlock.detected",
+            EventKind::TaskPanicked { .. } => "task.panicked",
+            EventKind::TaskCancelled { .. } => "task.cancelled",
             EventKind::InspectionPoint { .. } => "inspection.point",
             EventKind::StateChanged { .. } => "state.changed",
         };
@@ -159,9 +175,37 @@ impl OtelExporter {
             EventKind::PollEnded { duration } => {
                 vec![KeyValue::new("duration_ms", duration.as_millis() as i64)]
             }
-            EventKind::TaskCompleted { duration } => {
+            EventKind::LongPoll {
+                duration,
+                await_point,
+                location,
+            } => {
+                let mut attrs = vec![KeyValue::new("duration_ms", duration.as_millis() as i64)];
+                if let Some(point) = await_point {
+                    attrs.push(KeyValue::new("await.point", point.clone()));
+                }
+                if let Some(location) = location {
+                    attrs.push(KeyValue::new("location", location.clone()));
+                }
+                attrs
+            }
+            EventKind::TaskCompleted { duration } | EventKind::TaskCancelled { duration } => {
                 vec![KeyValue::new("duration_ms", duration.as_millis() as i64)]
             }
+            EventKind::TaskPanicked { message } => {
+                vec![KeyValue::new("panic.message", message.clone())]
+            }
+            EventKind::TaskStalled {
+                await_point,
+                duration,
+            } => vec![
+                KeyValue::new("await.point", await_point.clone()),
+                KeyValue::new("duration_ms", duration.as_millis() as i64),
+            ],
+            EventKind::DeadlockDetected { tasks, resources } => vec![
+                KeyValue::new("deadlock.tasks", tasks.len() as i64),
+                KeyValue::new("deadlock.resources", resources.len() as i64),
+            ],
             EventKind::TaskFailed { error } => {
                 if let Some(err) = error {
                     vec![KeyValue::new("error", err.clone())]
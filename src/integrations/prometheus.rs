@@ -4,7 +4,6 @@
 //! allowing integration with Prometheus monitoring and Grafana dashboards.
 
 use crate::inspector::Inspector;
-use crate::task::TaskState;
 use prometheus::{
     Counter, CounterVec, Gauge, GaugeVec, Histogram, HistogramOpts, HistogramVec, Opts, Registry,
 };
@@ -162,6 +161,12 @@ impl PrometheusExporter {
         self.tasks_by_state
             .with_label_values(&["failed"])
             .set(stats.failed_tasks as f64);
+        self.tasks_by_state
+            .with_label_values(&["panicked"])
+            .set(stats.panicked_tasks as f64);
+        self.tasks_by_state
+            .with_label_values(&["cancelled"])
+            .set(stats.cancelled_tasks as f64);
         self.tasks_by_state
             .with_label_values(&["blocked"])
             .set(stats.blocked_tasks as f64);
@@ -173,7 +178,7 @@ impl PrometheusExporter {
         // Update task durations and polls
         for task in self.inspector.get_all_tasks() {
             // Update task duration histogram for completed tasks
-            if matches!(task.state, TaskState::Completed | TaskState::Failed) {
+            if task.state.is_terminal() {
                 self.task_duration
                     .with_label_values(&[&task.name])
                     .observe(task.total_run_time.as_secs_f64());
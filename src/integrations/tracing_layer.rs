@@ -122,7 +122,7 @@ where
                     let old_state = task.state.clone();
 
                     // Don't change state if already completed/failed
-                    if !matches!(old_state, TaskState::Completed | TaskState::Failed) {
+                    if !old_state.is_terminal() {
                         task.update_state(TaskState::Pending);
 
                         self.inspector.record_event(Event {
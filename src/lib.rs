@@ -33,6 +33,9 @@
 // Re-export proc macros
 pub use async_inspect_macros::{inspect, trace};
 
+// Lets macro expansions refer to `::async_inspect` inside this crate too
+extern crate self as async_inspect;
+
 /// Production configuration
 pub mod config;
 
@@ -56,9 +59,19 @@ pub mod deadlock;
 /// Performance profiling
 pub mod profile;
 
+/// Background monitor threads
+pub mod background;
+
+/// Stuck-task watchdog
+pub mod watchdog;
+
 /// Runtime integration hooks
 pub mod runtime;
 
+/// Instrumented synchronization primitives
+#[cfg(feature = "tokio")]
+pub mod sync;
+
 /// Instrumentation and tracing
 pub mod instrument;
 
@@ -118,7 +131,7 @@ pub mod prelude {
 
     pub use crate::error::{Error, Result};
     pub use crate::inspector::{Inspector, InspectorStats};
-    pub use crate::instrument::{InspectContext, TaskGuard};
+    pub use crate::instrument::{InspectContext, LeakGuard, TaskGuard};
     pub use crate::reporter::html::HtmlReporter;
     pub use crate::reporter::Reporter;
     pub use crate::task::{TaskId, TaskInfo, TaskState};
@@ -3,10 +3,13 @@
 //! Command-line interface for inspecting and monitoring async Rust applications.
 
 use async_inspect::config::Config;
-use async_inspect::export::{CsvExporter, JsonExporter};
+use async_inspect::export::{ChromeTraceExporter, CsvExporter, ExportData, JsonExporter};
+use async_inspect::graph::TaskGraph;
 use async_inspect::inspector::Inspector;
 use async_inspect::reporter::Reporter;
 use clap::{Parser, Subcommand};
+use std::fs::File;
+use std::io::BufReader;
 use std::path::PathBuf;
 
 #[cfg(feature = "cli")]
@@ -56,6 +59,20 @@ enum Commands {
         with_events: bool,
     },
 
+    /// Render the task graph of a saved trace
+    Graph {
+        /// Trace file saved by the JSON exporter
+        input: PathBuf,
+
+        /// Output format
+        #[arg(short, long, value_enum, default_value = "dot")]
+        format: GraphFormat,
+
+        /// Output file path (defaults to stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
     /// Show current statistics
     Stats {
         /// Show detailed performance metrics
@@ -95,6 +112,22 @@ enum ExportFormat {
     Json,
     /// Export as CSV
     Csv,
+    /// Export as a Chrome Trace Event file, for chrome://tracing or Perfetto
+    ChromeTrace,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum GraphFormat {
+    /// Graphviz DOT
+    Dot,
+    /// Mermaid flowchart
+    Mermaid,
+    /// GraphML
+    Graphml,
+    /// JSON nodes and edges
+    Json,
+    /// Plain text summary
+    Text,
 }
 
 #[derive(Debug, Clone, Copy, clap::ValueEnum)]
@@ -182,6 +215,42 @@ fn main() -> anyhow::Result<()> {
                         println!("✅ Exported events to CSV: {}", events_path.display());
                     }
                 }
+                ExportFormat::ChromeTrace => {
+                    ChromeTraceExporter::export_to_file(inspector, &output)?;
+                    println!("✅ Exported Chrome trace: {}", output.display());
+                    println!("   Open it in https://ui.perfetto.dev or chrome://tracing");
+                }
+            }
+
+            Ok(())
+        }
+
+        Commands::Graph {
+            input,
+            format,
+            output,
+        } => {
+            let data: ExportData = serde_json::from_reader(BufReader::new(File::open(&input)?))?;
+            let graph = TaskGraph::from_export(&data);
+
+            let rendered = match format {
+                GraphFormat::Dot => graph.to_dot(),
+                GraphFormat::Mermaid => graph.to_mermaid(),
+                GraphFormat::Graphml => graph.to_graphml(),
+                GraphFormat::Json => graph.to_json()? + "\n",
+                GraphFormat::Text => graph.to_text(),
+            };
+
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, rendered)?;
+                    println!(
+                        "✅ Rendered graph of {} tasks to {}",
+                        data.tasks.len(),
+                        path.display()
+                    );
+                }
+                None => print!("{rendered}"),
             }
 
             Ok(())
@@ -294,7 +363,12 @@ fn main() -> anyhow::Result<()> {
             println!("  Running tasks:   {}", stats.running_tasks);
             println!("  Completed tasks: {}", stats.completed_tasks);
             println!("  Failed tasks:    {}", stats.failed_tasks);
+            println!("  Panicked tasks:  {}", stats.panicked_tasks);
+            println!("  Cancelled tasks: {}", stats.cancelled_tasks);
+            println!("  Lost wakeups:    {}", stats.lost_wakeups);
             println!("  Total events:    {}", stats.total_events);
+            println!("  Dropped tasks:   {}", stats.dropped_tasks);
+            println!("  Dropped events:  {}", stats.dropped_events);
             println!(
                 "  Duration:        {:.2}s",
                 stats.timeline_duration.as_secs_f64()
@@ -315,7 +389,8 @@ fn main() -> anyhow::Result<()> {
             println!("  • Performance profiling");
             #[cfg(feature = "cli")]
             println!("  • Real-time TUI monitoring");
-            println!("  • JSON/CSV export");
+            println!("  • JSON/CSV/Chrome trace export");
+            println!("  • Task graphs (DOT, Mermaid, GraphML, JSON)");
             println!("  • Production-ready configuration");
 
             println!("\n🔗 Links:");
@@ -333,6 +408,8 @@ fn main() -> anyhow::Result<()> {
             println!("     async-inspect monitor");
             println!("\n  4. Export data:");
             println!("     async-inspect export -f json -o trace.json");
+            println!("\n  5. Render the task graph of a saved trace:");
+            println!("     async-inspect graph trace.json -f mermaid");
 
             Ok(())
         }
@@ -375,5 +452,14 @@ fn print_config(config: &Config) {
     );
     println!("  Track awaits:    {}", config.track_awaits());
     println!("  Track polls:     {}", config.track_polls());
+    println!("  Track wakers:    {}", config.track_wakers());
+    println!(
+        "  Long poll:       {}",
+        if config.long_poll_threshold().is_zero() {
+            "off".to_string()
+        } else {
+            format!("{:?}", config.long_poll_threshold())
+        }
+    );
     println!("  Enable HTML:     {}", config.enable_html());
 }
@@ -43,6 +43,15 @@ pub struct TaskMetrics {
 
     /// Whether the task completed successfully
     pub completed: bool,
+
+    /// Number of polls that ran past the long poll threshold
+    pub long_polls: u64,
+
+    /// Time spent in those long polls, blocking the executor
+    pub blocking_time: Duration,
+
+    /// Longest of those polls
+    pub max_long_poll: Duration,
 }
 
 impl TaskMetrics {
@@ -59,6 +68,9 @@ impl TaskMetrics {
             await_durations: Vec::new(),
             avg_poll_duration: Duration::ZERO,
             completed: false,
+            long_polls: 0,
+            blocking_time: Duration::ZERO,
+            max_long_poll: Duration::ZERO,
         }
     }
 
@@ -293,6 +305,17 @@ impl Profiler {
         metrics.into_iter().take(count).collect()
     }
 
+    /// Find tasks that blocked the executor the longest with long polls
+    pub fn top_blocking_tasks(&self, count: usize) -> Vec<&TaskMetrics> {
+        let mut metrics: Vec<_> = self
+            .task_metrics
+            .values()
+            .filter(|m| m.long_polls > 0)
+            .collect();
+        metrics.sort_by_key(|m| std::cmp::Reverse(m.blocking_time));
+        metrics.into_iter().take(count).collect()
+    }
+
     /// Find least efficient tasks (high blocked time ratio)
     pub fn least_efficient_tasks(&self, count: usize) -> Vec<&TaskMetrics> {
         let mut metrics: Vec<_> = self.task_metrics.values().collect();
@@ -21,6 +21,7 @@ impl<'a> PerformanceReporter<'a> {
         self.print_bottlenecks();
         self.print_hot_paths();
         self.print_slowest_tasks();
+        self.print_blocking_tasks();
         self.print_await_stats();
         self.print_efficiency_analysis();
     }
@@ -170,6 +171,36 @@ impl<'a> PerformanceReporter<'a> {
         println!();
     }
 
+    /// Print tasks whose long polls blocked the executor
+    fn print_blocking_tasks(&self) {
+        let blocking = self.profiler.top_blocking_tasks(10);
+
+        println!("┌────────────────────────────────────────────────────────────┐");
+        println!("│ Top Blocking Tasks                                         │");
+        println!("└────────────────────────────────────────────────────────────┘");
+
+        if blocking.is_empty() {
+            println!("  No long polls recorded\n");
+            return;
+        }
+
+        for (i, metrics) in blocking.iter().enumerate() {
+            println!(
+                "  {}. {} (#{}) - {:.2}ms blocking",
+                i + 1,
+                metrics.name,
+                metrics.task_id.as_u64(),
+                metrics.blocking_time.as_secs_f64() * 1000.0
+            );
+            println!(
+                "     Long polls: {} | Longest: {:.2}ms",
+                metrics.long_polls,
+                metrics.max_long_poll.as_secs_f64() * 1000.0
+            );
+        }
+        println!();
+    }
+
     /// Print await point statistics
     fn print_await_stats(&self) {
         let stats = self.profiler.await_stats();
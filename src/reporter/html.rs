@@ -3,24 +3,59 @@
 //! Generates interactive HTML reports with timeline visualization,
 //! state machine graphs, and task inspection panels.
 
+use crate::deadlock::{Acquisition, DeadlockDetector};
+use crate::graph::{SegmentKind, TaskGraph};
 use crate::inspector::Inspector;
-use crate::task::{TaskInfo, TaskState};
+use crate::task::{ChildKind, TaskInfo, TaskState};
 use std::fmt::Write as FmtWrite;
 
 /// HTML report generator
 pub struct HtmlReporter {
     inspector: Inspector,
+    detector: Option<DeadlockDetector>,
+    graph: Option<TaskGraph>,
 }
 
 impl HtmlReporter {
     /// Create a new HTML reporter
     pub fn new(inspector: Inspector) -> Self {
-        Self { inspector }
+        Self {
+            inspector,
+            detector: None,
+            graph: None,
+        }
     }
 
-    /// Create a reporter using the global inspector
+    /// Create a reporter using the global inspector and deadlock detector
     pub fn global() -> Self {
         Self::new(Inspector::global().clone())
+            .with_deadlock_detector(DeadlockDetector::global().clone())
+    }
+
+    /// Include lock-order analysis from a deadlock detector
+    pub fn with_deadlock_detector(mut self, detector: DeadlockDetector) -> Self {
+        self.detector = Some(detector);
+        self
+    }
+
+    /// Only report the tasks of a graph, such as a [`GraphQuery`] result
+    ///
+    /// [`GraphQuery`]: crate::graph::GraphQuery
+    pub fn with_graph(mut self, graph: TaskGraph) -> Self {
+        self.graph = Some(graph);
+        self
+    }
+
+    /// Tasks to report on
+    fn tasks(&self) -> Vec<TaskInfo> {
+        let tasks = self.inspector.get_all_tasks();
+        match &self.graph {
+            Some(graph) => tasks
+                .into_iter()
+                .filter(|task| graph.get_task(&task.id).is_some())
+                .collect(),
+            None => tasks,
+        }
     }
 
     /// Generate a complete HTML report
@@ -60,6 +95,12 @@ impl HtmlReporter {
         // State machine graph
         html.push_str(&self.generate_state_machine_graph());
 
+        // Critical path of the slowest top-level task
+        html.push_str(&self.generate_critical_path());
+
+        // Lock order inversions
+        html.push_str(&self.generate_lock_order());
+
         // Task list with details
         html.push_str(&self.generate_task_list());
 
@@ -209,14 +250,83 @@ impl HtmlReporter {
             fill: #f44336;
         }
 
+        .task-bar.panicked {
+            fill: #b71c1c;
+        }
+
+        .task-bar.cancelled {
+            fill: #9c27b0;
+        }
+
         .task-bar.pending {
             fill: #9e9e9e;
         }
 
-        .task-list {
+        .task-list, .lock-order, .critical-path {
             padding: 30px;
         }
 
+        .critical-path h2 {
+            margin-bottom: 20px;
+            color: #333;
+        }
+
+        .path-segment {
+            display: grid;
+            grid-template-columns: 90px 260px 1fr 90px;
+            gap: 10px;
+            align-items: center;
+            font-family: monospace;
+            font-size: 0.9em;
+            margin: 4px 0;
+        }
+
+        .path-bar {
+            position: relative;
+            height: 14px;
+            background: #f0f0f0;
+            border-radius: 3px;
+        }
+
+        .path-bar span {
+            position: absolute;
+            top: 0;
+            height: 100%;
+            min-width: 2px;
+            border-radius: 3px;
+            background: #e53935;
+        }
+
+        .path-bar span.awaiting {
+            background: #ffb300;
+        }
+
+        .slack {
+            color: #666;
+            font-family: monospace;
+            font-size: 0.9em;
+            margin: 4px 0;
+        }
+
+        .lock-order h2 {
+            margin-bottom: 20px;
+            color: #333;
+        }
+
+        .inversion {
+            background: #fff3e0;
+            border-radius: 8px;
+            padding: 15px 20px;
+            margin-bottom: 15px;
+            border-left: 4px solid #ff9800;
+        }
+
+        .inversion .order {
+            font-family: monospace;
+            font-size: 0.9em;
+            margin: 4px 0;
+        }
+
         .task-list h2 {
             margin-bottom: 20px;
             color: #333;
@@ -282,6 +392,16 @@ impl HtmlReporter {
             color: white;
         }
 
+        .state-panicked {
+            background: #b71c1c;
+            color: white;
+        }
+
+        .state-cancelled {
+            background: #9c27b0;
+            color: white;
+        }
+
         .state-pending {
             background: #9e9e9e;
             color: white;
@@ -442,6 +562,18 @@ impl HtmlReporter {
             stroke: #d32f2f;
         }
 
+        .state-node.panicked rect,
+        .state-node.panicked circle {
+            fill: #b71c1c;
+            stroke: #7f0000;
+        }
+
+        .state-node.cancelled rect,
+        .state-node.cancelled circle {
+            fill: #9c27b0;
+            stroke: #7b1fa2;
+        }
+
         .state-node text {
             fill: white;
             font-size: 12px;
@@ -513,6 +645,8 @@ impl HtmlReporter {
         self.add_stat_card(&mut html, "Blocked", &stats.blocked_tasks.to_string());
         self.add_stat_card(&mut html, "Completed", &stats.completed_tasks.to_string());
         self.add_stat_card(&mut html, "Failed", &stats.failed_tasks.to_string());
+        self.add_stat_card(&mut html, "Panicked", &stats.panicked_tasks.to_string());
+        self.add_stat_card(&mut html, "Cancelled", &stats.cancelled_tasks.to_string());
         self.add_stat_card(&mut html, "Total Events", &stats.total_events.to_string());
         self.add_stat_card(
             &mut html,
@@ -535,7 +669,7 @@ impl HtmlReporter {
 
     /// Generate interactive timeline visualization
     fn generate_timeline_viz(&self) -> String {
-        let tasks = self.inspector.get_all_tasks();
+        let tasks = self.tasks();
 
         if tasks.is_empty() {
             return String::from(
@@ -700,7 +834,9 @@ impl HtmlReporter {
             TaskState::Completed => "completed",
             TaskState::Running => "running",
             TaskState::Blocked { .. } => "blocked",
-            TaskState::Failed => "failed",
+            TaskState::Failed { .. } => "failed",
+            TaskState::Panicked { .. } => "panicked",
+            TaskState::Cancelled => "cancelled",
             TaskState::Pending => "pending",
         };
 
@@ -787,7 +923,7 @@ impl HtmlReporter {
         use std::collections::{HashMap, HashSet};
 
         let mut svg = String::new();
-        let tasks = self.inspector.get_all_tasks();
+        let tasks = self.tasks();
 
         if tasks.is_empty() {
             writeln!(svg, "<svg width=\"800\" height=\"400\"><text x=\"400\" y=\"200\" text-anchor=\"middle\" fill=\"#666\">No tasks to visualize</text></svg>").unwrap();
@@ -830,12 +966,14 @@ impl HtmlReporter {
         writeln!(svg, "  </defs>").unwrap();
 
         // Build task hierarchy and relationships
-        let mut parent_child: Vec<(crate::task::TaskId, crate::task::TaskId)> = Vec::new();
+        let mut parent_child: Vec<(crate::task::TaskId, crate::task::TaskId, ChildKind)> =
+            Vec::new();
         let mut root_tasks: Vec<&TaskInfo> = Vec::new();
 
         for task in &tasks {
             if let Some(parent_id) = task.parent {
-                parent_child.push((parent_id, task.id));
+                let kind = task.child_kind.unwrap_or(ChildKind::Spawned);
+                parent_child.push((parent_id, task.id, kind));
             } else {
                 root_tasks.push(task);
             }
@@ -860,7 +998,7 @@ impl HtmlReporter {
             let mut next_layer = Vec::new();
 
             for &parent_id in last_layer {
-                for &(pid, cid) in &parent_child {
+                for &(pid, cid, _) in &parent_child {
                     if pid == parent_id && !processed.contains(&cid) {
                         next_layer.push(cid);
                         processed.insert(cid);
@@ -898,19 +1036,24 @@ impl HtmlReporter {
         }
 
         // Draw parent-child relationships
-        for &(parent_id, child_id) in &parent_child {
+        for &(parent_id, child_id, kind) in &parent_child {
             if let (Some(&(x1, y1)), Some(&(x2, y2))) = (
                 task_positions.get(&parent_id),
                 task_positions.get(&child_id),
             ) {
-                writeln!(svg, "  <line class=\"state-transition\" x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"#667eea\" stroke-width=\"2\" marker-end=\"url(#arrowhead-parent)\" stroke-dasharray=\"5,5\" />",
-                    x1, y1 + 35.0, x2, y2 - 35.0).unwrap();
+                // Spawned children are dashed, inline awaited children solid
+                let (dash, label) = match kind {
+                    ChildKind::Spawned => (" stroke-dasharray=\"5,5\"", "spawns"),
+                    ChildKind::Inline => ("", "awaits"),
+                };
+                writeln!(svg, "  <line class=\"state-transition\" x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"#667eea\" stroke-width=\"2\" marker-end=\"url(#arrowhead-parent)\"{} />",
+                    x1, y1 + 35.0, x2, y2 - 35.0, dash).unwrap();
 
                 // Add label
                 let mid_x = (x1 + x2) / 2.0;
                 let mid_y = (y1 + y2) / 2.0;
-                writeln!(svg, "  <text x=\"{}\" y=\"{}\" class=\"transition-label\" fill=\"#667eea\">spawns</text>",
-                    mid_x + 10.0, mid_y).unwrap();
+                writeln!(svg, "  <text x=\"{}\" y=\"{}\" class=\"transition-label\" fill=\"#667eea\">{}</text>",
+                    mid_x + 10.0, mid_y, label).unwrap();
             }
         }
 
@@ -944,7 +1087,9 @@ impl HtmlReporter {
                     TaskState::Running => "running",
                     TaskState::Blocked { .. } => "blocked",
                     TaskState::Completed => "completed",
-                    TaskState::Failed => "failed",
+                    TaskState::Failed { .. } => "failed",
+                    TaskState::Panicked { .. } => "panicked",
+                    TaskState::Cancelled => "cancelled",
                 };
 
                 // Draw rounded rectangle for task
@@ -981,7 +1126,9 @@ impl HtmlReporter {
                     TaskState::Running => "▶ Running",
                     TaskState::Blocked { .. } => "⏳ Blocked",
                     TaskState::Completed => "✓ Done",
-                    TaskState::Failed => "✗ Failed",
+                    TaskState::Failed { .. } => "✗ Failed",
+                    TaskState::Panicked { .. } => "! Panicked",
+                    TaskState::Cancelled => "⊘ Cancelled",
                 };
                 writeln!(svg, "    <text x=\"{}\" y=\"{}\" font-size=\"9\" fill=\"white\" opacity=\"0.9\">{}</text>",
                     x, y + 25.0, state_text).unwrap();
@@ -1021,14 +1168,140 @@ impl HtmlReporter {
         )
         .unwrap();
 
+        writeln!(svg, "  <line x1=\"20\" y1=\"{}\" x2=\"80\" y2=\"{}\" stroke=\"#667eea\" stroke-width=\"2\" marker-end=\"url(#arrowhead-parent)\" />",
+            legend_y + 55.0, legend_y + 55.0).unwrap();
+        writeln!(
+            svg,
+            "  <text x=\"90\" y=\"{}\" font-size=\"12\" fill=\"#666\">Parent awaits child inline</text>",
+            legend_y + 60.0
+        )
+        .unwrap();
+
         writeln!(svg, "</svg>").unwrap();
 
         svg
     }
 
+    /// Generate the critical path section for the slowest top-level task
+    fn generate_critical_path(&self) -> String {
+        let Some(path) = self.inspector.slowest_critical_path() else {
+            return String::new();
+        };
+        if let Some(graph) = &self.graph {
+            if graph.get_task(&path.root).is_none() {
+                return String::new();
+            }
+        }
+        let mut html = String::new();
+        let ms = |duration: std::time::Duration| duration.as_secs_f64() * 1000.0;
+        let percent = |duration| {
+            if path.latency.is_zero() {
+                0.0
+            } else {
+                ms(duration) / ms(path.latency) * 100.0
+            }
+        };
+
+        writeln!(html, "        <div class=\"critical-path\">").unwrap();
+        writeln!(
+            html,
+            "            <h2>Critical Path of {} ({:.2}ms)</h2>",
+            path.root,
+            ms(path.latency)
+        )
+        .unwrap();
+
+        for segment in &path.segments {
+            let (class, activity) = match &segment.kind {
+                SegmentKind::Running => ("running", "running".to_string()),
+                SegmentKind::Awaiting { await_point } => {
+                    ("awaiting", format!("awaiting {await_point}"))
+                }
+            };
+            writeln!(
+                html,
+                "            <div class=\"path-segment\"><span>+{:.2}ms</span><span>{} {}: {}</span><div class=\"path-bar\"><span class=\"{}\" style=\"left: {:.2}%; width: {:.2}%;\"></span></div><span>{:.2}ms</span></div>",
+                ms(segment.start),
+                segment.task_id,
+                segment.task_name,
+                activity,
+                class,
+                percent(segment.start),
+                percent(segment.duration),
+                ms(segment.duration)
+            )
+            .unwrap();
+        }
+
+        for slack in &path.slack {
+            writeln!(
+                html,
+                "            <div class=\"slack\">{} {} finished at +{:.2}ms with {:.2}ms of slack</div>",
+                slack.task_id,
+                slack.task_name,
+                ms(slack.finished_at),
+                ms(slack.slack)
+            )
+            .unwrap();
+        }
+
+        writeln!(html, "        </div>").unwrap();
+
+        html
+    }
+
+    /// Generate the lock-order inversion section, if a detector is attached
+    fn generate_lock_order(&self) -> String {
+        let Some(detector) = &self.detector else {
+            return String::new();
+        };
+        let inversions = detector.lock_order_inversions();
+        let mut html = String::new();
+
+        writeln!(html, "        <div class=\"lock-order\">").unwrap();
+        writeln!(html, "            <h2>Lock Order Inversions</h2>").unwrap();
+
+        if inversions.is_empty() {
+            writeln!(
+                html,
+                "            <p style=\"color: #666;\">No resources were acquired in conflicting orders</p>"
+            )
+            .unwrap();
+        }
+
+        let site = |acquisition: &Acquisition| {
+            acquisition
+                .site
+                .map_or_else(|| "unknown location".to_string(), ToString::to_string)
+        };
+
+        for inversion in &inversions {
+            writeln!(html, "            <div class=\"inversion\">").unwrap();
+            for order in [&inversion.forward, &inversion.reverse] {
+                writeln!(
+                    html,
+                    "                <div class=\"order\">{} took <strong>{}</strong> ({}) at {}, then <strong>{}</strong> ({}) at {}</div>",
+                    order.task,
+                    order.first.name,
+                    order.first.access,
+                    site(&order.first),
+                    order.then.name,
+                    order.then.access,
+                    site(&order.then)
+                )
+                .unwrap();
+            }
+            writeln!(html, "            </div>").unwrap();
+        }
+
+        writeln!(html, "        </div>").unwrap();
+
+        html
+    }
+
     /// Generate task list with details
     fn generate_task_list(&self) -> String {
-        let tasks = self.inspector.get_all_tasks();
+        let tasks = self.tasks();
         let mut html = String::new();
 
         writeln!(html, "        <div class=\"task-list\">").unwrap();
@@ -1051,7 +1324,9 @@ impl HtmlReporter {
             TaskState::Completed => ("completed", "Completed"),
             TaskState::Running => ("running", "Running"),
             TaskState::Blocked { .. } => ("blocked", "Blocked"),
-            TaskState::Failed => ("failed", "Failed"),
+            TaskState::Failed { .. } => ("failed", "Failed"),
+            TaskState::Panicked { .. } => ("panicked", "Panicked"),
+            TaskState::Cancelled => ("cancelled", "Cancelled"),
             TaskState::Pending => ("pending", "Pending"),
         };
 
@@ -1258,4 +1533,67 @@ mod tests {
         // Cleanup
         std::fs::remove_file(temp_file).ok();
     }
+
+    #[test]
+    fn test_lock_order_section() {
+        use crate::deadlock::{ResourceInfo, ResourceKind};
+        use crate::task::TaskId;
+
+        let detector = DeadlockDetector::new();
+        let a =
+            detector.register_resource(ResourceInfo::new(ResourceKind::Mutex, "accounts".into()));
+        let b = detector.register_resource(ResourceInfo::new(ResourceKind::Mutex, "ledger".into()));
+        let (first, second) = (TaskId::new(), TaskId::new());
+        detector.acquire(first, a);
+        detector.acquire(first, b);
+        detector.acquire(second, b);
+        detector.acquire(second, a);
+
+        let html = HtmlReporter::new(Inspector::new())
+            .with_deadlock_detector(detector)
+            .generate_html();
+
+        assert!(html.contains("Lock Order Inversions"));
+        assert!(html.contains("<strong>ledger</strong>"));
+        assert!(html.contains("html.rs"));
+    }
+
+    #[test]
+    fn test_critical_path_section() {
+        let inspector = Inspector::new();
+        let root = inspector.register_task("root".to_string());
+        let child = inspector.register_task_with_info(
+            TaskInfo::new("slow_child".to_string()).with_parent_kind(root, ChildKind::Inline),
+        );
+        inspector.await_started(child, "fetch".to_string(), None);
+        std::thread::sleep(std::time::Duration::from_millis(1));
+        inspector.await_ended(
+            child,
+            "fetch".to_string(),
+            std::time::Duration::from_millis(1),
+        );
+        inspector.task_completed(child);
+        inspector.task_completed(root);
+
+        let html = HtmlReporter::new(inspector).generate_html();
+        assert!(html.contains(&format!("Critical Path of {root}")));
+        assert!(html.contains("slow_child: awaiting fetch"));
+    }
+
+    #[test]
+    fn test_report_limited_to_graph() {
+        let inspector = Inspector::new();
+        let request = inspector.register_task("request".to_string());
+        inspector
+            .register_task_with_info(TaskInfo::new("db_call".to_string()).with_parent(request));
+        inspector.register_task("background_job".to_string());
+
+        let subtree = inspector.build_graph().subtree(request);
+        let html = HtmlReporter::new(inspector)
+            .with_graph(subtree)
+            .generate_html();
+
+        assert!(html.contains("db_call"));
+        assert!(!html.contains("background_job"));
+    }
 }
@@ -50,6 +50,23 @@ impl Reporter {
             }
         }
 
+        let lost_wakeups = self.inspector.lost_wakeups();
+        if !lost_wakeups.is_empty() {
+            println!("├─────────────────────────────────────────────────────────────┤");
+            println!("│ Lost Wakeups                                                │");
+            println!("├─────────────────────────────────────────────────────────────┤");
+            for lost in &lost_wakeups {
+                let entry = format!("{} 🔕 {}", lost.task_id, lost.name);
+                println!("│ {entry:<59} │");
+                let detail = format!(
+                    "    └─> lost wakeup at {} ({:.2}s)",
+                    lost.await_point.as_deref().unwrap_or("unknown await point"),
+                    lost.stuck_for.as_secs_f64()
+                );
+                println!("│ {detail:<59} │");
+            }
+        }
+
         println!("└─────────────────────────────────────────────────────────────┘");
     }
 
@@ -73,6 +90,32 @@ impl Reporter {
             "│ Failed:          {:>3}                                      │",
             stats.failed_tasks
         );
+        println!(
+            "│ Panicked:        {:>3}                                      │",
+            stats.panicked_tasks
+        );
+        println!(
+            "│ Cancelled:       {:>3}                                      │",
+            stats.cancelled_tasks
+        );
+        if stats.lost_wakeups > 0 {
+            println!(
+                "│ Lost Wakeups:    {:>3}                                      │",
+                stats.lost_wakeups
+            );
+        }
+        if stats.long_polls > 0 {
+            println!(
+                "│ Long Polls:      {:>3}                                      │",
+                stats.long_polls
+            );
+        }
+        if stats.active_deadlocks > 0 {
+            println!(
+                "│ Deadlocks:       {:>3}                                      │",
+                stats.active_deadlocks
+            );
+        }
         println!(
             "│ Total Events:    {:>3}                                      │",
             stats.total_events
@@ -90,7 +133,9 @@ impl Reporter {
             TaskState::Running => "🏃",
             TaskState::Blocked { .. } => "⏳",
             TaskState::Completed => "✅",
-            TaskState::Failed => "❌",
+            TaskState::Failed { .. } => "❌",
+            TaskState::Panicked { .. } => "💥",
+            TaskState::Cancelled => "🚫",
         };
 
         let status = format!("{} {} {}", task.id, state_icon, task.name);
@@ -136,13 +181,41 @@ impl Reporter {
         );
 
         if let Some(parent) = task.parent {
-            println!("│ Parent:          {:<44}│", parent.to_string());
+            let parent = match task.child_kind {
+                Some(kind) => format!("{parent} ({kind})"),
+                None => parent.to_string(),
+            };
+            println!("│ Parent:          {parent:<44}│");
         }
 
         if let Some(location) = &task.location {
             println!("│ Location:        {:<44}│", location);
         }
 
+        for (key, value) in &task.fields {
+            let field = format!("{key} = {value}");
+            println!("│ Field:           {field:<44}│");
+        }
+
+        let stats = &task.wakers;
+        if stats.total_wakes() > 0 || stats.clones > 0 {
+            let wakes = format!(
+                "{} ({} by ref, {} self)",
+                stats.total_wakes(),
+                stats.wakes_by_ref,
+                stats.self_wakes
+            );
+            println!("│ Wakes:           {wakes:<44}│");
+            let clones = format!("{} ({} live)", stats.clones, stats.live_clones());
+            println!("│ Waker Clones:    {clones:<44}│");
+            let latency = format!(
+                "{:.2}ms avg, {:.2}ms max",
+                stats.mean_schedule_latency().as_secs_f64() * 1000.0,
+                stats.max_schedule_latency.as_secs_f64() * 1000.0
+            );
+            println!("│ Wake Latency:    {latency:<44}│");
+        }
+
         println!("│                                                             │");
         println!("├─────────────────────────────────────────────────────────────┤");
         println!("│ Events                                                      │");
@@ -226,7 +299,14 @@ impl Reporter {
         writeln!(report, "  Blocked:         {}", stats.blocked_tasks).unwrap();
         writeln!(report, "  Completed:       {}", stats.completed_tasks).unwrap();
         writeln!(report, "  Failed:          {}", stats.failed_tasks).unwrap();
+        writeln!(report, "  Panicked:        {}", stats.panicked_tasks).unwrap();
+        writeln!(report, "  Cancelled:       {}", stats.cancelled_tasks).unwrap();
+        writeln!(report, "  Long Polls:      {}", stats.long_polls).unwrap();
         writeln!(report, "  Total Events:    {}", stats.total_events).unwrap();
+        if stats.dropped_tasks > 0 || stats.dropped_events > 0 {
+            writeln!(report, "  Dropped Tasks:   {}", stats.dropped_tasks).unwrap();
+            writeln!(report, "  Dropped Events:  {}", stats.dropped_events).unwrap();
+        }
         writeln!(
             report,
             "  Duration:        {:.2}s",
@@ -240,6 +320,20 @@ impl Reporter {
             writeln!(report, "  {}", task).unwrap();
         }
 
+        let lost_wakeups = self.inspector.lost_wakeups();
+        if !lost_wakeups.is_empty() {
+            writeln!(report).unwrap();
+            writeln!(report, "Lost Wakeups:").unwrap();
+            for lost in &lost_wakeups {
+                writeln!(report, "  lost wakeup: {lost}").unwrap();
+            }
+        }
+
+        if let Some(path) = self.inspector.slowest_critical_path() {
+            writeln!(report).unwrap();
+            report.push_str(&path.describe());
+        }
+
         report
     }
 
@@ -247,11 +341,13 @@ impl Reporter {
     pub fn print_compact_summary(&self) {
         let stats = self.inspector.stats();
         println!(
-            "async-inspect: {} tasks ({} active, {} completed, {} failed) | {} events | {:.2}s",
+            "async-inspect: {} tasks ({} active, {} completed, {} failed, {} panicked, {} cancelled) | {} events | {:.2}s",
             stats.total_tasks,
             stats.running_tasks + stats.blocked_tasks,
             stats.completed_tasks,
             stats.failed_tasks,
+            stats.panicked_tasks,
+            stats.cancelled_tasks,
             stats.total_events,
             stats.timeline_duration.as_secs_f64()
         );
@@ -392,7 +488,8 @@ impl Reporter {
                     TaskState::Running => '█',
                     TaskState::Blocked { .. } => '░',
                     TaskState::Completed => '█',
-                    TaskState::Failed => '▓',
+                    TaskState::Failed { .. } | TaskState::Panicked { .. } => '▓',
+                    TaskState::Cancelled => '▒',
                     TaskState::Pending => '─',
                 };
                 line.push(ch);
@@ -404,7 +501,9 @@ impl Reporter {
         // Add state indicator
         let indicator = match task.state {
             TaskState::Completed => " ✓",
-            TaskState::Failed => " ✗",
+            TaskState::Failed { .. } => " ✗",
+            TaskState::Panicked { .. } => " !",
+            TaskState::Cancelled => " ⊘",
             TaskState::Running => " →",
             TaskState::Blocked { .. } => " ⏸",
             TaskState::Pending => " ○",
@@ -423,6 +522,7 @@ impl Reporter {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::task::ChildKind;
 
     #[test]
     fn test_reporter_creation() {
@@ -443,4 +543,19 @@ mod tests {
         assert!(report.contains("async-inspect Report"));
         assert!(report.contains("Total Tasks:     1"));
     }
+
+    #[test]
+    fn test_report_includes_critical_path() {
+        let inspector = Inspector::new();
+        let root = inspector.register_task("root".to_string());
+        let child = inspector.register_task_with_info(
+            TaskInfo::new("child".to_string()).with_parent_kind(root, ChildKind::Inline),
+        );
+        inspector.task_completed(child);
+        inspector.task_completed(root);
+
+        let report = Reporter::new(inspector).generate_report();
+        assert!(report.contains(&format!("Critical path of task {root}")));
+        assert!(report.contains(&format!("{child} child running")));
+    }
 }
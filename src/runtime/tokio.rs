@@ -2,18 +2,58 @@
 //!
 //! This module provides automatic tracking for Tokio tasks.
 
+use crate::graph::{join_point, Relationship, RelationshipType};
 use crate::inspector::Inspector;
-use crate::instrument::{clear_current_task_id, set_current_task_id};
-use crate::task::TaskId;
+use crate::instrument::{
+    current_task_id, register_child_of_current, PollGuard, Traced, WakerTracker,
+};
+use crate::task::{ChildKind, TaskId, TaskInfo};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::collections::{HashMap, HashSet};
 use std::future::Future;
+use std::ops::Deref;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 use std::time::Instant;
 
+/// Tasks spawned with [`spawn_tracked`] that are still running, by Tokio
+/// task ID, so [`JoinHandleExt::tracked`] can find their task IDs
+static SPAWNED: Lazy<Mutex<Spawned>> = Lazy::new(Default::default);
+
+#[derive(Default)]
+struct Spawned {
+    running: HashMap<tokio::task::Id, TaskId>,
+    /// Tasks that finished before their spawner registered them
+    finished_early: HashSet<tokio::task::Id>,
+}
+
+fn register_spawned(id: tokio::task::Id, task_id: TaskId) {
+    let mut spawned = SPAWNED.lock();
+    if !spawned.finished_early.remove(&id) {
+        spawned.running.insert(id, task_id);
+    }
+}
+
+/// Forget the current Tokio task, called when its future is dropped
+fn unregister_spawned() {
+    let Some(id) = tokio::task::try_id() else {
+        return;
+    };
+
+    let mut spawned = SPAWNED.lock();
+    if spawned.running.remove(&id).is_none() {
+        spawned.finished_early.insert(id);
+    }
+}
+
 /// Spawn a task with automatic tracking
 ///
 /// This is a drop-in replacement for `tokio::spawn()` that automatically
-/// tracks the spawned task.
+/// tracks the spawned task, including its polls and wakes. A panic marks the
+/// task as panicked, and aborting it through the `JoinHandle` marks it as
+/// cancelled. Wrap the returned handle with [`JoinHandleExt::tracked`] to
+/// record the tracked task awaiting it as awaiting this one.
 ///
 /// # Examples
 ///
@@ -31,57 +71,186 @@ where
     F::Output: Send + 'static,
     T: Into<String>,
 {
-    use crate::instrument::current_task_id;
+    let task_id = register_child_of_current(TaskInfo::new(name.into()), ChildKind::Spawned);
 
-    let task_name = name.into();
+    // Wrapped before spawning, so an abort before the first poll still
+    // drops the wrapper and records the cancellation
+    let handle = tokio::spawn(TrackedFuture::spawned(task_id, future));
+    register_spawned(handle.id(), task_id);
+    handle
+}
 
-    // Check if there's a parent task
-    let task_id = if let Some(parent_id) = current_task_id() {
-        Inspector::global().register_child_task(task_name, parent_id)
-    } else {
-        Inspector::global().register_task(task_name)
-    };
+/// Extension trait for Tokio join handles to record awaits on tracked tasks
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use async_inspect::runtime::tokio::{spawn_tracked, JoinHandleExt};
+///
+/// let handle = spawn_tracked("fetch", fetch_data()).tracked();
+/// let data = handle.await?;
+/// ```
+pub trait JoinHandleExt<T> {
+    /// Wrap this handle so awaiting it from a tracked task is recorded
+    ///
+    /// Call it right after [`spawn_tracked`]: a handle to a task that is not
+    /// tracked, or has already finished, is wrapped without a task ID and
+    /// records nothing.
+    fn tracked(self) -> TrackedJoinHandle<T>;
+}
 
-    tokio::spawn(async move {
-        // Set task context for this task
-        set_current_task_id(task_id);
+impl<T> JoinHandleExt<T> for tokio::task::JoinHandle<T> {
+    fn tracked(self) -> TrackedJoinHandle<T> {
+        let task_id = SPAWNED.lock().running.get(&self.id()).copied();
+        TrackedJoinHandle::new(self, task_id)
+    }
+}
 
-        // Wrap execution to track completion
-        let result = future.await;
+/// Handle to a tracked task, created by [`JoinHandleExt::tracked`]
+///
+/// Dereferences to the underlying [`tokio::task::JoinHandle`]. The first
+/// time it is polled from a tracked task, that task is recorded as awaiting
+/// the spawned one, and while it waits for the spawned task to finish it is
+/// blocked at a `join` await point.
+pub struct TrackedJoinHandle<T> {
+    inner: tokio::task::JoinHandle<T>,
+    task_id: Option<TaskId>,
+    awaited: bool,
+    join: Option<JoinWait>,
+}
 
-        // Mark as completed
-        Inspector::global().task_completed(task_id);
+/// A tracked task waiting on a join handle, until dropped
+struct JoinWait {
+    waiter: TaskId,
+    await_point: String,
+    since: Instant,
+}
 
-        // Clear context
-        clear_current_task_id();
+impl JoinWait {
+    fn start(waiter: TaskId, task_id: TaskId) -> Option<Self> {
+        let task = Inspector::global().get_task(task_id)?;
+        let await_point = join_point(&task);
+        Inspector::global().await_started(waiter, await_point.clone(), None);
+        Some(Self {
+            waiter,
+            await_point,
+            since: Instant::now(),
+        })
+    }
+}
+
+impl Drop for JoinWait {
+    fn drop(&mut self) {
+        Inspector::global().await_ended(
+            self.waiter,
+            std::mem::take(&mut self.await_point),
+            self.since.elapsed(),
+        );
+    }
+}
+
+impl<T> TrackedJoinHandle<T> {
+    fn new(inner: tokio::task::JoinHandle<T>, task_id: Option<TaskId>) -> Self {
+        Self {
+            inner,
+            task_id,
+            awaited: false,
+            join: None,
+        }
+    }
+
+    /// Get the task ID of the spawned task, if it is tracked
+    pub fn task_id(&self) -> Option<TaskId> {
+        self.task_id
+    }
+
+    /// Get the underlying Tokio handle, no longer recording awaits
+    pub fn into_inner(self) -> tokio::task::JoinHandle<T> {
+        self.inner
+    }
+}
+
+impl<T> Deref for TrackedJoinHandle<T> {
+    type Target = tokio::task::JoinHandle<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<T> Future for TrackedJoinHandle<T> {
+    type Output = Result<T, tokio::task::JoinError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let waiter = current_task_id().zip(self.task_id);
+        if !self.awaited {
+            self.awaited = true;
+            if let Some((waiter, task_id)) = waiter {
+                Inspector::global().add_relationship(Relationship::new(
+                    waiter,
+                    task_id,
+                    RelationshipType::AwaitsOn,
+                ));
+            }
+        }
 
+        let result = Pin::new(&mut self.inner).poll(cx);
+        if result.is_ready() {
+            self.join = None;
+        } else if self.join.is_none() {
+            self.join = waiter.and_then(|(waiter, task_id)| JoinWait::start(waiter, task_id));
+        }
         result
-    })
+    }
+}
+
+impl<T> std::fmt::Debug for TrackedJoinHandle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TrackedJoinHandle")
+            .field("task_id", &self.task_id)
+            .finish_non_exhaustive()
+    }
 }
 
 /// A future wrapper that automatically tracks execution
 ///
-/// This wrapper tracks polls, completion, and can be used with any future.
+/// This wrapper tracks polls, wakes, completion, panics and cancellation
+/// (dropping it before it completes), and can be used with any future.
 pub struct TrackedFuture<F> {
-    future: F,
+    future: Traced<F>,
     task_id: TaskId,
-    started: bool,
-    poll_start: Option<Instant>,
+    waker: WakerTracker,
+    /// Whether this is the root future of a task spawned on Tokio
+    spawned: bool,
 }
 
 impl<F> TrackedFuture<F> {
     /// Create a new tracked future
+    ///
+    /// Created inside another task, it is registered as a child awaited
+    /// inline by that task.
     pub fn new(future: F, name: String) -> Self {
-        let task_id = Inspector::global().register_task(name);
+        let task_id = register_child_of_current(TaskInfo::new(name), ChildKind::Inline);
+        Self::with_task_id(task_id, future)
+    }
 
+    /// Track a future as an already registered task
+    fn with_task_id(task_id: TaskId, future: F) -> Self {
         Self {
-            future,
+            future: Traced::new(task_id, future),
             task_id,
-            started: false,
-            poll_start: None,
+            waker: WakerTracker::new(task_id),
+            spawned: false,
         }
     }
 
+    /// Track the root future of a task about to be spawned on Tokio
+    fn spawned(task_id: TaskId, future: F) -> Self {
+        let mut tracked = Self::with_task_id(task_id, future);
+        tracked.spawned = true;
+        tracked
+    }
+
     /// Get the task ID
     pub fn task_id(&self) -> TaskId {
         self.task_id
@@ -95,38 +264,27 @@ impl<F: Future> Future for TrackedFuture<F> {
         // SAFETY: We don't move the future
         let this = unsafe { self.get_unchecked_mut() };
 
-        // Set task context
-        set_current_task_id(this.task_id);
+        // Record the poll, ending it even if the inner future panics
+        let _poll = PollGuard::new(this.task_id);
 
-        // Record poll start
-        if !this.started {
-            this.started = true;
-        }
+        // Poll the inner future in the task's context, with a tracked waker
+        // SAFETY: We're pinning the projection
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
+        let result = this.waker.poll_with(cx, |cx| future.poll(cx));
 
-        let poll_start = Instant::now();
-        this.poll_start = Some(poll_start);
+        if result.is_ready() {
+            Inspector::global().task_completed(this.task_id);
+        }
 
-        Inspector::global().poll_started(this.task_id);
+        result
+    }
+}
 
-        // Poll the inner future
-        // SAFETY: We're pinning the projection
-        let result = unsafe { Pin::new_unchecked(&mut this.future).poll(cx) };
-
-        // Record poll end
-        let poll_duration = poll_start.elapsed();
-        Inspector::global().poll_ended(this.task_id, poll_duration);
-
-        match result {
-            Poll::Ready(output) => {
-                // Task completed
-                Inspector::global().task_completed(this.task_id);
-                clear_current_task_id();
-                Poll::Ready(output)
-            }
-            Poll::Pending => {
-                // Still pending
-                Poll::Pending
-            }
+impl<F> Drop for TrackedFuture<F> {
+    fn drop(&mut self) {
+        // Tokio drops a task's future inside that task's context
+        if self.spawned {
+            unregister_spawned();
         }
     }
 }
@@ -183,24 +341,18 @@ where
     F::Output: 'static,
     T: Into<String>,
 {
-    let task_name = name.into();
-    let task_id = Inspector::global().register_task(task_name);
-
-    tokio::task::spawn_local(async move {
-        set_current_task_id(task_id);
-
-        let result = future.await;
-
-        Inspector::global().task_completed(task_id);
-        clear_current_task_id();
-
-        result
-    })
+    let task_id = register_child_of_current(TaskInfo::new(name.into()), ChildKind::Spawned);
+    let handle = tokio::task::spawn_local(TrackedFuture::spawned(task_id, future));
+    register_spawned(handle.id(), task_id);
+    handle
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::instrument::LeakGuard;
+    use crate::task::TaskState;
+    use crate::timeline::EventKind;
 
     #[tokio::test]
     async fn test_spawn_tracked() {
@@ -247,6 +399,244 @@ mod tests {
         assert!(task.poll_count > 0);
     }
 
+    #[tokio::test]
+    async fn test_child_kinds() {
+        let parent = TrackedFuture::new(
+            async {
+                let inline = TrackedFuture::new(async {}, "test_child_kind_inline".to_string());
+                let inline_id = inline.task_id();
+                inline.await;
+
+                let spawned = spawn_tracked("test_child_kind_spawned", async {});
+                spawned.await.unwrap();
+                inline_id
+            },
+            "test_child_kind_parent".to_string(),
+        );
+        let parent_id = parent.task_id();
+        let inline_id = parent.await;
+
+        let inline = Inspector::global().get_task(inline_id).unwrap();
+        assert_eq!(inline.parent, Some(parent_id));
+        assert_eq!(inline.child_kind, Some(ChildKind::Inline));
+
+        let spawned = Inspector::global()
+            .get_all_tasks()
+            .into_iter()
+            .find(|t| t.name == "test_child_kind_spawned")
+            .unwrap();
+        assert_eq!(spawned.parent, Some(parent_id));
+        assert_eq!(spawned.child_kind, Some(ChildKind::Spawned));
+    }
+
+    #[tokio::test]
+    async fn test_awaited_handles_are_related() {
+        let parent = TrackedFuture::new(
+            async {
+                let awaited = spawn_tracked("test_handle_awaited", async {}).tracked();
+                let detached = spawn_tracked("test_handle_detached", async {}).tracked();
+                let ids = (awaited.task_id().unwrap(), detached.task_id().unwrap());
+                awaited.await.unwrap();
+                ids
+            },
+            "test_handle_parent".to_string(),
+        );
+        let parent_id = parent.task_id();
+        let (awaited, detached) = parent.await;
+
+        let related = Inspector::global()
+            .build_graph()
+            .get_related_tasks(parent_id);
+        assert!(related.contains(&(awaited, RelationshipType::Spawned)));
+        assert!(related.contains(&(awaited, RelationshipType::AwaitsOn)));
+        assert!(related.contains(&(detached, RelationshipType::Spawned)));
+        assert!(!related.contains(&(detached, RelationshipType::AwaitsOn)));
+    }
+
+    #[tokio::test]
+    async fn test_joined_tasks_are_on_the_critical_path() {
+        let parent = TrackedFuture::new(
+            async {
+                let slow = spawn_tracked("test_join_slow", async {
+                    tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+                })
+                .tracked();
+                let slow_id = slow.task_id().unwrap();
+                slow.await.unwrap();
+                slow_id
+            },
+            "test_join_parent".to_string(),
+        );
+        let parent_id = parent.task_id();
+        let slow_id = parent.await;
+
+        let joined = Inspector::global()
+            .get_task_events(parent_id)
+            .into_iter()
+            .filter(|e| {
+                matches!(&e.kind, EventKind::AwaitStarted { await_point, .. }
+                    if await_point.starts_with("join test_join_slow"))
+            })
+            .count();
+        assert_eq!(joined, 1);
+
+        let path = Inspector::global().critical_path(parent_id).unwrap();
+        assert_eq!(path.tasks(), vec![parent_id, slow_id]);
+        assert!(path.time_in(slow_id) >= std::time::Duration::from_millis(20));
+    }
+
+    #[tokio::test]
+    async fn test_terminal_states() {
+        let dropped = TrackedFuture::new(
+            std::future::pending::<()>(),
+            "test_terminal_dropped".to_string(),
+        );
+        let dropped_id = dropped.task_id();
+        drop(dropped);
+        assert_eq!(
+            Inspector::global().get_task(dropped_id).unwrap().state,
+            TaskState::Cancelled
+        );
+
+        let aborted = spawn_tracked("test_terminal_aborted", std::future::pending::<()>());
+        aborted.abort();
+        assert!(aborted.await.unwrap_err().is_cancelled());
+
+        let panicked = spawn_tracked("test_terminal_panicked", async { panic!("oops") });
+        assert!(panicked.await.unwrap_err().is_panic());
+
+        let state = |name: &str| {
+            Inspector::global()
+                .get_all_tasks()
+                .into_iter()
+                .find(|t| t.name == name)
+                .unwrap()
+                .state
+        };
+        assert_eq!(state("test_terminal_aborted"), TaskState::Cancelled);
+        assert_eq!(
+            state("test_terminal_panicked"),
+            TaskState::Panicked {
+                message: "oops".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_waker_activity() {
+        // Wakes itself by reference, then waits on a clone woken by value
+        // from another thread
+        let mut step = 0;
+        let future = std::future::poll_fn(move |cx| {
+            step += 1;
+            match step {
+                1 => cx.waker().wake_by_ref(),
+                2 => {
+                    let waker = cx.waker().clone();
+                    std::thread::spawn(move || {
+                        std::thread::sleep(std::time::Duration::from_millis(5));
+                        waker.wake();
+                    });
+                }
+                _ => return Poll::Ready(()),
+            }
+            Poll::Pending
+        });
+
+        let tracked = TrackedFuture::new(future, "test_waker_activity".to_string());
+        let task_id = tracked.task_id();
+        tracked.await;
+
+        let wakers = Inspector::global().get_task(task_id).unwrap().wakers;
+        assert_eq!(wakers.wakes_by_ref, 1);
+        assert_eq!(wakers.self_wakes, 1);
+        assert_eq!(wakers.wakes, 1);
+        assert_eq!(wakers.clones, 1);
+        assert_eq!(wakers.live_clones(), 0);
+        assert_eq!(wakers.scheduled_polls, 2);
+        assert!(wakers.woken_at.is_none());
+
+        let scheduled = Inspector::global().with_timeline(|timeline| {
+            timeline
+                .events_for_task(task_id)
+                .into_iter()
+                .filter(|e| matches!(e.kind, EventKind::TaskScheduled { .. }))
+                .count()
+        });
+        assert_eq!(scheduled, 2);
+    }
+
+    #[crate::trace]
+    async fn test_lost_wakeup_stuck() {
+        // Registers interest by cloning the waker, then forgets it
+        std::future::poll_fn(|cx| {
+            drop(cx.waker().clone());
+            Poll::<()>::Pending
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_lost_wakeup() {
+        let stuck = TrackedFuture::new(test_lost_wakeup_stuck(), "test_lost_wakeup".to_string());
+        let stuck_id = stuck.task_id();
+        let mut stuck = std::pin::pin!(stuck);
+        assert!(futures::poll!(stuck.as_mut()).is_pending());
+
+        // A timer holds on to its waker, so it can still be woken
+        let sleeping = TrackedFuture::new(
+            tokio::time::sleep(std::time::Duration::from_secs(3600)),
+            "test_lost_wakeup_sleeping".to_string(),
+        );
+        let sleeping_id = sleeping.task_id();
+        let mut sleeping = std::pin::pin!(sleeping);
+        assert!(futures::poll!(sleeping.as_mut()).is_pending());
+
+        let lost = Inspector::global().lost_wakeups();
+        let flagged = lost.iter().find(|l| l.task_id == stuck_id).unwrap();
+        assert_eq!(
+            flagged.await_point.as_deref(),
+            Some("test_lost_wakeup_stuck::await#1")
+        );
+        assert!(flagged.location.as_ref().unwrap().contains("tokio.rs"));
+        assert!(!lost.iter().any(|l| l.task_id == sleeping_id));
+    }
+
+    #[tokio::test]
+    async fn test_leak_guard() {
+        LeakGuard::scope("test_leak_guard_clean", async {
+            spawn_tracked("test_leak_guard_done", async {})
+                .await
+                .unwrap();
+        })
+        .await;
+
+        let leaky = LeakGuard::new("test_leak_guard_leaky");
+        let mut forgotten = None;
+        leaky
+            .enter(async {
+                forgotten = Some(spawn_tracked(
+                    "test_leak_guard_pending",
+                    std::future::pending::<()>(),
+                ));
+                tokio::task::yield_now().await;
+            })
+            .await;
+
+        // Tasks created outside the guard are not its concern
+        let _outside = spawn_tracked("test_leak_guard_outside", std::future::pending::<()>());
+
+        let report = leaky.check();
+        assert_eq!(report.total(), 1);
+        assert_eq!(report.groups[0].name, "test_leak_guard_pending");
+
+        let panic =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| drop(leaky))).unwrap_err();
+        let message = crate::instrument::panic_message(&*panic);
+        assert!(message.contains("1 leaked task(s)"));
+        assert!(message.contains("test_leak_guard_pending"));
+    }
+
     #[tokio::test]
     async fn test_spawn_tracked_multiple() {
         let handles: Vec<_> = (0..5)
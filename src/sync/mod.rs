@@ -0,0 +1,321 @@
+//! Instrumented synchronization primitives
+//!
+//! Drop-in wrappers around Tokio's `Mutex`, `RwLock`, `Semaphore` and bounded
+//! [`mpsc`] channel that report to a [`DeadlockDetector`]. Each wrapper registers itself as a
+//! resource when created, records the current task waiting for and acquiring
+//! it, and records the release when the guard or permit is dropped, so
+//! [`DeadlockDetector::detect_deadlocks`] sees real wait-for cycles without
+//! any manual bookkeeping.
+//!
+//! Waits and holds are attributed to the task current when the operation
+//! starts (see [`current_task_id`]); operations outside a tracked task are
+//! not recorded. A task that has to wait for tasks holding a primitive is
+//! also recorded in the global [`Inspector`] as sharing that resource with
+//! them, for [`Inspector::build_graph`].
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use async_inspect::deadlock::DeadlockDetector;
+//! use async_inspect::sync::Mutex;
+//!
+//! let accounts = Mutex::named("accounts", HashMap::new());
+//! accounts.lock().await.insert(id, balance);
+//!
+//! for cycle in DeadlockDetector::global().detect_deadlocks() {
+//!     eprintln!("{}", cycle.describe());
+//! }
+//! ```
+
+pub mod mpsc;
+mod mutex;
+mod rwlock;
+mod semaphore;
+
+pub use mutex::{Mutex, MutexGuard};
+pub use rwlock::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+pub use semaphore::{Semaphore, SemaphorePermit};
+
+use crate::deadlock::{Access, DeadlockDetector, ResourceId, ResourceInfo, ResourceKind};
+use crate::graph::{Relationship, RelationshipType};
+use crate::inspector::Inspector;
+use crate::instrument::current_task_id;
+use crate::task::TaskId;
+use std::panic::Location;
+
+/// Name given to a primitive created without one: where it was created
+fn caller_name(kind: &ResourceKind, location: &Location<'_>) -> String {
+    format!(
+        "{kind} at {}:{}:{}",
+        location.file(),
+        location.line(),
+        location.column()
+    )
+}
+
+/// A primitive's registration with a deadlock detector
+///
+/// The resource is unregistered when the primitive is dropped.
+struct Registration {
+    detector: DeadlockDetector,
+    resource: ResourceId,
+}
+
+impl Registration {
+    fn new(detector: DeadlockDetector, info: ResourceInfo) -> Self {
+        let resource = detector.register_resource(info);
+        Self { detector, resource }
+    }
+
+    /// Record the current task starting to wait, if there is one
+    fn wait(&self, access: Access, site: &'static Location<'static>) -> Option<Wait<'_>> {
+        let task = current_task_id()?;
+        self.detector.wait_for_access(task, self.resource, access);
+        self.record_contention(task, access);
+        Some(Wait {
+            registration: self,
+            task,
+            access,
+            site,
+            acquired: false,
+        })
+    }
+
+    /// Record a waiting task sharing the primitive with the tasks it waits on
+    fn record_contention(&self, task: TaskId, access: Access) {
+        let Some(info) = self.detector.get_resource(self.resource) else {
+            return;
+        };
+
+        for holder in info.blockers(access) {
+            if holder != task {
+                Inspector::global().add_relationship(
+                    Relationship::new(task, holder, RelationshipType::SharedResource)
+                        .with_resource(info.name.clone()),
+                );
+            }
+        }
+    }
+
+    /// Record the current task acquiring without waiting, if there is one
+    fn hold(&self, access: Access, site: &'static Location<'static>) -> Option<Hold<'_>> {
+        let task = current_task_id()?;
+        self.detector.acquire_at(task, self.resource, access, site);
+        Some(Hold {
+            registration: self,
+            task,
+        })
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        self.detector.unregister_resource(self.resource);
+    }
+}
+
+/// A task waiting for a primitive
+///
+/// Dropped before [`Wait::acquired`], as when the waiting future is
+/// cancelled, the wait is withdrawn.
+struct Wait<'a> {
+    registration: &'a Registration,
+    task: TaskId,
+    access: Access,
+    site: &'static Location<'static>,
+    acquired: bool,
+}
+
+impl<'a> Wait<'a> {
+    /// Record the wait ending with the task acquiring the primitive
+    fn acquired(mut self) -> Hold<'a> {
+        self.acquired = true;
+        self.registration.detector.acquire_at(
+            self.task,
+            self.registration.resource,
+            self.access,
+            self.site,
+        );
+        Hold {
+            registration: self.registration,
+            task: self.task,
+        }
+    }
+}
+
+impl Drop for Wait<'_> {
+    fn drop(&mut self) {
+        if !self.acquired {
+            self.registration
+                .detector
+                .cancel_wait(self.task, self.registration.resource);
+        }
+    }
+}
+
+/// A task holding a primitive, releasing it when dropped
+struct Hold<'a> {
+    registration: &'a Registration,
+    task: TaskId,
+}
+
+impl Drop for Hold<'_> {
+    fn drop(&mut self) {
+        self.registration
+            .detector
+            .release(self.task, self.registration.resource);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::tokio::{spawn_tracked, JoinHandleExt};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_lock_order_deadlock_is_detected() {
+        let detector = DeadlockDetector::new();
+        let a = Arc::new(Mutex::with_detector(detector.clone(), "a", ()));
+        let b = Arc::new(Mutex::with_detector(detector.clone(), "b", ()));
+        let barrier = Arc::new(tokio::sync::Barrier::new(2));
+
+        let lock_both = |first: Arc<Mutex<()>>, second: Arc<Mutex<()>>| {
+            let barrier = Arc::clone(&barrier);
+            async move {
+                let _first = first.lock().await;
+                barrier.wait().await;
+                let _second = second.lock().await;
+            }
+        };
+        let t1 = spawn_tracked("test_sync_deadlock_1", lock_both(a.clone(), b.clone())).tracked();
+        let t2 = spawn_tracked("test_sync_deadlock_2", lock_both(b.clone(), a.clone())).tracked();
+
+        let cycles = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let cycles = detector.detect_deadlocks();
+                if !cycles.is_empty() {
+                    return cycles;
+                }
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(cycles[0].tasks.len(), 2);
+
+        // Each task shares the lock it waits for with the other
+        let graph = Inspector::global().build_graph();
+        let (id1, id2) = (t1.task_id().unwrap(), t2.task_id().unwrap());
+        assert!(graph
+            .get_related_tasks(id1)
+            .contains(&(id2, RelationshipType::SharedResource)));
+        assert!(graph
+            .get_related_tasks(id2)
+            .contains(&(id1, RelationshipType::SharedResource)));
+        assert!(!graph.detect_potential_deadlocks().is_empty());
+
+        let mut resources = cycles[0].resources.clone();
+        resources.sort_by_key(ResourceId::as_u64);
+        let mut expected = vec![a.resource_id(), b.resource_id()];
+        expected.sort_by_key(ResourceId::as_u64);
+        assert_eq!(resources, expected);
+
+        // Cancelling the tasks withdraws their waits and releases the locks
+        t1.abort();
+        t2.abort();
+        let _ = t1.await;
+        let _ = t2.await;
+        assert!(detector.detect_deadlocks().is_empty());
+        let a_info = detector.get_resource(a.resource_id()).unwrap();
+        assert!(!a_info.is_held() && !a_info.has_waiters());
+    }
+
+    #[tokio::test]
+    async fn test_primitives_record_holders() {
+        let detector = DeadlockDetector::new();
+        let lock = RwLock::with_detector(detector.clone(), "config", 1);
+        let permits = Semaphore::with_detector(detector.clone(), "pool", 2);
+
+        let (lock_id, permits_id) = (lock.resource_id(), permits.resource_id());
+        let tracked = detector.clone();
+
+        let task = spawn_tracked("test_sync_holders", async move {
+            let holders = |id| tracked.get_resource(id).unwrap().holders;
+            let task = current_task_id().unwrap();
+            {
+                let mut value = lock.write().await;
+                *value += 1;
+                assert_eq!(holders(lock_id)[0].access, Access::Exclusive);
+            }
+            assert!(holders(lock_id).is_empty());
+            {
+                let first = lock.read().await;
+                let second = lock.read().await;
+                assert_eq!(*first + *second, 4);
+                assert_eq!(holders(lock_id).len(), 2);
+                assert!(holders(lock_id).iter().all(|h| h.access == Access::Shared));
+            }
+
+            permits.add_permits(1);
+            let _one = permits.acquire().await.unwrap();
+            let _two = permits.try_acquire_many(2).unwrap();
+            let info = tracked.get_resource(permits_id).unwrap();
+            assert!(info.is_held_by(task));
+            assert_eq!((info.held_permits(), info.capacity), (3, Some(3)));
+            drop(lock);
+        });
+        task.await.unwrap();
+
+        // Dropping a primitive unregisters it
+        assert!(detector.get_resource(lock_id).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_channel_deadlock_is_detected() {
+        let detector = DeadlockDetector::new();
+        let (to_b, from_a) = mpsc::channel_with_detector(detector.clone(), "a->b", 1);
+        let (to_a, from_b) = mpsc::channel_with_detector(detector.clone(), "b->a", 1);
+
+        let exchange = |tx: mpsc::Sender<u32>, mut rx: mpsc::Receiver<u32>, leads| async move {
+            // A round trip attributes both endpoints to the task using them
+            if leads {
+                tx.send(0).await.unwrap();
+                rx.recv().await.unwrap();
+            } else {
+                rx.recv().await.unwrap();
+                tx.send(0).await.unwrap();
+            }
+
+            // Each side then fills its outgoing channel before reading the other
+            tx.send(1).await.unwrap();
+            tx.send(2).await.unwrap();
+            rx.recv().await
+        };
+        let a = spawn_tracked("test_channel_deadlock_a", exchange(to_b, from_b, true));
+        let b = spawn_tracked("test_channel_deadlock_b", exchange(to_a, from_a, false));
+
+        let cycles = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let cycles = detector.detect_deadlocks();
+                if !cycles.is_empty() {
+                    return cycles;
+                }
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(cycles[0].tasks.len(), 2);
+        assert!(cycles[0].chain.iter().all(|e| e.access == Access::Sender));
+
+        a.abort();
+        b.abort();
+        let _ = a.await;
+        let _ = b.await;
+        assert!(detector.detect_deadlocks().is_empty());
+    }
+}
@@ -0,0 +1,255 @@
+//! Instrumented bounded `tokio::sync::mpsc` channel
+//!
+//! A channel has no single holder, so each endpoint is recorded as a claim
+//! on the channel resource: senders as [`Access::Sender`], the receiver as
+//! [`Access::Receiver`]. A send blocked on a full channel waits on the
+//! receiver's task, and a receive on an empty channel waits on every task
+//! holding a sender, which lets the detector find cycles through channels,
+//! including a receiver whose senders are all alive but parked.
+//!
+//! An endpoint is attributed lazily to the task that sends or receives with
+//! it, and moves to another task as soon as that task uses it, so endpoints
+//! handed to spawned tasks need no extra call.
+
+use super::{caller_name, Registration};
+use crate::deadlock::{Access, DeadlockDetector, ResourceId, ResourceInfo, ResourceKind};
+use crate::instrument::current_task_id;
+use crate::task::TaskId;
+use parking_lot::Mutex;
+use std::fmt;
+use std::future::Future;
+use std::panic::Location;
+use std::sync::Arc;
+use tokio::sync::mpsc::error::{SendError, TryRecvError, TrySendError};
+
+/// Create a bounded channel named after the place it is created, reporting
+/// to the global detector
+///
+/// # Panics
+///
+/// Panics if `buffer` is zero.
+#[track_caller]
+pub fn channel<T>(buffer: usize) -> (Sender<T>, Receiver<T>) {
+    let name = caller_name(&ResourceKind::Channel, Location::caller());
+    channel_with_detector(DeadlockDetector::global().clone(), name, buffer)
+}
+
+/// Create a named bounded channel reporting to the global detector
+///
+/// # Panics
+///
+/// Panics if `buffer` is zero.
+pub fn named_channel<T>(name: impl Into<String>, buffer: usize) -> (Sender<T>, Receiver<T>) {
+    channel_with_detector(DeadlockDetector::global().clone(), name, buffer)
+}
+
+/// Create a named bounded channel reporting to a specific detector
+///
+/// # Panics
+///
+/// Panics if `buffer` is zero.
+pub fn channel_with_detector<T>(
+    detector: DeadlockDetector,
+    name: impl Into<String>,
+    buffer: usize,
+) -> (Sender<T>, Receiver<T>) {
+    let (tx, rx) = tokio::sync::mpsc::channel(buffer);
+    let registration = Arc::new(Registration::new(
+        detector,
+        ResourceInfo::new(ResourceKind::Channel, name.into()),
+    ));
+
+    let sender = Sender {
+        endpoint: Endpoint::new(Arc::clone(&registration), Access::Sender),
+        inner: tx,
+    };
+    let receiver = Receiver {
+        endpoint: Endpoint::new(registration, Access::Receiver),
+        inner: rx,
+    };
+    (sender, receiver)
+}
+
+/// One end of a channel, held by the task that last used it
+struct Endpoint {
+    registration: Arc<Registration>,
+    access: Access,
+    holder: Mutex<Option<TaskId>>,
+}
+
+impl Endpoint {
+    fn new(registration: Arc<Registration>, access: Access) -> Self {
+        Self {
+            registration,
+            access,
+            holder: Mutex::new(None),
+        }
+    }
+
+    /// Attribute the endpoint to the current task, if there is one and it
+    /// is not already the holder
+    fn claim(&self) {
+        let Some(task) = current_task_id() else {
+            return;
+        };
+
+        let mut holder = self.holder.lock();
+        if *holder == Some(task) {
+            return;
+        }
+
+        let detector = &self.registration.detector;
+        let resource = self.registration.resource;
+        if let Some(previous) = holder.replace(task) {
+            detector.release_access(previous, resource, self.access);
+        }
+        detector.acquire_access(task, resource, self.access);
+    }
+}
+
+impl Drop for Endpoint {
+    fn drop(&mut self) {
+        if let Some(task) = self.holder.get_mut().take() {
+            self.registration.detector.release_access(
+                task,
+                self.registration.resource,
+                self.access,
+            );
+        }
+    }
+}
+
+/// Sending half of an instrumented channel
+pub struct Sender<T> {
+    endpoint: Endpoint,
+    inner: tokio::sync::mpsc::Sender<T>,
+}
+
+impl<T> Sender<T> {
+    /// Get the resource ID the channel is registered under
+    pub fn resource_id(&self) -> ResourceId {
+        self.endpoint.registration.resource
+    }
+
+    /// Send a value, waiting while the channel is full
+    ///
+    /// # Errors
+    ///
+    /// Returns the value back if the receiver has been dropped.
+    #[track_caller]
+    pub fn send(&self, value: T) -> impl Future<Output = Result<(), SendError<T>>> + '_ {
+        let site = Location::caller();
+        async move {
+            self.endpoint.claim();
+
+            // Only a send that finds the channel full waits on the receiver
+            let value = match self.inner.try_send(value) {
+                Ok(()) => return Ok(()),
+                Err(TrySendError::Closed(value)) => return Err(SendError(value)),
+                Err(TrySendError::Full(value)) => value,
+            };
+
+            let _wait = self.endpoint.registration.wait(Access::Sender, site);
+            self.inner.send(value).await
+        }
+    }
+
+    /// Try to send a value without waiting
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the channel is full or the receiver has been
+    /// dropped.
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        self.endpoint.claim();
+        self.inner.try_send(value)
+    }
+
+    /// Check whether the receiver has been dropped or closed
+    pub fn is_closed(&self) -> bool {
+        self.inner.is_closed()
+    }
+
+    /// Get the number of free slots in the channel
+    pub fn capacity(&self) -> usize {
+        self.inner.capacity()
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            endpoint: Endpoint::new(Arc::clone(&self.endpoint.registration), Access::Sender),
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for Sender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sender")
+            .field("resource", &self.endpoint.registration.resource)
+            .field("holder", &*self.endpoint.holder.lock())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Receiving half of an instrumented channel
+pub struct Receiver<T> {
+    endpoint: Endpoint,
+    inner: tokio::sync::mpsc::Receiver<T>,
+}
+
+impl<T> Receiver<T> {
+    /// Get the resource ID the channel is registered under
+    pub fn resource_id(&self) -> ResourceId {
+        self.endpoint.registration.resource
+    }
+
+    /// Receive the next value, waiting while the channel is empty
+    ///
+    /// Returns `None` once every sender has been dropped and the channel is
+    /// drained.
+    #[track_caller]
+    pub fn recv(&mut self) -> impl Future<Output = Option<T>> + '_ {
+        let site = Location::caller();
+        async move {
+            self.endpoint.claim();
+
+            // Only a receive that finds the channel empty waits on the senders
+            match self.inner.try_recv() {
+                Ok(value) => return Some(value),
+                Err(TryRecvError::Disconnected) => return None,
+                Err(TryRecvError::Empty) => {}
+            }
+
+            let _wait = self.endpoint.registration.wait(Access::Receiver, site);
+            self.inner.recv().await
+        }
+    }
+
+    /// Try to receive a value without waiting
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the channel is empty or every sender has been
+    /// dropped.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        self.endpoint.claim();
+        self.inner.try_recv()
+    }
+
+    /// Close the channel, failing further sends
+    pub fn close(&mut self) {
+        self.inner.close();
+    }
+}
+
+impl<T> fmt::Debug for Receiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Receiver")
+            .field("resource", &self.endpoint.registration.resource)
+            .field("holder", &*self.endpoint.holder.lock())
+            .finish_non_exhaustive()
+    }
+}
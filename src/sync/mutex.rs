@@ -0,0 +1,124 @@
+//! Instrumented `tokio::sync::Mutex`
+
+use super::{caller_name, Hold, Registration};
+use crate::deadlock::{Access, DeadlockDetector, ResourceId, ResourceInfo, ResourceKind};
+use std::fmt;
+use std::future::Future;
+use std::ops::{Deref, DerefMut};
+use std::panic::Location;
+
+/// An asynchronous mutex reporting to a deadlock detector
+///
+/// Behaves like `tokio::sync::Mutex`; see the [module docs](super).
+pub struct Mutex<T: ?Sized> {
+    registration: Registration,
+    inner: tokio::sync::Mutex<T>,
+}
+
+impl<T> Mutex<T> {
+    /// Create a mutex named after the place it is created, reporting to the
+    /// global detector
+    #[track_caller]
+    pub fn new(value: T) -> Self {
+        let name = caller_name(&ResourceKind::Mutex, Location::caller());
+        Self::with_detector(DeadlockDetector::global().clone(), name, value)
+    }
+
+    /// Create a named mutex reporting to the global detector
+    pub fn named(name: impl Into<String>, value: T) -> Self {
+        Self::with_detector(DeadlockDetector::global().clone(), name, value)
+    }
+
+    /// Create a named mutex reporting to a specific detector
+    pub fn with_detector(detector: DeadlockDetector, name: impl Into<String>, value: T) -> Self {
+        Self {
+            registration: Registration::new(
+                detector,
+                ResourceInfo::new(ResourceKind::Mutex, name.into()),
+            ),
+            inner: tokio::sync::Mutex::new(value),
+        }
+    }
+
+    /// Consume the mutex, returning the protected value
+    pub fn into_inner(self) -> T {
+        self.inner.into_inner()
+    }
+}
+
+impl<T: ?Sized> Mutex<T> {
+    /// Get the resource ID this mutex is registered under
+    pub fn resource_id(&self) -> ResourceId {
+        self.registration.resource
+    }
+
+    /// Lock the mutex, waiting until it is available
+    #[track_caller]
+    pub fn lock(&self) -> impl Future<Output = MutexGuard<'_, T>> {
+        let site = Location::caller();
+        async move {
+            let wait = self.registration.wait(Access::Exclusive, site);
+            let inner = self.inner.lock().await;
+            MutexGuard {
+                inner,
+                _hold: wait.map(super::Wait::acquired),
+            }
+        }
+    }
+
+    /// Try to lock the mutex without waiting
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the mutex is currently locked.
+    #[track_caller]
+    pub fn try_lock(&self) -> Result<MutexGuard<'_, T>, tokio::sync::TryLockError> {
+        let inner = self.inner.try_lock()?;
+        Ok(MutexGuard {
+            inner,
+            _hold: self
+                .registration
+                .hold(Access::Exclusive, Location::caller()),
+        })
+    }
+
+    /// Get a mutable reference to the value; no locking is needed
+    pub fn get_mut(&mut self) -> &mut T {
+        self.inner.get_mut()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Mutex<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Mutex")
+            .field("resource", &self.registration.resource)
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+/// Guard of a locked [`Mutex`], releasing it when dropped
+pub struct MutexGuard<'a, T: ?Sized> {
+    inner: tokio::sync::MutexGuard<'a, T>,
+    _hold: Option<Hold<'a>>,
+}
+
+impl<T: ?Sized> Deref for MutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T: ?Sized> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for MutexGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.inner, f)
+    }
+}
@@ -0,0 +1,172 @@
+//! Instrumented `tokio::sync::RwLock`
+
+use super::{caller_name, Hold, Registration};
+use crate::deadlock::{Access, DeadlockDetector, ResourceId, ResourceInfo, ResourceKind};
+use std::fmt;
+use std::future::Future;
+use std::ops::{Deref, DerefMut};
+use std::panic::Location;
+
+/// An asynchronous reader-writer lock reporting to a deadlock detector
+///
+/// Behaves like `tokio::sync::RwLock`; see the [module docs](super).
+pub struct RwLock<T: ?Sized> {
+    registration: Registration,
+    inner: tokio::sync::RwLock<T>,
+}
+
+impl<T> RwLock<T> {
+    /// Create a lock named after the place it is created, reporting to the
+    /// global detector
+    #[track_caller]
+    pub fn new(value: T) -> Self {
+        let name = caller_name(&ResourceKind::RwLock, Location::caller());
+        Self::with_detector(DeadlockDetector::global().clone(), name, value)
+    }
+
+    /// Create a named lock reporting to the global detector
+    pub fn named(name: impl Into<String>, value: T) -> Self {
+        Self::with_detector(DeadlockDetector::global().clone(), name, value)
+    }
+
+    /// Create a named lock reporting to a specific detector
+    pub fn with_detector(detector: DeadlockDetector, name: impl Into<String>, value: T) -> Self {
+        Self {
+            registration: Registration::new(
+                detector,
+                ResourceInfo::new(ResourceKind::RwLock, name.into()),
+            ),
+            inner: tokio::sync::RwLock::new(value),
+        }
+    }
+
+    /// Consume the lock, returning the protected value
+    pub fn into_inner(self) -> T {
+        self.inner.into_inner()
+    }
+}
+
+impl<T: ?Sized> RwLock<T> {
+    /// Get the resource ID this lock is registered under
+    pub fn resource_id(&self) -> ResourceId {
+        self.registration.resource
+    }
+
+    /// Lock for shared reading, waiting until no writer holds the lock
+    #[track_caller]
+    pub fn read(&self) -> impl Future<Output = RwLockReadGuard<'_, T>> {
+        let site = Location::caller();
+        async move {
+            let wait = self.registration.wait(Access::Shared, site);
+            let inner = self.inner.read().await;
+            RwLockReadGuard {
+                inner,
+                _hold: wait.map(super::Wait::acquired),
+            }
+        }
+    }
+
+    /// Lock for exclusive writing, waiting until the lock is free
+    #[track_caller]
+    pub fn write(&self) -> impl Future<Output = RwLockWriteGuard<'_, T>> {
+        let site = Location::caller();
+        async move {
+            let wait = self.registration.wait(Access::Exclusive, site);
+            let inner = self.inner.write().await;
+            RwLockWriteGuard {
+                inner,
+                _hold: wait.map(super::Wait::acquired),
+            }
+        }
+    }
+
+    /// Try to lock for reading without waiting
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a writer holds or is waiting for the lock.
+    #[track_caller]
+    pub fn try_read(&self) -> Result<RwLockReadGuard<'_, T>, tokio::sync::TryLockError> {
+        let inner = self.inner.try_read()?;
+        Ok(RwLockReadGuard {
+            inner,
+            _hold: self.registration.hold(Access::Shared, Location::caller()),
+        })
+    }
+
+    /// Try to lock for writing without waiting
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the lock is held.
+    #[track_caller]
+    pub fn try_write(&self) -> Result<RwLockWriteGuard<'_, T>, tokio::sync::TryLockError> {
+        let inner = self.inner.try_write()?;
+        Ok(RwLockWriteGuard {
+            inner,
+            _hold: self
+                .registration
+                .hold(Access::Exclusive, Location::caller()),
+        })
+    }
+
+    /// Get a mutable reference to the value; no locking is needed
+    pub fn get_mut(&mut self) -> &mut T {
+        self.inner.get_mut()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for RwLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RwLock")
+            .field("resource", &self.registration.resource)
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+/// Shared guard of an [`RwLock`], releasing it when dropped
+pub struct RwLockReadGuard<'a, T: ?Sized> {
+    inner: tokio::sync::RwLockReadGuard<'a, T>,
+    _hold: Option<Hold<'a>>,
+}
+
+impl<T: ?Sized> Deref for RwLockReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for RwLockReadGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.inner, f)
+    }
+}
+
+/// Exclusive guard of an [`RwLock`], releasing it when dropped
+pub struct RwLockWriteGuard<'a, T: ?Sized> {
+    inner: tokio::sync::RwLockWriteGuard<'a, T>,
+    _hold: Option<Hold<'a>>,
+}
+
+impl<T: ?Sized> Deref for RwLockWriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T: ?Sized> DerefMut for RwLockWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for RwLockWriteGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.inner, f)
+    }
+}
@@ -0,0 +1,155 @@
+//! Instrumented `tokio::sync::Semaphore`
+
+use super::{caller_name, Hold, Registration};
+use crate::deadlock::{Access, DeadlockDetector, ResourceId, ResourceInfo, ResourceKind};
+use std::fmt;
+use std::future::Future;
+use std::panic::Location;
+use tokio::sync::{AcquireError, TryAcquireError};
+
+/// An asynchronous counting semaphore reporting to a deadlock detector
+///
+/// Behaves like `tokio::sync::Semaphore`; see the [module docs](super).
+pub struct Semaphore {
+    registration: Registration,
+    inner: tokio::sync::Semaphore,
+}
+
+impl Semaphore {
+    /// Create a semaphore named after the place it is created, reporting to
+    /// the global detector
+    #[track_caller]
+    pub fn new(permits: usize) -> Self {
+        let name = caller_name(&ResourceKind::Semaphore, Location::caller());
+        Self::with_detector(DeadlockDetector::global().clone(), name, permits)
+    }
+
+    /// Create a named semaphore reporting to the global detector
+    pub fn named(name: impl Into<String>, permits: usize) -> Self {
+        Self::with_detector(DeadlockDetector::global().clone(), name, permits)
+    }
+
+    /// Create a named semaphore reporting to a specific detector
+    pub fn with_detector(
+        detector: DeadlockDetector,
+        name: impl Into<String>,
+        permits: usize,
+    ) -> Self {
+        Self {
+            registration: Registration::new(
+                detector,
+                ResourceInfo::new(ResourceKind::Semaphore, name.into()).with_capacity(permits),
+            ),
+            inner: tokio::sync::Semaphore::new(permits),
+        }
+    }
+
+    /// Get the resource ID this semaphore is registered under
+    pub fn resource_id(&self) -> ResourceId {
+        self.registration.resource
+    }
+
+    /// Get the number of permits currently available
+    pub fn available_permits(&self) -> usize {
+        self.inner.available_permits()
+    }
+
+    /// Add permits to the semaphore
+    pub fn add_permits(&self, permits: usize) {
+        self.inner.add_permits(permits);
+        self.registration
+            .detector
+            .add_capacity(self.registration.resource, permits);
+    }
+
+    /// Close the semaphore, failing every pending and future acquire
+    pub fn close(&self) {
+        self.inner.close();
+    }
+
+    /// Acquire a permit, waiting until one is available
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the semaphore is closed.
+    #[track_caller]
+    pub fn acquire(&self) -> impl Future<Output = Result<SemaphorePermit<'_>, AcquireError>> {
+        self.acquire_many(1)
+    }
+
+    /// Acquire several permits at once, waiting until they are available
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the semaphore is closed.
+    #[track_caller]
+    pub fn acquire_many(
+        &self,
+        permits: u32,
+    ) -> impl Future<Output = Result<SemaphorePermit<'_>, AcquireError>> {
+        let site = Location::caller();
+        async move {
+            let wait = self.registration.wait(Access::Permits(permits), site);
+            let inner = self.inner.acquire_many(permits).await?;
+            Ok(SemaphorePermit {
+                inner,
+                _hold: wait.map(super::Wait::acquired),
+            })
+        }
+    }
+
+    /// Try to acquire a permit without waiting
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no permit is available or the semaphore is closed.
+    #[track_caller]
+    pub fn try_acquire(&self) -> Result<SemaphorePermit<'_>, TryAcquireError> {
+        self.try_acquire_many(1)
+    }
+
+    /// Try to acquire several permits without waiting
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if not enough permits are available or the semaphore
+    /// is closed.
+    #[track_caller]
+    pub fn try_acquire_many(&self, permits: u32) -> Result<SemaphorePermit<'_>, TryAcquireError> {
+        let inner = self.inner.try_acquire_many(permits)?;
+        Ok(SemaphorePermit {
+            inner,
+            _hold: self
+                .registration
+                .hold(Access::Permits(permits), Location::caller()),
+        })
+    }
+}
+
+impl fmt::Debug for Semaphore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Semaphore")
+            .field("resource", &self.registration.resource)
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+/// Permits acquired from a [`Semaphore`], returned when dropped
+pub struct SemaphorePermit<'a> {
+    inner: tokio::sync::SemaphorePermit<'a>,
+    _hold: Option<Hold<'a>>,
+}
+
+impl SemaphorePermit<'_> {
+    /// Get the number of permits held
+    pub fn num_permits(&self) -> usize {
+        self.inner.num_permits()
+    }
+}
+
+impl fmt::Debug for SemaphorePermit<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.inner, f)
+    }
+}
@@ -56,8 +56,33 @@ pub enum TaskState {
     },
     /// Task has completed successfully
     Completed,
-    /// Task was cancelled or panicked
-    Failed,
+    /// Task returned an error
+    Failed {
+        /// Error message, if any
+        error: Option<String>,
+    },
+    /// Task panicked while being polled
+    Panicked {
+        /// Panic payload message
+        message: String,
+    },
+    /// Task was dropped before it completed
+    Cancelled,
+}
+
+impl TaskState {
+    /// Check whether the task has finished, successfully or not
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            Self::Completed | Self::Failed { .. } | Self::Panicked { .. } | Self::Cancelled
+        )
+    }
+
+    /// Check whether the task ended with an error or a panic
+    pub fn is_failure(&self) -> bool {
+        matches!(self, Self::Failed { .. } | Self::Panicked { .. })
+    }
 }
 
 impl fmt::Display for TaskState {
@@ -67,11 +92,139 @@ impl fmt::Display for TaskState {
             Self::Running => write!(f, "RUNNING"),
             Self::Blocked { await_point } => write!(f, "BLOCKED({})", await_point),
             Self::Completed => write!(f, "COMPLETED"),
-            Self::Failed => write!(f, "FAILED"),
+            Self::Failed { error: Some(error) } => write!(f, "FAILED({error})"),
+            Self::Failed { error: None } => write!(f, "FAILED"),
+            Self::Panicked { message } => write!(f, "PANICKED({message})"),
+            Self::Cancelled => write!(f, "CANCELLED"),
         }
     }
 }
 
+/// How a child task relates to its parent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ChildKind {
+    /// Awaited inline by the parent, e.g. a nested `#[trace]` call
+    Inline,
+    /// Spawned as a separate task that runs concurrently with the parent
+    Spawned,
+}
+
+impl fmt::Display for ChildKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Inline => write!(f, "awaited"),
+            Self::Spawned => write!(f, "spawned"),
+        }
+    }
+}
+
+/// Verbosity level of a task, from least to most important
+///
+/// Set with `#[trace(level = "...")]`; tasks below
+/// [`Config::min_level`](crate::config::Config::min_level) are not tracked.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum TaskLevel {
+    /// Very detailed, usually hot-path tasks
+    Trace,
+    /// Tasks useful when debugging
+    Debug,
+    /// Regular tasks
+    #[default]
+    Info,
+    /// Tasks worth watching
+    Warn,
+    /// Tasks whose behaviour always matters
+    Error,
+}
+
+impl TaskLevel {
+    /// All levels, in order
+    pub const ALL: [Self; 5] = [
+        Self::Trace,
+        Self::Debug,
+        Self::Info,
+        Self::Warn,
+        Self::Error,
+    ];
+}
+
+impl fmt::Display for TaskLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Trace => write!(f, "trace"),
+            Self::Debug => write!(f, "debug"),
+            Self::Info => write!(f, "info"),
+            Self::Warn => write!(f, "warn"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Waker activity of a task
+#[derive(Debug, Clone, Default)]
+pub struct WakerStats {
+    /// Wakes through `wake`, consuming the waker
+    pub wakes: u64,
+    /// Wakes through `wake_by_ref`
+    pub wakes_by_ref: u64,
+    /// Wakes issued by the task itself while being polled
+    pub self_wakes: u64,
+    /// Waker clones created by the task's futures
+    pub clones: u64,
+    /// Waker clones released, by being dropped or consumed by `wake`
+    pub drops: u64,
+    /// When the task was woken, if it has not been polled since
+    pub woken_at: Option<Instant>,
+    /// Whether the last instrumented poll returned `Poll::Pending`
+    pub last_poll_pending: bool,
+    /// Number of polls that followed a wake
+    pub scheduled_polls: u64,
+    /// Total time between wakes and the polls that followed
+    pub total_schedule_latency: Duration,
+    /// Longest time between a wake and the poll that followed
+    pub max_schedule_latency: Duration,
+}
+
+impl WakerStats {
+    /// Total number of wakes, by value or by reference
+    pub fn total_wakes(&self) -> u64 {
+        self.wakes + self.wakes_by_ref
+    }
+
+    /// Number of waker clones still alive
+    pub fn live_clones(&self) -> u64 {
+        self.clones.saturating_sub(self.drops)
+    }
+
+    /// Whether the task can no longer be woken
+    ///
+    /// True when the last poll returned `Poll::Pending`, no wake happened
+    /// since, and every clone of the waker has been dropped.
+    pub fn lost_wakeup(&self) -> bool {
+        self.last_poll_pending && self.woken_at.is_none() && self.live_clones() == 0
+    }
+
+    /// Average time between a wake and the poll that followed
+    pub fn mean_schedule_latency(&self) -> Duration {
+        if self.scheduled_polls == 0 {
+            return Duration::ZERO;
+        }
+        let polls = u32::try_from(self.scheduled_polls).unwrap_or(u32::MAX);
+        self.total_schedule_latency / polls
+    }
+
+    /// Record a poll, returning the time since the wake that scheduled it
+    pub fn record_poll(&mut self) -> Option<Duration> {
+        let latency = self.woken_at.take()?.elapsed();
+        self.scheduled_polls += 1;
+        self.total_schedule_latency += latency;
+        self.max_schedule_latency = self.max_schedule_latency.max(latency);
+        Some(latency)
+    }
+}
+
 /// Information about a task
 #[derive(Debug, Clone)]
 pub struct TaskInfo {
@@ -99,8 +252,32 @@ pub struct TaskInfo {
     /// Parent task ID, if any
     pub parent: Option<TaskId>,
 
-    /// Source location (file:line)
+    /// How this task relates to its parent, if it has one
+    pub child_kind: Option<ChildKind>,
+
+    /// Source location (file:line:column)
     pub location: Option<String>,
+
+    /// Await point the task is suspended at, until that await ends
+    pub await_point: Option<String>,
+
+    /// Source location of the current await point, if recorded
+    pub await_location: Option<String>,
+
+    /// When the task started waiting at the current await point
+    ///
+    /// Unlike `last_updated`, polls that leave the task at the same await
+    /// point do not move it.
+    pub await_since: Option<Instant>,
+
+    /// Verbosity level
+    pub level: TaskLevel,
+
+    /// Recorded key/value attributes, such as `#[trace]` fields
+    pub fields: Vec<(String, String)>,
+
+    /// Waker activity, recorded when the task's waker is instrumented
+    pub wakers: WakerStats,
 }
 
 impl TaskInfo {
@@ -116,7 +293,14 @@ impl TaskInfo {
             poll_count: 0,
             total_run_time: Duration::ZERO,
             parent: None,
+            child_kind: None,
             location: None,
+            await_point: None,
+            await_location: None,
+            await_since: None,
+            level: TaskLevel::default(),
+            fields: Vec::new(),
+            wakers: WakerStats::default(),
         }
     }
 
@@ -138,14 +322,30 @@ impl TaskInfo {
         self.created_at.elapsed()
     }
 
+    /// Get how long the task ran: until it finished, or until now while it
+    /// is still running
+    pub fn duration(&self) -> Duration {
+        if self.state.is_terminal() {
+            self.last_updated.duration_since(self.created_at)
+        } else {
+            self.age()
+        }
+    }
+
     /// Get time since last update
     pub fn time_since_update(&self) -> Duration {
         self.last_updated.elapsed()
     }
 
-    /// Set the parent task
-    pub fn with_parent(mut self, parent: TaskId) -> Self {
+    /// Set the parent task that spawned this one
+    pub fn with_parent(self, parent: TaskId) -> Self {
+        self.with_parent_kind(parent, ChildKind::Spawned)
+    }
+
+    /// Set the parent task and how this task relates to it
+    pub fn with_parent_kind(mut self, parent: TaskId, kind: ChildKind) -> Self {
         self.parent = Some(parent);
+        self.child_kind = Some(kind);
         self
     }
 
@@ -154,6 +354,26 @@ impl TaskInfo {
         self.location = Some(location);
         self
     }
+
+    /// Set the verbosity level
+    pub fn with_level(mut self, level: TaskLevel) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Attach a key/value attribute
+    pub fn with_field(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.fields.push((key.into(), value.into()));
+        self
+    }
+
+    /// Get the value of an attribute
+    pub fn field(&self, key: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
 }
 
 impl fmt::Display for TaskInfo {
@@ -1,14 +1,26 @@
 //! Execution timeline tracking
 //!
 //! This module provides event tracking and timeline management for async operations.
+//! The [`Timeline`] is a bounded ring buffer: once it reaches its capacity, an
+//! [`EvictionPolicy`] decides which events make room for new ones. Readers can
+//! follow it incrementally with an [`EventCursor`].
 
+use crate::deadlock::ResourceId;
 use crate::task::{TaskId, TaskState};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
 use std::time::{Duration, Instant};
 
 /// Unique identifier for an event
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+///
+/// IDs are assigned in increasing order as events are recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct EventId(u64);
 
 impl EventId {
@@ -16,6 +28,55 @@ impl EventId {
     pub fn new(id: u64) -> Self {
         Self(id)
     }
+
+    /// Get the raw ID value
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Names of the threads that were given an index
+static THREAD_NAMES: Lazy<RwLock<HashMap<u64, String>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+thread_local! {
+    /// Index of the current thread, once it recorded an event
+    static THREAD_INDEX: Cell<Option<ThreadIndex>> = const { Cell::new(None) };
+}
+
+/// Index of the OS thread an event was recorded on
+///
+/// Indices are small and assigned in the order threads first record an
+/// event, so that worker threads of a runtime can be told apart in exports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ThreadIndex(u64);
+
+impl ThreadIndex {
+    /// Get the index of the current thread, assigning one on first use
+    pub fn current() -> Self {
+        static NEXT_INDEX: AtomicU64 = AtomicU64::new(1);
+
+        THREAD_INDEX.with(|cell| {
+            if let Some(index) = cell.get() {
+                return index;
+            }
+            let index = Self(NEXT_INDEX.fetch_add(1, Ordering::Relaxed));
+            if let Some(name) = thread::current().name() {
+                THREAD_NAMES.write().insert(index.0, name.to_string());
+            }
+            cell.set(Some(index));
+            index
+        })
+    }
+
+    /// Get the raw index value
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+
+    /// Get the name of the thread, if it had one
+    pub fn name(&self) -> Option<String> {
+        THREAD_NAMES.read().get(&self.0).cloned()
+    }
 }
 
 /// Type of event that occurred
@@ -40,6 +101,16 @@ pub enum EventKind {
         duration: Duration,
     },
 
+    /// A poll ran past the long poll threshold, blocking the executor
+    LongPoll {
+        /// Time spent in this poll
+        duration: Duration,
+        /// Await point the task stopped at, if known
+        await_point: Option<String>,
+        /// Source location of that await point, or of the task
+        location: Option<String>,
+    },
+
     /// Task started waiting at an await point
     AwaitStarted {
         /// Name/description of what we're waiting for
@@ -62,12 +133,60 @@ pub enum EventKind {
         duration: Duration,
     },
 
-    /// Task failed or was cancelled
+    /// Task's waker was invoked
+    TaskWoken {
+        /// Whether `wake_by_ref` was used rather than `wake`
+        by_ref: bool,
+        /// Whether the task woke itself while being polled
+        self_wake: bool,
+    },
+
+    /// Task's waker was cloned
+    WakerCloned,
+
+    /// A clone of the task's waker was dropped or consumed
+    WakerDropped,
+
+    /// A woken task started being polled
+    TaskScheduled {
+        /// Time between the wake and the poll
+        latency: Duration,
+    },
+
+    /// Task stayed blocked at an await point past its stall timeout
+    TaskStalled {
+        /// Await point the task is blocked on
+        await_point: String,
+        /// How long the task has been blocked there
+        duration: Duration,
+    },
+
+    /// Task found waiting in a deadlock cycle
+    DeadlockDetected {
+        /// Tasks in the cycle, in wait order
+        tasks: Vec<TaskId>,
+        /// Resources the tasks wait for, in the same order
+        resources: Vec<ResourceId>,
+    },
+
+    /// Task returned an error
     TaskFailed {
         /// Error message, if any
         error: Option<String>,
     },
 
+    /// Task panicked while being polled
+    TaskPanicked {
+        /// Panic payload message
+        message: String,
+    },
+
+    /// Task was dropped before it completed
+    TaskCancelled {
+        /// Time from spawn until the task was dropped
+        duration: Duration,
+    },
+
     /// Custom inspection point
     InspectionPoint {
         /// Label for this point
@@ -85,6 +204,19 @@ pub enum EventKind {
     },
 }
 
+impl EventKind {
+    /// Check whether this event ends its task
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            Self::TaskCompleted { .. }
+                | Self::TaskFailed { .. }
+                | Self::TaskPanicked { .. }
+                | Self::TaskCancelled { .. }
+        )
+    }
+}
+
 impl fmt::Display for EventKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -93,6 +225,17 @@ impl fmt::Display for EventKind {
             Self::PollEnded { duration } => {
                 write!(f, "Poll ended ({:.2}ms)", duration.as_secs_f64() * 1000.0)
             }
+            Self::LongPoll {
+                duration,
+                await_point,
+                ..
+            } => {
+                let ms = duration.as_secs_f64() * 1000.0;
+                match await_point {
+                    Some(point) => write!(f, "Long poll ({ms:.2}ms) before {point}"),
+                    None => write!(f, "Long poll ({ms:.2}ms)"),
+                }
+            }
             Self::AwaitStarted { await_point, .. } => write!(f, "Await started: {}", await_point),
             Self::AwaitEnded {
                 await_point,
@@ -115,6 +258,37 @@ impl fmt::Display for EventKind {
                     write!(f, "Failed")
                 }
             }
+            Self::TaskWoken { by_ref, self_wake } => {
+                let how = if *by_ref { "by ref" } else { "by value" };
+                if *self_wake {
+                    write!(f, "Woken {how} (self)")
+                } else {
+                    write!(f, "Woken {how}")
+                }
+            }
+            Self::WakerCloned => write!(f, "Waker cloned"),
+            Self::WakerDropped => write!(f, "Waker dropped"),
+            Self::TaskScheduled { latency } => {
+                write!(f, "Scheduled ({:.2}ms)", latency.as_secs_f64() * 1000.0)
+            }
+            Self::TaskStalled {
+                await_point,
+                duration,
+            } => write!(
+                f,
+                "Stalled at {await_point} ({:.2}s)",
+                duration.as_secs_f64()
+            ),
+            Self::DeadlockDetected { tasks, resources } => write!(
+                f,
+                "Deadlocked ({} tasks, {} resources)",
+                tasks.len(),
+                resources.len()
+            ),
+            Self::TaskPanicked { message } => write!(f, "Panicked: {message}"),
+            Self::TaskCancelled { duration } => {
+                write!(f, "Cancelled ({:.2}s)", duration.as_secs_f64())
+            }
             Self::InspectionPoint { label, message } => {
                 if let Some(msg) = message {
                     write!(f, "Inspection[{}]: {}", label, msg)
@@ -144,6 +318,9 @@ pub struct Event {
     /// When the event occurred
     pub timestamp: Instant,
 
+    /// Thread the event was recorded on
+    pub thread: ThreadIndex,
+
     /// Type and details of the event
     pub kind: EventKind,
 }
@@ -155,6 +332,7 @@ impl Event {
             id: EventId::new(id),
             task_id,
             timestamp: Instant::now(),
+            thread: ThreadIndex::current(),
             kind,
         }
     }
@@ -177,36 +355,273 @@ impl fmt::Display for Event {
     }
 }
 
+/// Policy deciding which events are dropped once a [`Timeline`] is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EvictionPolicy {
+    /// Drop the oldest events first (plain ring buffer)
+    #[default]
+    DropOldest,
+    /// Drop the oldest events of tasks holding more than this many events,
+    /// so every task keeps at least its most recent N events
+    KeepTaskTail(usize),
+    /// Drop events of completed or failed tasks before those of tasks
+    /// that are still alive
+    KeepUnfinished,
+}
+
+impl fmt::Display for EvictionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DropOldest => write!(f, "drop oldest"),
+            Self::KeepTaskTail(n) => write!(f, "keep last {n} events per task"),
+            Self::KeepUnfinished => write!(f, "keep unfinished tasks"),
+        }
+    }
+}
+
+/// Fraction of the capacity freed at once by the selective policies, so the
+/// cost of scanning the buffer is amortised over many insertions
+const EVICTION_BATCH_DIVISOR: usize = 16;
+
+/// Read position in a [`Timeline`]
+///
+/// A cursor remembers the last event a reader has seen, so subsequent reads
+/// only return newer events instead of the whole timeline.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EventCursor {
+    last_seen: Option<EventId>,
+}
+
+impl EventCursor {
+    /// Create a cursor positioned before the first event
+    pub fn new() -> Self {
+        Self { last_seen: None }
+    }
+
+    /// Create a cursor positioned just after the given event
+    pub fn after(id: EventId) -> Self {
+        Self {
+            last_seen: Some(id),
+        }
+    }
+
+    /// Get the last event seen through this cursor
+    pub fn last_seen(&self) -> Option<EventId> {
+        self.last_seen
+    }
+
+    /// Move the cursor past the given event
+    pub fn advance(&mut self, id: EventId) {
+        if self.last_seen.map_or(true, |last| id > last) {
+            self.last_seen = Some(id);
+        }
+    }
+}
+
 /// Timeline of events
+///
+/// Events are kept in a ring buffer of at most `capacity` events
+/// (0 = unlimited). Event IDs are expected to be added in increasing order.
 #[derive(Debug, Default)]
 pub struct Timeline {
     /// All events in chronological order
-    events: Vec<Event>,
+    events: VecDeque<Event>,
 
     /// Start time of the timeline
     start_time: Option<Instant>,
+
+    /// Maximum number of retained events (0 = unlimited)
+    capacity: usize,
+
+    /// Which events to drop once full
+    policy: EvictionPolicy,
+
+    /// Number of retained events per task
+    task_counts: HashMap<TaskId, usize>,
+
+    /// Tasks whose completion or failure is in the buffer
+    finished_tasks: HashSet<TaskId>,
 }
 
 impl Timeline {
-    /// Create a new timeline
+    /// Create a new unbounded timeline
     pub fn new() -> Self {
+        Self::with_capacity(0, EvictionPolicy::DropOldest)
+    }
+
+    /// Create a timeline holding at most `capacity` events (0 = unlimited)
+    pub fn with_capacity(capacity: usize, policy: EvictionPolicy) -> Self {
         Self {
-            events: Vec::new(),
+            events: VecDeque::new(),
             start_time: None,
+            capacity,
+            policy,
+            task_counts: HashMap::new(),
+            finished_tasks: HashSet::new(),
         }
     }
 
+    /// Get the maximum number of retained events (0 = unlimited)
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Set the maximum number of retained events (0 = unlimited)
+    ///
+    /// Takes effect on the next insertion.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+    }
+
+    /// Get the eviction policy
+    pub fn policy(&self) -> EvictionPolicy {
+        self.policy
+    }
+
+    /// Set the eviction policy
+    pub fn set_policy(&mut self, policy: EvictionPolicy) {
+        self.policy = policy;
+    }
+
     /// Add an event to the timeline
-    pub fn add_event(&mut self, event: Event) {
+    ///
+    /// Returns the number of events evicted to stay within capacity.
+    pub fn add_event(&mut self, event: Event) -> usize {
         if self.start_time.is_none() {
             self.start_time = Some(event.timestamp);
         }
-        self.events.push(event);
+
+        *self.task_counts.entry(event.task_id).or_insert(0) += 1;
+        if event.kind.is_terminal() {
+            self.finished_tasks.insert(event.task_id);
+        }
+
+        self.events.push_back(event);
+        let evicted = self.enforce_capacity();
+        self.keep_contiguous();
+        evicted
+    }
+
+    /// Rotate the buffer back into a single slice once it wraps around, so
+    /// [`Timeline::events`] can borrow it
+    ///
+    /// Room for twice the current length is reserved first, so a full
+    /// buffer wraps at most once every `len` insertions.
+    fn keep_contiguous(&mut self) {
+        if self.events.as_slices().1.is_empty() {
+            return;
+        }
+        self.events.reserve(self.events.len());
+        self.events.make_contiguous();
+    }
+
+    /// Evict events according to the policy until within capacity
+    fn enforce_capacity(&mut self) -> usize {
+        if self.capacity == 0 || self.events.len() <= self.capacity {
+            return 0;
+        }
+
+        let before = self.events.len();
+        let target = match self.policy {
+            EvictionPolicy::DropOldest => self.capacity,
+            EvictionPolicy::KeepTaskTail(_) | EvictionPolicy::KeepUnfinished => {
+                let target = self.capacity - self.capacity / EVICTION_BATCH_DIVISOR;
+                self.evict_selected(target);
+                target
+            }
+        };
+
+        while self.events.len() > target {
+            let Some(event) = self.events.pop_front() else {
+                break;
+            };
+            self.forget(event.task_id);
+        }
+
+        before - self.events.len()
+    }
+
+    /// Evict, oldest first, the events the policy prefers to drop
+    fn evict_selected(&mut self, target: usize) {
+        let has_candidates = match self.policy {
+            EvictionPolicy::DropOldest => false,
+            EvictionPolicy::KeepTaskTail(n) => self.task_counts.values().any(|&c| c > n),
+            EvictionPolicy::KeepUnfinished => !self.finished_tasks.is_empty(),
+        };
+        if !has_candidates {
+            return;
+        }
+
+        let mut remaining = self.events.len().saturating_sub(target);
+        let policy = self.policy;
+        let task_counts = &mut self.task_counts;
+        let finished_tasks = &mut self.finished_tasks;
+
+        self.events.retain(|event| {
+            if remaining == 0 {
+                return true;
+            }
+
+            let count = task_counts.get(&event.task_id).copied().unwrap_or(0);
+            let evict = match policy {
+                EvictionPolicy::DropOldest => false,
+                EvictionPolicy::KeepTaskTail(n) => count > n,
+                EvictionPolicy::KeepUnfinished => finished_tasks.contains(&event.task_id),
+            };
+
+            if evict {
+                remaining -= 1;
+                if count <= 1 {
+                    task_counts.remove(&event.task_id);
+                    finished_tasks.remove(&event.task_id);
+                } else {
+                    task_counts.insert(event.task_id, count - 1);
+                }
+            }
+            !evict
+        });
+    }
+
+    /// Update bookkeeping after an event of `task_id` was evicted
+    fn forget(&mut self, task_id: TaskId) {
+        if let Some(count) = self.task_counts.get_mut(&task_id) {
+            *count -= 1;
+            if *count == 0 {
+                self.task_counts.remove(&task_id);
+                self.finished_tasks.remove(&task_id);
+            }
+        }
     }
 
-    /// Get all events
+    /// Get all events in chronological order
     pub fn events(&self) -> &[Event] {
-        &self.events
+        // Kept contiguous by every insertion
+        self.events.as_slices().0
+    }
+
+    /// Iterate over all events in chronological order
+    pub fn iter_events(&self) -> impl DoubleEndedIterator<Item = &Event> + ExactSizeIterator {
+        self.events.iter()
+    }
+
+    /// Get the events recorded after `id`, without copying the rest
+    pub fn events_since(&self, id: EventId) -> impl Iterator<Item = &Event> {
+        let start = self.events.partition_point(|e| e.id <= id);
+        self.events.range(start..)
+    }
+
+    /// Get the events a cursor has not seen yet
+    pub fn events_after(&self, cursor: &EventCursor) -> impl Iterator<Item = &Event> {
+        let start = match cursor.last_seen() {
+            Some(id) => self.events.partition_point(|e| e.id <= id),
+            None => 0,
+        };
+        self.events.range(start..)
+    }
+
+    /// Get the most recent event
+    pub fn last_event(&self) -> Option<&Event> {
+        self.events.back()
     }
 
     /// Get events for a specific task
@@ -237,6 +652,8 @@ impl Timeline {
     /// Clear all events
     pub fn clear(&mut self) {
         self.events.clear();
+        self.task_counts.clear();
+        self.finished_tasks.clear();
         self.start_time = None;
     }
 }
@@ -290,4 +707,83 @@ mod tests {
         let task1_events = timeline.events_for_task(task1);
         assert_eq!(task1_events.len(), 2);
     }
+
+    #[test]
+    fn test_drop_oldest_ring_buffer() {
+        let mut timeline = Timeline::with_capacity(3, EvictionPolicy::DropOldest);
+        let task_id = TaskId::new();
+
+        let evicted: usize = (1..=5)
+            .map(|i| timeline.add_event(Event::new(i, task_id, EventKind::PollStarted)))
+            .sum();
+
+        assert_eq!(evicted, 2);
+        assert_eq!(timeline.len(), 3);
+        assert_eq!(timeline.events()[0].id, EventId::new(3));
+
+        // The whole buffer stays borrowable as one slice as it wraps
+        for i in 6..=100 {
+            timeline.add_event(Event::new(i, task_id, EventKind::PollStarted));
+            let ids: Vec<u64> = timeline.events().iter().map(|e| e.id.as_u64()).collect();
+            assert_eq!(ids, vec![i - 2, i - 1, i]);
+        }
+    }
+
+    #[test]
+    fn test_keep_task_tail() {
+        let mut timeline = Timeline::with_capacity(4, EvictionPolicy::KeepTaskTail(1));
+        let quiet = TaskId::new();
+        let noisy = TaskId::new();
+
+        timeline.add_event(Event::new(1, quiet, EventKind::PollStarted));
+        for i in 2..=6 {
+            timeline.add_event(Event::new(i, noisy, EventKind::PollStarted));
+        }
+
+        // The quiet task's only event survives although it is the oldest
+        assert_eq!(timeline.events_for_task(quiet).len(), 1);
+        assert!(timeline.len() <= 4);
+        assert_eq!(timeline.last_event().unwrap().id, EventId::new(6));
+    }
+
+    #[test]
+    fn test_keep_unfinished() {
+        let mut timeline = Timeline::with_capacity(3, EvictionPolicy::KeepUnfinished);
+        let stuck = TaskId::new();
+        let done = TaskId::new();
+
+        timeline.add_event(Event::new(1, stuck, EventKind::PollStarted));
+        timeline.add_event(Event::new(2, done, EventKind::PollStarted));
+        timeline.add_event(Event::new(
+            3,
+            done,
+            EventKind::TaskCompleted {
+                duration: Duration::from_millis(1),
+            },
+        ));
+        timeline.add_event(Event::new(4, stuck, EventKind::PollStarted));
+
+        assert_eq!(timeline.events_for_task(stuck).len(), 2);
+        assert!(timeline.events_for_task(done).len() < 2);
+    }
+
+    #[test]
+    fn test_events_since_cursor() {
+        let mut timeline = Timeline::new();
+        let task_id = TaskId::new();
+        for i in 1..=5 {
+            timeline.add_event(Event::new(i, task_id, EventKind::PollStarted));
+        }
+
+        let mut cursor = EventCursor::new();
+        assert_eq!(timeline.events_after(&cursor).count(), 5);
+
+        cursor.advance(EventId::new(3));
+        let ids: Vec<u64> = timeline
+            .events_after(&cursor)
+            .map(|e| e.id.as_u64())
+            .collect();
+        assert_eq!(ids, vec![4, 5]);
+        assert_eq!(timeline.events_since(EventId::new(5)).count(), 0);
+    }
 }
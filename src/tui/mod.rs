@@ -4,7 +4,9 @@
 //! async tasks in real-time, similar to htop for processes.
 
 use crate::inspector::Inspector;
-use crate::task::{TaskInfo, TaskState};
+use crate::profile::TaskMetrics;
+use crate::task::{TaskId, TaskInfo, TaskState};
+use crate::timeline::EventCursor;
 use crossterm::{
     event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
     execute,
@@ -18,9 +20,16 @@ use ratatui::{
     widgets::{Block, Borders, Paragraph, Row, Table},
     Frame, Terminal,
 };
+use std::collections::{HashSet, VecDeque};
 use std::io;
 use std::time::{Duration, Instant};
 
+/// Number of recent events kept for the events panel
+const RECENT_EVENTS: usize = 100;
+
+/// Number of tasks listed in the top blocking panel
+const TOP_BLOCKING: usize = 5;
+
 /// Sort mode for task list
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SortMode {
@@ -73,6 +82,18 @@ pub struct TuiApp {
 
     /// Update interval
     update_interval: Duration,
+
+    /// Read position in the inspector timeline
+    event_cursor: EventCursor,
+
+    /// Most recent events, newest last
+    recent_events: VecDeque<String>,
+
+    /// Tasks with a lost wakeup, as of the last update
+    lost_wakeups: HashSet<TaskId>,
+
+    /// Tasks that blocked the executor the longest, as of the last update
+    top_blocking: Vec<TaskMetrics>,
 }
 
 impl TuiApp {
@@ -86,6 +107,38 @@ impl TuiApp {
             show_help: false,
             last_update: Instant::now(),
             update_interval: Duration::from_millis(100),
+            event_cursor: EventCursor::new(),
+            recent_events: VecDeque::with_capacity(RECENT_EVENTS),
+            lost_wakeups: HashSet::new(),
+            top_blocking: Vec::new(),
+        }
+    }
+
+    /// Refresh the data shown, once per update interval
+    ///
+    /// Frames drawn in between, such as after a key press, reuse it.
+    fn update(&mut self) {
+        self.lost_wakeups = self
+            .inspector
+            .lost_wakeups()
+            .iter()
+            .map(|lost| lost.task_id)
+            .collect();
+        self.top_blocking = self
+            .inspector
+            .build_profiler()
+            .top_blocking_tasks(TOP_BLOCKING)
+            .into_iter()
+            .cloned()
+            .collect();
+        self.last_update = Instant::now();
+
+        for event in self.inspector.events_since(&mut self.event_cursor) {
+            if self.recent_events.len() == RECENT_EVENTS {
+                self.recent_events.pop_front();
+            }
+            self.recent_events
+                .push_back(format!("{} {}", event.task_id, event.kind));
         }
     }
 
@@ -103,7 +156,7 @@ impl TuiApp {
             FilterMode::All => true,
             FilterMode::Running => matches!(task.state, TaskState::Running),
             FilterMode::Completed => matches!(task.state, TaskState::Completed),
-            FilterMode::Failed => matches!(task.state, TaskState::Failed),
+            FilterMode::Failed => task.state.is_failure(),
             FilterMode::Blocked => matches!(task.state, TaskState::Blocked { .. }),
         });
 
@@ -197,7 +250,11 @@ fn run_app<B: ratatui::backend::Backend>(
     terminal: &mut Terminal<B>,
     app: &mut TuiApp,
 ) -> io::Result<()> {
+    app.update();
     loop {
+        if app.last_update.elapsed() >= app.update_interval {
+            app.update();
+        }
         terminal.draw(|f| ui(f, app))?;
 
         // Handle input with timeout
@@ -218,8 +275,6 @@ fn run_app<B: ratatui::backend::Backend>(
                 }
             }
         }
-
-        app.last_update = Instant::now();
     }
 }
 
@@ -237,6 +292,8 @@ fn ui(f: &mut Frame, app: &mut TuiApp) {
             Constraint::Length(3), // Header
             Constraint::Length(7), // Stats
             Constraint::Min(10),   // Task list
+            Constraint::Length(7), // Top blocking tasks
+            Constraint::Length(8), // Recent events
             Constraint::Length(3), // Footer
         ])
         .split(f.size());
@@ -244,7 +301,9 @@ fn ui(f: &mut Frame, app: &mut TuiApp) {
     draw_header(f, chunks[0], app);
     draw_stats(f, chunks[1], app);
     draw_tasks(f, chunks[2], app);
-    draw_footer(f, chunks[3], app);
+    draw_blocking(f, chunks[3], app);
+    draw_events(f, chunks[4], app);
+    draw_footer(f, chunks[5], app);
 }
 
 /// Draw header
@@ -311,6 +370,20 @@ fn draw_stats(f: &mut Frame, area: Rect, app: &TuiApp) {
                 Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
             ),
             Span::raw("  "),
+            Span::styled("Cancelled: ", Style::default().fg(Color::Magenta)),
+            Span::styled(
+                format!("{}", stats.cancelled_tasks),
+                Style::default()
+                    .fg(Color::Magenta)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw("  "),
+            Span::styled("Lost wakeups: ", Style::default().fg(Color::Red)),
+            Span::styled(
+                format!("{}", stats.lost_wakeups),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ),
+            Span::raw("  "),
             Span::styled("Events: ", Style::default().fg(Color::Gray)),
             Span::styled(
                 format!("{}", stats.total_events),
@@ -323,6 +396,20 @@ fn draw_stats(f: &mut Frame, area: Rect, app: &TuiApp) {
                 format!("{:.2}s", stats.timeline_duration.as_secs_f64()),
                 Style::default().fg(Color::Cyan),
             ),
+            Span::raw("  "),
+            Span::styled("Long polls: ", Style::default().fg(Color::LightRed)),
+            Span::styled(
+                format!("{}", stats.long_polls),
+                Style::default()
+                    .fg(Color::LightRed)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw("  "),
+            Span::styled("Deadlocks: ", Style::default().fg(Color::Red)),
+            Span::styled(
+                format!("{}", stats.active_deadlocks),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ),
         ]),
     ];
 
@@ -336,25 +423,40 @@ fn draw_stats(f: &mut Frame, area: Rect, app: &TuiApp) {
 /// Draw task list
 fn draw_tasks(f: &mut Frame, area: Rect, app: &TuiApp) {
     let tasks = app.get_tasks();
+    let deadlocked: HashSet<TaskId> = app
+        .inspector
+        .active_deadlocks()
+        .iter()
+        .flat_map(|cycle| cycle.tasks.iter().copied())
+        .collect();
 
     let rows: Vec<Row> = tasks
         .iter()
         .enumerate()
         .map(|(i, task)| {
+            let lost_wakeup = app.lost_wakeups.contains(&task.id);
+            let deadlock = deadlocked.contains(&task.id);
+
             let state_color = match task.state {
+                _ if deadlock || lost_wakeup => Color::Red,
                 TaskState::Pending => Color::Gray,
                 TaskState::Running => Color::Blue,
                 TaskState::Blocked { .. } => Color::Yellow,
                 TaskState::Completed => Color::Green,
-                TaskState::Failed => Color::Red,
+                TaskState::Failed { .. } | TaskState::Panicked { .. } => Color::Red,
+                TaskState::Cancelled => Color::Magenta,
             };
 
             let state_str = match &task.state {
+                _ if deadlock => "DEADLOCKED",
+                _ if lost_wakeup => "LOST WAKEUP",
                 TaskState::Pending => "PENDING",
                 TaskState::Running => "RUNNING",
                 TaskState::Blocked { .. } => "BLOCKED",
                 TaskState::Completed => "DONE",
-                TaskState::Failed => "FAILED",
+                TaskState::Failed { .. } => "FAILED",
+                TaskState::Panicked { .. } => "PANICKED",
+                TaskState::Cancelled => "CANCELLED",
             };
 
             let style = if i == app.selected {
@@ -388,7 +490,7 @@ fn draw_tasks(f: &mut Frame, area: Rect, app: &TuiApp) {
         [
             Constraint::Length(8),  // ID
             Constraint::Min(20),    // Name
-            Constraint::Length(10), // State
+            Constraint::Length(12), // State
             Constraint::Length(12), // Duration
             Constraint::Length(8),  // Polls
             Constraint::Length(12), // Run Time
@@ -409,6 +511,70 @@ fn draw_tasks(f: &mut Frame, area: Rect, app: &TuiApp) {
     f.render_widget(table, area);
 }
 
+/// Draw the tasks that blocked the executor the longest
+fn draw_blocking(f: &mut Frame, area: Rect, app: &TuiApp) {
+    let rows: Vec<Row> = app
+        .top_blocking
+        .iter()
+        .map(|m| {
+            Row::new(vec![
+                format!("#{}", m.task_id.as_u64()),
+                format!("{:.20}", m.name),
+                format!("{}", m.long_polls),
+                format!("{:.2}ms", m.blocking_time.as_secs_f64() * 1000.0),
+                format!("{:.2}ms", m.max_long_poll.as_secs_f64() * 1000.0),
+            ])
+            .fg(Color::Yellow)
+        })
+        .collect();
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(8),  // ID
+            Constraint::Min(20),    // Name
+            Constraint::Length(12), // Long polls
+            Constraint::Length(12), // Blocking
+            Constraint::Length(12), // Longest
+        ],
+    )
+    .header(
+        Row::new(vec!["ID", "Name", "Long Polls", "Blocking", "Longest"]).style(
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        ),
+    )
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Top Blocking Tasks"),
+    );
+
+    f.render_widget(table, area);
+}
+
+/// Draw the most recent events
+fn draw_events(f: &mut Frame, area: Rect, app: &TuiApp) {
+    let visible = usize::from(area.height.saturating_sub(2));
+    let lines: Vec<Line> = app
+        .recent_events
+        .iter()
+        .skip(app.recent_events.len().saturating_sub(visible))
+        .map(|e| Line::from(Span::styled(e.clone(), Style::default().fg(Color::Gray))))
+        .collect();
+
+    let events = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Recent Events"),
+        )
+        .style(Style::default());
+
+    f.render_widget(events, area);
+}
+
 /// Draw footer with help hint
 fn draw_footer(f: &mut Frame, area: Rect, _app: &TuiApp) {
     let help_text = vec![Line::from(vec![
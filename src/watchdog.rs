@@ -0,0 +1,334 @@
+//! Stuck-task watchdog
+//!
+//! A [`Watchdog`] periodically scans an [`Inspector`] for tasks that stay
+//! blocked at the same await point for longer than a stall timeout. Each
+//! stall is recorded as an [`EventKind::TaskStalled`] event and handed to the
+//! registered callbacks, together with the task's recent events and the chain
+//! of tasks that spawned or awaited it.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use async_inspect::inspector::Inspector;
+//! use async_inspect::watchdog::Watchdog;
+//! use std::time::Duration;
+//!
+//! let handle = Watchdog::new(Inspector::global().clone())
+//!     .with_timeout(Duration::from_secs(5))
+//!     .with_task_timeout("flush_batch", Duration::from_secs(30))
+//!     .on_stall(|alert| eprintln!("{alert}"))
+//!     .spawn_thread();
+//! ```
+
+use crate::background::MonitorHandle;
+use crate::inspector::Inspector;
+use crate::task::{TaskId, TaskInfo};
+use crate::timeline::{Event, EventKind};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Callback invoked for each stalled task
+type StallCallback = Box<dyn Fn(&StallAlert) + Send + Sync>;
+
+/// A task blocked at one await point for longer than its stall timeout
+#[derive(Debug, Clone)]
+pub struct StallAlert {
+    /// The stalled task, as of the scan
+    pub task: TaskInfo,
+    /// Await point the task is blocked on
+    pub await_point: String,
+    /// How long the task has been blocked there
+    pub stalled_for: Duration,
+    /// Timeout the task exceeded
+    pub timeout: Duration,
+    /// Most recent events of the task, oldest first
+    pub recent_events: Vec<Event>,
+    /// Ancestors of the task, nearest parent first
+    pub parent_chain: Vec<TaskInfo>,
+}
+
+impl fmt::Display for StallAlert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} stalled at {} for {:.2}s (timeout {:.2}s)",
+            self.task.id,
+            self.task.name,
+            self.await_point,
+            self.stalled_for.as_secs_f64(),
+            self.timeout.as_secs_f64()
+        )?;
+
+        for parent in &self.parent_chain {
+            write!(f, "\n  in {} {}", parent.id, parent.name)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Periodic scanner for stalled tasks
+///
+/// A task is checked against the timeout configured for its name, falling
+/// back to the global timeout; tasks with neither are never flagged. Each
+/// stall is reported once, until the task moves on and blocks again.
+pub struct Watchdog {
+    inspector: Inspector,
+    timeout: Option<Duration>,
+    task_timeouts: HashMap<String, Duration>,
+    interval: Duration,
+    history: usize,
+    callbacks: Vec<StallCallback>,
+    /// Stalls already reported, keyed by task, with the time the task started
+    /// waiting
+    reported: HashMap<TaskId, Instant>,
+}
+
+impl Watchdog {
+    /// Create a watchdog for an inspector, with no timeouts configured
+    pub fn new(inspector: Inspector) -> Self {
+        Self {
+            inspector,
+            timeout: None,
+            task_timeouts: HashMap::new(),
+            interval: Duration::from_secs(1),
+            history: 20,
+            callbacks: Vec::new(),
+            reported: HashMap::new(),
+        }
+    }
+
+    /// Set the stall timeout applied to every task
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the stall timeout for tasks with a given name
+    pub fn with_task_timeout(mut self, name: impl Into<String>, timeout: Duration) -> Self {
+        self.task_timeouts.insert(name.into(), timeout);
+        self
+    }
+
+    /// Set how often the background watchdog scans (default 1s)
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Set how many recent events an alert carries (default 20)
+    pub fn with_history(mut self, events: usize) -> Self {
+        self.history = events;
+        self
+    }
+
+    /// Register a callback invoked for each stalled task
+    pub fn on_stall(mut self, callback: impl Fn(&StallAlert) + Send + Sync + 'static) -> Self {
+        self.callbacks.push(Box::new(callback));
+        self
+    }
+
+    /// Timeout that applies to a task, if any
+    fn timeout_for(&self, task: &TaskInfo) -> Option<Duration> {
+        self.task_timeouts.get(&task.name).copied().or(self.timeout)
+    }
+
+    /// Scan once, reporting tasks that stalled since the last scan
+    pub fn check(&mut self) -> Vec<StallAlert> {
+        let tasks = self.inspector.get_all_tasks();
+        let mut blocked = HashSet::new();
+        let mut alerts = Vec::new();
+
+        for task in &tasks {
+            // Measured from when the wait started, so a task woken and
+            // polled again without getting past its await point still stalls
+            let (Some(await_point), Some(since)) = (&task.await_point, task.await_since) else {
+                continue;
+            };
+            if task.state.is_terminal() {
+                continue;
+            }
+            blocked.insert(task.id);
+
+            let Some(timeout) = self.timeout_for(task) else {
+                continue;
+            };
+            let stalled_for = since.elapsed();
+            if stalled_for < timeout || self.reported.get(&task.id) == Some(&since) {
+                continue;
+            }
+            self.reported.insert(task.id, since);
+
+            alerts.push(StallAlert {
+                task: task.clone(),
+                await_point: await_point.clone(),
+                stalled_for,
+                timeout,
+                recent_events: self.recent_events(task.id),
+                parent_chain: Self::parent_chain(&tasks, task),
+            });
+        }
+
+        // Tasks that moved on may stall again later
+        self.reported.retain(|id, _| blocked.contains(id));
+
+        for alert in &alerts {
+            self.inspector.add_event(
+                alert.task.id,
+                EventKind::TaskStalled {
+                    await_point: alert.await_point.clone(),
+                    duration: alert.stalled_for,
+                },
+            );
+            for callback in &self.callbacks {
+                callback(alert);
+            }
+        }
+
+        alerts
+    }
+
+    /// Last events of a task, oldest first
+    fn recent_events(&self, task_id: TaskId) -> Vec<Event> {
+        let mut events = self.inspector.get_task_events(task_id);
+        let skip = events.len().saturating_sub(self.history);
+        events.drain(..skip);
+        events
+    }
+
+    /// Ancestors of a task, nearest first
+    fn parent_chain(tasks: &[TaskInfo], task: &TaskInfo) -> Vec<TaskInfo> {
+        let mut chain: Vec<TaskInfo> = Vec::new();
+        let mut next = task.parent;
+
+        while let Some(parent_id) = next {
+            // Guard against cycles in malformed parent links
+            if parent_id == task.id || chain.iter().any(|t| t.id == parent_id) {
+                break;
+            }
+            let Some(parent) = tasks.iter().find(|t| t.id == parent_id) else {
+                break;
+            };
+            next = parent.parent;
+            chain.push(parent.clone());
+        }
+
+        chain
+    }
+
+    /// Run the watchdog on a dedicated thread until the handle is stopped
+    ///
+    /// # Panics
+    ///
+    /// Panics if the operating system fails to create the thread.
+    pub fn spawn_thread(mut self) -> MonitorHandle {
+        let interval = self.interval;
+        MonitorHandle::spawn("async-inspect-watchdog", interval, move || {
+            self.check();
+        })
+    }
+
+    /// Run the watchdog as a Tokio task until it is aborted
+    #[cfg(feature = "tokio")]
+    pub fn spawn_tokio(mut self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(self.interval);
+            loop {
+                interval.tick().await;
+                self.check();
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use std::sync::mpsc;
+
+    fn blocked_task(inspector: &Inspector, name: &str, parent: Option<TaskId>) -> TaskId {
+        let task_id = match parent {
+            Some(parent) => inspector.register_child_task(name.to_string(), parent),
+            None => inspector.register_task(name.to_string()),
+        };
+        inspector.await_started(task_id, format!("{name}::await#1"), None);
+        task_id
+    }
+
+    #[test]
+    fn test_stalled_tasks_are_reported_once() {
+        let inspector = Inspector::with_config(Config::new());
+        let root = inspector.register_task("root".to_string());
+        let stuck = blocked_task(&inspector, "stuck", Some(root));
+        blocked_task(&inspector, "patient", None);
+
+        let mut watchdog = Watchdog::new(inspector.clone())
+            .with_timeout(Duration::ZERO)
+            .with_task_timeout("patient", Duration::from_secs(3600));
+
+        let alerts = watchdog.check();
+        assert_eq!(alerts.len(), 1);
+        let alert = &alerts[0];
+        assert_eq!(alert.task.id, stuck);
+        assert_eq!(alert.await_point, "stuck::await#1");
+        assert_eq!(alert.parent_chain.len(), 1);
+        assert_eq!(alert.parent_chain[0].id, root);
+        assert!(alert
+            .recent_events
+            .iter()
+            .any(|e| matches!(e.kind, EventKind::AwaitStarted { .. })));
+
+        assert!(inspector
+            .get_task_events(stuck)
+            .iter()
+            .any(|e| matches!(e.kind, EventKind::TaskStalled { .. })));
+
+        // Still the same stall, even when polled again without moving on
+        inspector.poll_started(stuck);
+        inspector.poll_ended(stuck, Duration::from_millis(1));
+        assert!(watchdog.check().is_empty());
+
+        // Blocking again is a new stall
+        inspector.await_started(stuck, "stuck::await#2".to_string(), None);
+        assert_eq!(watchdog.check().len(), 1);
+    }
+
+    #[test]
+    fn test_spinning_tasks_are_reported() {
+        let inspector = Inspector::with_config(Config::new());
+        let spinning = blocked_task(&inspector, "spinning", None);
+        let mut watchdog = Watchdog::new(inspector.clone()).with_timeout(Duration::from_millis(20));
+
+        // Woken and polled again and again, never getting past the await
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_millis(30) {
+            inspector.poll_started(spinning);
+            inspector.poll_ended(spinning, Duration::from_micros(10));
+        }
+
+        let alerts = watchdog.check();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].task.id, spinning);
+        assert!(alerts[0].stalled_for >= Duration::from_millis(20));
+    }
+
+    #[test]
+    fn test_watchdog_thread_fires_callbacks() {
+        let inspector = Inspector::with_config(Config::new());
+        let stuck = blocked_task(&inspector, "stuck", None);
+
+        let (tx, rx) = mpsc::channel();
+        let handle = Watchdog::new(inspector)
+            .with_timeout(Duration::ZERO)
+            .with_interval(Duration::from_millis(5))
+            .on_stall(move |alert| {
+                let _ = tx.send(alert.task.id);
+            })
+            .spawn_thread();
+
+        assert_eq!(rx.recv_timeout(Duration::from_secs(5)).unwrap(), stuck);
+        handle.stop();
+    }
+}